@@ -0,0 +1,224 @@
+//! Select the Microsoft Entra authority host used to request tokens.
+//!
+//! Every credential and helper in this crate that talks to Microsoft Entra ID
+//! accepts an optional [`AuthorityHost`].  When none is given, the
+//! `AZURE_AUTHORITY_HOST` environment variable is used, and otherwise the
+//! public cloud.  `AZURE_AUTHORITY_HOST` may be an `https` URL such as
+//! `https://login.chinacloudapi.cn` or one of the cloud names used by the Azure
+//! CLI and VS Code, such as `AzureUSGovernment`.
+//!
+//! Credentials from the upstream `azure_identity` crate are configured through
+//! the `cloud` field of their [`ClientOptions`], which can be built with
+//! [`AuthorityHost::cloud_configuration`].
+
+use azure_core::{
+    cloud::{CloudConfiguration, CustomConfiguration},
+    error::{Error, ErrorKind},
+    http::{ClientOptions, Url},
+};
+use std::{env, fmt, str::FromStr, sync::Arc};
+
+pub(crate) const AZURE_AUTHORITY_HOST: &str = "AZURE_AUTHORITY_HOST";
+
+const PUBLIC_HOST: &str = "login.microsoftonline.com";
+const CHINA_HOST: &str = "login.chinacloudapi.cn";
+const US_GOVERNMENT_HOST: &str = "login.microsoftonline.us";
+
+/// The Microsoft Entra authority host of an Azure cloud.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AuthorityHost {
+    /// The Azure public cloud, `https://login.microsoftonline.com`.
+    #[default]
+    Public,
+    /// Azure operated by 21Vianet in China, `https://login.chinacloudapi.cn`.
+    China,
+    /// Azure US Government, `https://login.microsoftonline.us`.
+    USGovernment,
+    /// Any other authority host, such as that of an Azure Stack deployment.
+    Custom(Url),
+}
+
+impl AuthorityHost {
+    /// Read `AZURE_AUTHORITY_HOST`, defaulting to the public cloud when it is
+    /// not set.
+    pub fn from_env() -> azure_core::Result<Self> {
+        env::var(AZURE_AUTHORITY_HOST)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .map_or(Ok(Self::Public), |value| value.parse())
+    }
+
+    /// Use `host` when given, otherwise read `AZURE_AUTHORITY_HOST`.
+    pub(crate) fn or_from_env(host: Option<Self>) -> azure_core::Result<Self> {
+        host.map_or_else(Self::from_env, Ok)
+    }
+
+    /// Map a cloud name, as used by the `azure.cloud` VS Code setting and the
+    /// Azure CLI, to its authority host.
+    #[must_use]
+    pub fn from_cloud_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "azurecloud" | "azurepubliccloud" => Some(Self::Public),
+            "azurechinacloud" => Some(Self::China),
+            "azureusgovernment" | "azureusgovernmentcloud" => Some(Self::USGovernment),
+            _ => None,
+        }
+    }
+
+    /// The URL of the authority host.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Public => "https://login.microsoftonline.com",
+            Self::China => "https://login.chinacloudapi.cn",
+            Self::USGovernment => "https://login.microsoftonline.us",
+            Self::Custom(url) => url.as_str(),
+        }
+    }
+
+    /// The `oauth2/v2.0` endpoint `name`, such as `token`, for `tenant_id`.
+    pub(crate) fn endpoint(&self, tenant_id: &str, name: &str) -> azure_core::Result<Url> {
+        Ok(Url::parse(&format!(
+            "{}/{tenant_id}/oauth2/v2.0/{name}",
+            self.as_str().trim_end_matches('/')
+        ))?)
+    }
+
+    /// The equivalent configuration for credentials from `azure_identity`.
+    #[must_use]
+    pub fn cloud_configuration(&self) -> CloudConfiguration {
+        match self {
+            Self::Public => CloudConfiguration::AzurePublic,
+            Self::China => CloudConfiguration::AzureChina,
+            Self::USGovernment => CloudConfiguration::AzureGovernment,
+            Self::Custom(url) => {
+                let mut custom = CustomConfiguration::default();
+                url.as_str()
+                    .trim_end_matches('/')
+                    .clone_into(&mut custom.authority_host);
+                custom.into()
+            }
+        }
+    }
+
+    /// Set the cloud of `options` to this authority host, unless one is
+    /// already configured.
+    pub(crate) fn apply_to(&self, options: &mut ClientOptions) {
+        if options.cloud.is_none() {
+            options.cloud = Some(Arc::new(self.cloud_configuration()));
+        }
+    }
+}
+
+impl FromStr for AuthorityHost {
+    type Err = Error;
+
+    /// Parse an `https` URL or a cloud name.
+    fn from_str(value: &str) -> azure_core::Result<Self> {
+        if let Some(host) = Self::from_cloud_name(value) {
+            return Ok(host);
+        }
+
+        let url = Url::parse(value).map_err(|error| {
+            Error::with_error(
+                ErrorKind::Credential,
+                error,
+                format!("invalid authority host '{value}'"),
+            )
+        })?;
+        if url.scheme() != "https" {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                format!("authority host '{value}' doesn't use the https scheme"),
+            ));
+        }
+
+        let well_known = url.path().trim_matches('/').is_empty() && url.port().is_none();
+        Ok(match url.host_str() {
+            Some(PUBLIC_HOST) if well_known => Self::Public,
+            Some(CHINA_HOST) if well_known => Self::China,
+            Some(US_GOVERNMENT_HOST) if well_known => Self::USGovernment,
+            _ => Self::Custom(url),
+        })
+    }
+}
+
+impl fmt::Display for AuthorityHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str().trim_end_matches('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_urls_and_cloud_names() -> azure_core::Result<()> {
+        assert_eq!(
+            "https://login.microsoftonline.com/".parse::<AuthorityHost>()?,
+            AuthorityHost::Public
+        );
+        assert_eq!(
+            "https://login.chinacloudapi.cn".parse::<AuthorityHost>()?,
+            AuthorityHost::China
+        );
+        assert_eq!(
+            "AzureUSGovernment".parse::<AuthorityHost>()?,
+            AuthorityHost::USGovernment
+        );
+
+        let custom = "https://login.contoso.local/adfs".parse::<AuthorityHost>()?;
+        assert!(matches!(custom, AuthorityHost::Custom(_)));
+        assert_eq!(custom.to_string(), "https://login.contoso.local/adfs");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_insecure_and_invalid_hosts() {
+        assert!(
+            "http://login.contoso.local"
+                .parse::<AuthorityHost>()
+                .is_err()
+        );
+        assert!("not a url".parse::<AuthorityHost>().is_err());
+    }
+
+    #[test]
+    fn builds_endpoints() -> azure_core::Result<()> {
+        assert_eq!(
+            AuthorityHost::USGovernment
+                .endpoint("fake-tenant", "token")?
+                .as_str(),
+            "https://login.microsoftonline.us/fake-tenant/oauth2/v2.0/token"
+        );
+        assert_eq!(
+            "https://login.contoso.local/"
+                .parse::<AuthorityHost>()?
+                .endpoint("fake-tenant", "devicecode")?
+                .as_str(),
+            "https://login.contoso.local/fake-tenant/oauth2/v2.0/devicecode"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn maps_to_cloud_configuration() -> azure_core::Result<()> {
+        assert_eq!(
+            AuthorityHost::China.cloud_configuration(),
+            CloudConfiguration::AzureChina
+        );
+        let CloudConfiguration::Custom(custom) = "https://login.contoso.local/"
+            .parse::<AuthorityHost>()?
+            .cloud_configuration()
+        else {
+            return Err(Error::with_message(
+                ErrorKind::Other,
+                "expected a custom cloud",
+            ));
+        };
+        assert_eq!(custom.authority_host, "https://login.contoso.local");
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+//! Authenticate an Azure Pipelines job through a workload identity federation
+//! service connection.
+//!
+//! Azure Pipelines does not write an ID token to a file.  Instead, a job can
+//! request one for a service connection from the URL in
+//! `SYSTEM_OIDCREQUESTURI`, authorized by the job's `SYSTEM_ACCESSTOKEN`.
+//! [`AzurePipelinesCredential`] requests that token and redeems it as a client
+//! assertion for the service connection's app registration or managed
+//! identity.
+//!
+//! `SYSTEM_ACCESSTOKEN` is not exposed to scripts by default.  Map it into the
+//! task's environment:
+//!
+//! ```yaml
+//! - script: cargo run
+//!   env:
+//!     SYSTEM_ACCESSTOKEN: $(System.AccessToken)
+//! ```
+
+use crate::{
+    authority_host::AuthorityHost,
+    client_assertion_credential::{ClientAssertionCredential, ClientAssertionCredentialOptions},
+};
+use azure_core::{
+    credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::{ClientOptions, Context, Method, Pipeline, PipelineSendOptions, Request, Url, headers},
+    json::from_json,
+};
+use serde::Deserialize;
+use std::{env, sync::Arc};
+
+const SYSTEM_ACCESSTOKEN: &str = "SYSTEM_ACCESSTOKEN";
+const SYSTEM_OIDCREQUESTURI: &str = "SYSTEM_OIDCREQUESTURI";
+
+/// The version of the Azure DevOps OIDC token API.
+const OIDC_API_VERSION: &str = "7.1";
+
+/// Options for constructing an [`AzurePipelinesCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct AzurePipelinesCredentialOptions {
+    /// Options for the HTTP pipeline used to request OIDC tokens and tokens.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OidcTokenResponse {
+    oidc_token: Option<Secret>,
+}
+
+#[derive(Debug)]
+/// Enables authentication to Azure Active Directory from an Azure Pipelines
+/// job using a workload identity federation service connection.
+pub struct AzurePipelinesCredential {
+    credential: Arc<ClientAssertionCredential>,
+}
+
+impl AzurePipelinesCredential {
+    /// Create a new `AzurePipelinesCredential` for the service connection
+    /// `service_connection_id`, from `SYSTEM_OIDCREQUESTURI` and
+    /// `SYSTEM_ACCESSTOKEN`.
+    pub fn new<T, C>(
+        tenant_id: T,
+        client_id: C,
+        service_connection_id: &str,
+        options: Option<AzurePipelinesCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+    {
+        Self::from_lookup(
+            tenant_id,
+            client_id,
+            service_connection_id,
+            options,
+            |name| env::var(name).ok(),
+        )
+    }
+
+    /// Create a new `AzurePipelinesCredential` that requests OIDC tokens from
+    /// `request_uri` with `access_token`, instead of reading them from the
+    /// environment.
+    pub fn with_oidc_request<T, C>(
+        tenant_id: T,
+        client_id: C,
+        service_connection_id: &str,
+        request_uri: &str,
+        access_token: Secret,
+        options: Option<AzurePipelinesCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+    {
+        if service_connection_id.is_empty() {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                "AzurePipelinesCredential requires a service connection ID",
+            ));
+        }
+        let AzurePipelinesCredentialOptions {
+            client_options,
+            authority_host,
+        } = options.unwrap_or_default();
+        let mut request_uri = Url::parse(request_uri).map_err(|error| {
+            Error::with_error(
+                ErrorKind::Credential,
+                error,
+                format!("{SYSTEM_OIDCREQUESTURI} is not a valid URL"),
+            )
+        })?;
+        request_uri
+            .query_pairs_mut()
+            .append_pair("api-version", OIDC_API_VERSION)
+            .append_pair("serviceConnectionId", service_connection_id);
+
+        let pipeline_options = client_options.clone();
+        let credential = ClientAssertionCredential::new(
+            tenant_id,
+            client_id,
+            move || {
+                request_oidc_token(
+                    pipeline_options.clone(),
+                    request_uri.clone(),
+                    access_token.clone(),
+                )
+            },
+            Some(ClientAssertionCredentialOptions {
+                client_options,
+                authority_host,
+            }),
+        )?;
+        Ok(Arc::new(Self { credential }))
+    }
+
+    fn from_lookup<T, C>(
+        tenant_id: T,
+        client_id: C,
+        service_connection_id: &str,
+        options: Option<AzurePipelinesCredentialOptions>,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+    {
+        let variable = |name, remediation| {
+            lookup(name)
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| {
+                    Error::with_message(
+                        ErrorKind::Credential,
+                        format!("{name} is not set. {remediation}"),
+                    )
+                })
+        };
+        let request_uri = variable(
+            SYSTEM_OIDCREQUESTURI,
+            "AzurePipelinesCredential must run in an Azure Pipelines job",
+        )?;
+        let access_token = variable(
+            SYSTEM_ACCESSTOKEN,
+            "Map it into the task's environment with `SYSTEM_ACCESSTOKEN: $(System.AccessToken)`",
+        )?;
+        Self::with_oidc_request(
+            tenant_id,
+            client_id,
+            service_connection_id,
+            &request_uri,
+            Secret::new(access_token),
+            options,
+        )
+    }
+}
+
+async fn request_oidc_token(
+    client_options: ClientOptions,
+    url: Url,
+    access_token: Secret,
+) -> azure_core::Result<String> {
+    let pipeline = Pipeline::new(None, None, client_options, vec![], vec![], None);
+    let mut request = Request::new(url, Method::Post);
+    request.insert_header(
+        headers::AUTHORIZATION,
+        format!("Bearer {}", access_token.secret()),
+    );
+    request.insert_header(headers::CONTENT_TYPE, "application/json");
+    request.set_body("{}");
+
+    let response = pipeline
+        .send(
+            &Context::new(),
+            &mut request,
+            Some(PipelineSendOptions {
+                skip_checks: true,
+                ..PipelineSendOptions::default()
+            }),
+        )
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            format!(
+                "AzurePipelinesCredential failed to get an OIDC token with status {status}. Check that the service connection ID is correct and that the pipeline is authorized to use it"
+            ),
+        ));
+    }
+    let response: OidcTokenResponse = from_json(response.into_body())?;
+    response
+        .oidc_token
+        .map(|token| token.secret().to_owned())
+        .ok_or_else(|| {
+            Error::with_message(
+                ErrorKind::Credential,
+                "AzurePipelinesCredential received a response without an oidcToken",
+            )
+        })
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for AzurePipelinesCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.credential.get_token(scopes, options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestServer, http_response};
+
+    const OIDC_TOKEN_BODY: &str = r#"{"oidcToken":"fake-oidc-token"}"#;
+
+    fn credential(
+        pipelines: &TestServer,
+        entra: &TestServer,
+    ) -> azure_core::Result<Arc<AzurePipelinesCredential>> {
+        AzurePipelinesCredential::with_oidc_request(
+            "fake-tenant",
+            "fake-client",
+            "fake-connection",
+            &format!(
+                "{}fake-project/_apis/distributedtask/hubs/build/plans/fake-plan/jobs/fake-job/oidctoken",
+                pipelines.url
+            ),
+            Secret::new("fake-access-token"),
+            Some(AzurePipelinesCredentialOptions {
+                authority_host: Some(entra.authority_host()?),
+                ..AzurePipelinesCredentialOptions::default()
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn exchanges_oidc_token() -> azure_core::Result<()> {
+        let pipelines = TestServer::start(Some(&http_response(
+            "200 OK",
+            "application/json",
+            OIDC_TOKEN_BODY,
+        )))
+        .await?;
+        let entra = TestServer::entra().await?;
+
+        let token = credential(&pipelines, &entra)?
+            .get_token(&["fake-scope"], None)
+            .await?;
+
+        assert_eq!(token.token.secret(), "fake-token");
+        let oidc_requests = pipelines.requests();
+        let oidc_request = oidc_requests
+            .first()
+            .map(|request| request.to_ascii_lowercase())
+            .unwrap_or_default();
+        assert!(oidc_request.starts_with("post /fake-project/_apis/distributedtask/hubs/build/plans/fake-plan/jobs/fake-job/oidctoken?api-version=7.1&serviceconnectionid=fake-connection "));
+        assert!(oidc_request.contains("authorization: bearer fake-access-token"));
+        assert!(oidc_request.contains("content-type: application/json"));
+        assert!(oidc_request.ends_with("\r\n\r\n{}"));
+        let token_requests = entra.requests();
+        let token_request = token_requests
+            .first()
+            .map(String::as_str)
+            .unwrap_or_default();
+        assert!(token_request.starts_with("POST /fake-tenant/oauth2/v2.0/token "));
+        assert!(token_request.contains("client_assertion=fake-oidc-token"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_oidc_token_errors() -> azure_core::Result<()> {
+        let pipelines =
+            TestServer::start(Some(&http_response("401 Unauthorized", "text/plain", ""))).await?;
+        let entra = TestServer::entra().await?;
+
+        let result = credential(&pipelines, &entra)?
+            .get_token(&["fake-scope"], None)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && std::error::Error::source(error)
+                    .is_some_and(|source| source.to_string().contains("401"))
+        ));
+        assert!(entra.requests().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn reports_missing_pipeline_variables() {
+        let result = AzurePipelinesCredential::from_lookup(
+            "fake-tenant",
+            "fake-client",
+            "fake-connection",
+            None,
+            |name| {
+                (name == SYSTEM_OIDCREQUESTURI).then(|| "https://example.com/oidctoken".to_owned())
+            },
+        );
+
+        assert!(matches!(
+            result,
+            Err(ref error) if error.to_string().contains("SYSTEM_ACCESSTOKEN is not set")
+        ));
+    }
+}
@@ -0,0 +1,411 @@
+//! Authenticate using the `Az.Accounts` module of Azure `PowerShell`.
+//!
+//! The credential runs `pwsh`, falling back to Windows `powershell`, and calls
+//! `Get-AzAccessToken` for the account that was signed in with
+//! `Connect-AzAccount`.
+
+use crate::cache::TokenCache;
+use azure_core::{
+    base64,
+    credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    json::from_json,
+};
+use azure_identity::{Executor, new_executor};
+use serde::Deserialize;
+use std::{ffi::OsStr, process::Output, sync::Arc};
+use time::OffsetDateTime;
+use tracing::trace;
+
+const POWERSHELL_CORE: &str = "pwsh";
+const WINDOWS_POWERSHELL: &str = "powershell";
+const NO_AZ_ACCOUNT_MODULE: &str = "NoAzAccountModule";
+const NOT_LOGGED_IN: &str = "Run Connect-AzAccount to login";
+
+#[derive(Deserialize)]
+struct PowerShellTokenResponse {
+    #[serde(rename = "Token")]
+    access_token: Secret,
+    #[serde(rename = "ExpiresOn")]
+    expires_on: i64,
+}
+
+/// Options for constructing an [`AzurePowerShellCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct AzurePowerShellCredentialOptions {
+    /// Identifies the tenant the credential should authenticate in.
+    ///
+    /// Defaults to the tenant of the account selected in Azure `PowerShell`.
+    pub tenant_id: Option<String>,
+
+    /// An implementation of [`Executor`] used to run `PowerShell`.
+    ///
+    /// If `None`, one is created using [`new_executor`].
+    pub executor: Option<Arc<dyn Executor>>,
+}
+
+#[derive(Debug)]
+/// Enables authentication to Azure Active Directory using Azure `PowerShell` to obtain an access token.
+pub struct AzurePowerShellCredential {
+    tenant_id: Option<String>,
+    cache: TokenCache,
+    executor: Arc<dyn Executor>,
+}
+
+impl AzurePowerShellCredential {
+    /// Create a new `AzurePowerShellCredential`.
+    pub fn new(options: Option<AzurePowerShellCredentialOptions>) -> azure_core::Result<Arc<Self>> {
+        let AzurePowerShellCredentialOptions {
+            tenant_id,
+            executor,
+        } = options.unwrap_or_default();
+        if let Some(tenant_id) = &tenant_id {
+            validate_tenant_id(tenant_id)?;
+        }
+
+        Ok(Arc::new(Self {
+            tenant_id,
+            cache: TokenCache::new(),
+            executor: executor.unwrap_or_else(new_executor),
+        }))
+    }
+
+    async fn get_access_token(
+        &self,
+        scopes: &[&str],
+        _options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let [scope] = scopes else {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                "exactly one scope required",
+            ));
+        };
+        let resource = scope_to_resource(scope)?;
+        let command = encode_command(&token_script(resource, self.tenant_id.as_deref()));
+        let args = [
+            OsStr::new("-NoProfile"),
+            OsStr::new("-NonInteractive"),
+            OsStr::new("-EncodedCommand"),
+            OsStr::new(&command),
+        ];
+
+        let output = self.run_powershell(&args).await?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.contains(NO_AZ_ACCOUNT_MODULE) {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                "Az.Accounts module >= 2.2.0 is not installed",
+            ));
+        }
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains(NOT_LOGGED_IN) {
+                return Err(Error::with_message(
+                    ErrorKind::Credential,
+                    "Please run 'Connect-AzAccount' to set up an account",
+                ));
+            }
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                format!("'Get-AzAccessToken' failed: {stderr}"),
+            ));
+        }
+
+        parse_token_response(&stdout)
+    }
+
+    /// Run `pwsh`, falling back to `powershell` when it is not installed.
+    async fn run_powershell(&self, args: &[&OsStr]) -> azure_core::Result<Output> {
+        for program in [POWERSHELL_CORE, WINDOWS_POWERSHELL] {
+            trace!("running {program} to get an Azure PowerShell token");
+            match self.executor.run(OsStr::new(program), args).await {
+                Ok(output) => return Ok(output),
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+                Err(error) => {
+                    return Err(Error::with_error(
+                        ErrorKind::Credential,
+                        error,
+                        format!("unable to run {program}"),
+                    ));
+                }
+            }
+        }
+
+        Err(Error::with_message(
+            ErrorKind::Credential,
+            "PowerShell is not installed",
+        ))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for AzurePowerShellCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.cache
+            .get_token(scopes, options, |s, o| self.get_access_token(s, o))
+            .await
+    }
+}
+
+fn validate_tenant_id(tenant_id: &str) -> azure_core::Result<()> {
+    if tenant_id.is_empty()
+        || !tenant_id
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '.' || c == '-')
+    {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            format!("invalid tenant ID {tenant_id}"),
+        ));
+    }
+    Ok(())
+}
+
+fn scope_to_resource(scope: &str) -> azure_core::Result<&str> {
+    if scope.is_empty()
+        || !scope
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | ':' | '/'))
+    {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            format!("invalid scope {scope}"),
+        ));
+    }
+    Ok(scope.strip_suffix("/.default").unwrap_or(scope))
+}
+
+/// Build the script passed to `PowerShell`.
+///
+/// `resource` and `tenant_id` must already be validated, as they are inserted
+/// into the script verbatim.
+fn token_script(resource: &str, tenant_id: Option<&str>) -> String {
+    let tenant = tenant_id
+        .map(|tenant_id| format!("$params['TenantId'] = '{tenant_id}'\n"))
+        .unwrap_or_default();
+    format!(
+        r"$ErrorActionPreference = 'Stop'
+$module = Import-Module Az.Accounts -MinimumVersion 2.2.0 -PassThru -ErrorAction SilentlyContinue
+if (-not $module) {{
+    Write-Output '{NO_AZ_ACCOUNT_MODULE}'
+    exit
+}}
+$params = @{{ ResourceUrl = '{resource}'; WarningAction = 'Ignore' }}
+{tenant}$token = Get-AzAccessToken @params
+$value = $token.Token
+if ($value -is [System.Security.SecureString]) {{
+    $value = [System.Net.NetworkCredential]::new('', $value).Password
+}}
+$result = @{{ Token = $value; ExpiresOn = $token.ExpiresOn.ToUnixTimeSeconds() }}
+Write-Output (ConvertTo-Json $result -Compress)
+"
+    )
+}
+
+/// Encode a script for `-EncodedCommand`, which expects base64 of UTF-16LE.
+fn encode_command(script: &str) -> String {
+    let bytes = script
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect::<Vec<_>>();
+    base64::encode(bytes)
+}
+
+fn parse_token_response(stdout: &str) -> azure_core::Result<AccessToken> {
+    let line = stdout
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .ok_or_else(|| {
+            Error::with_message(
+                ErrorKind::Credential,
+                "Azure PowerShell did not return a token",
+            )
+        })?;
+    let response: PowerShellTokenResponse = from_json(line)?;
+    let expires_on = OffsetDateTime::from_unix_timestamp(response.expires_on).map_err(|_| {
+        Error::with_message(
+            ErrorKind::DataConversion,
+            format!("unable to parse ExpiresOn '{}'", response.expires_on),
+        )
+    })?;
+    Ok(AccessToken {
+        token: response.access_token,
+        expires_on,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_lock::Mutex;
+    use std::{io, process::ExitStatus};
+
+    #[cfg(unix)]
+    fn exit_status(code: i32) -> ExitStatus {
+        use std::os::unix::process::ExitStatusExt;
+        ExitStatus::from_raw(code << 8)
+    }
+
+    #[cfg(windows)]
+    fn exit_status(code: i32) -> ExitStatus {
+        use std::os::windows::process::ExitStatusExt;
+        ExitStatus::from_raw(code.cast_unsigned())
+    }
+
+    #[derive(Debug)]
+    struct MockExecutor {
+        missing: Vec<&'static str>,
+        code: i32,
+        stdout: String,
+        stderr: String,
+        programs: Mutex<Vec<String>>,
+    }
+
+    impl MockExecutor {
+        fn new(code: i32, stdout: &str, stderr: &str) -> Self {
+            Self {
+                missing: Vec::new(),
+                code,
+                stdout: stdout.to_owned(),
+                stderr: stderr.to_owned(),
+                programs: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Executor for MockExecutor {
+        async fn run(&self, program: &OsStr, _args: &[&OsStr]) -> io::Result<Output> {
+            let program = program.to_string_lossy().to_string();
+            self.programs.lock().await.push(program.clone());
+            if self.missing.contains(&program.as_str()) {
+                return Err(io::Error::from(io::ErrorKind::NotFound));
+            }
+            Ok(Output {
+                status: exit_status(self.code),
+                stdout: self.stdout.clone().into_bytes(),
+                stderr: self.stderr.clone().into_bytes(),
+            })
+        }
+    }
+
+    async fn get_token(executor: Arc<MockExecutor>) -> azure_core::Result<AccessToken> {
+        let credential = AzurePowerShellCredential::new(Some(AzurePowerShellCredentialOptions {
+            tenant_id: None,
+            executor: Some(executor),
+        }))?;
+        credential
+            .get_token(&["https://management.azure.com/.default"], None)
+            .await
+    }
+
+    #[tokio::test]
+    async fn get_token_success() -> azure_core::Result<()> {
+        let executor = Arc::new(MockExecutor::new(
+            0,
+            "WARNING: something\n{\"Token\":\"fake-token\",\"ExpiresOn\":2147483647}\n",
+            "",
+        ));
+
+        let token = get_token(executor.clone()).await?;
+
+        assert_eq!(token.token.secret(), "fake-token");
+        assert_eq!(token.expires_on.unix_timestamp(), 2_147_483_647);
+        assert_eq!(*executor.programs.lock().await, vec![POWERSHELL_CORE]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_windows_powershell() -> azure_core::Result<()> {
+        let executor = Arc::new(MockExecutor {
+            missing: vec![POWERSHELL_CORE],
+            ..MockExecutor::new(0, "{\"Token\":\"fake-token\",\"ExpiresOn\":2147483647}", "")
+        });
+
+        get_token(executor.clone()).await?;
+
+        assert_eq!(
+            *executor.programs.lock().await,
+            vec![POWERSHELL_CORE, WINDOWS_POWERSHELL]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn powershell_not_installed() {
+        let executor = Arc::new(MockExecutor {
+            missing: vec![POWERSHELL_CORE, WINDOWS_POWERSHELL],
+            ..MockExecutor::new(0, "", "")
+        });
+
+        let result = get_token(executor).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+    }
+
+    #[tokio::test]
+    async fn module_not_installed() {
+        let executor = Arc::new(MockExecutor::new(0, NO_AZ_ACCOUNT_MODULE, ""));
+
+        let result = get_token(executor).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("Az.Accounts")
+        ));
+    }
+
+    #[tokio::test]
+    async fn not_logged_in() {
+        let executor = Arc::new(MockExecutor::new(
+            1,
+            "",
+            "Get-AzAccessToken: Run Connect-AzAccount to login.",
+        ));
+
+        let result = get_token(executor).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("Connect-AzAccount")
+        ));
+    }
+
+    #[test]
+    fn script_includes_resource_and_tenant() {
+        let script = token_script("https://management.azure.com", Some("fake-tenant"));
+
+        assert!(script.contains("ResourceUrl = 'https://management.azure.com'"));
+        assert!(script.contains("$params['TenantId'] = 'fake-tenant'"));
+    }
+
+    #[test]
+    fn rejects_invalid_scope_and_tenant() {
+        assert!(scope_to_resource("https://example.com/'; Remove-Item").is_err());
+        assert!(
+            AzurePowerShellCredential::new(Some(AzurePowerShellCredentialOptions {
+                tenant_id: Some(String::from("tenant'")),
+                executor: None,
+            }))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn encode_command_uses_utf16le() {
+        assert_eq!(encode_command("ab"), "YQBiAA==");
+    }
+}
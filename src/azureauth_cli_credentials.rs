@@ -61,6 +61,15 @@ pub enum AzureauthCliMode {
     Web,
 }
 
+/// Options for constructing an [`AzureauthCliCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct AzureauthCliCredentialOptions {
+    /// Authentication modes passed to `azureauth` via `--mode`.
+    pub modes: Vec<AzureauthCliMode>,
+    /// Hint displayed by `azureauth` when prompting the user.
+    pub prompt_hint: Option<String>,
+}
+
 #[derive(Debug)]
 /// Enables authentication to Azure Active Directory using Azure CLI to obtain an access token.
 pub struct AzureauthCliCredential {
@@ -79,11 +88,25 @@ impl AzureauthCliCredential {
         T: Into<String>,
         C: Into<String>,
     {
+        Self::new_with_options(tenant_id, client_id, None)
+    }
+
+    /// Create a new `AzureauthCliCredential` with options.
+    pub fn new_with_options<T, C>(
+        tenant_id: T,
+        client_id: C,
+        options: Option<AzureauthCliCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: Into<String>,
+        C: Into<String>,
+    {
+        let AzureauthCliCredentialOptions { modes, prompt_hint } = options.unwrap_or_default();
         Ok(Arc::new(Self {
             tenant_id: tenant_id.into(),
             client_id: client_id.into(),
-            modes: Vec::new(),
-            prompt_hint: None,
+            modes,
+            prompt_hint,
             cache: TokenCache::new(),
             executor: new_executor(),
         }))
@@ -1,14 +1,18 @@
 // Copyright (c) Microsoft Corporation. All rights reserved.
 // Licensed under the MIT License.
 
+use crate::tenant::RequestedTenant;
 use async_lock::RwLock;
 use azure_core::credentials::{AccessToken, TokenRequestOptions};
 use std::{collections::HashMap, time::Duration};
 use time::OffsetDateTime;
 use tracing::trace;
 
+/// Tokens are cached per requested tenant, if any, and scopes.
+type CacheKey = (Option<String>, Vec<String>);
+
 #[derive(Debug)]
-pub(crate) struct TokenCache(RwLock<HashMap<Vec<String>, AccessToken>>);
+pub(crate) struct TokenCache(RwLock<HashMap<CacheKey, AccessToken>>);
 
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) trait MaybeSend: Send {}
@@ -36,8 +40,11 @@ impl TokenCache {
         F: Future<Output = azure_core::Result<AccessToken>> + MaybeSend,
     {
         let token_cache = self.0.read().await;
-        let scopes_owned = scopes.iter().map(ToString::to_string).collect::<Vec<_>>();
-        if let Some(token) = token_cache.get(&scopes_owned)
+        let key = (
+            RequestedTenant::from_options(options.as_ref()).map(str::to_ascii_lowercase),
+            scopes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        );
+        if let Some(token) = token_cache.get(&key)
             && !should_refresh(token)
         {
             trace!("returning cached token");
@@ -50,7 +57,7 @@ impl TokenCache {
 
         // check again in case another thread refreshed the token while we were
         // waiting on the write lock
-        if let Some(token) = token_cache.get(&scopes_owned)
+        if let Some(token) = token_cache.get(&key)
             && !should_refresh(token)
         {
             trace!("returning token that was updated while waiting on write lock");
@@ -59,7 +66,7 @@ impl TokenCache {
 
         trace!("token cache miss");
         let token = callback(scopes, options).await?;
-        token_cache.insert(scopes_owned, token.clone());
+        token_cache.insert(key, token.clone());
         Ok(token)
     }
 }
@@ -70,7 +77,7 @@ impl Default for TokenCache {
     }
 }
 
-fn should_refresh(token: &AccessToken) -> bool {
+pub(crate) fn should_refresh(token: &AccessToken) -> bool {
     token.expires_on <= OffsetDateTime::now_utc() + Duration::from_mins(5)
 }
 
@@ -156,6 +163,41 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_token_different_tenants() -> azure_core::Result<()> {
+        let resource = &[STORAGE_TOKEN_SCOPE];
+        let expires_on = OffsetDateTime::now_utc() + Duration::from_hours(1);
+        let mock_credential =
+            MockCredential::new(AccessToken::new(Secret::new("test-token"), expires_on));
+        let cache = TokenCache::new();
+        let tenant = |tenant_id: &str| {
+            let mut options = TokenRequestOptions::default();
+            options
+                .method_options
+                .context
+                .insert(RequestedTenant::new(tenant_id));
+            Some(options)
+        };
+
+        let home = cache
+            .get_token(resource, None, |s, o| mock_credential.get_token(s, o))
+            .await?;
+        let other = cache
+            .get_token(resource, tenant("other"), |s, o| {
+                mock_credential.get_token(s, o)
+            })
+            .await?;
+        let other_again = cache
+            .get_token(resource, tenant("OTHER"), |s, o| {
+                mock_credential.get_token(s, o)
+            })
+            .await?;
+
+        assert_ne!(home.token.secret(), other.token.secret());
+        assert_eq!(other.token.secret(), other_again.token.secret());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_refresh_expired_token() -> azure_core::Result<()> {
         let resource = &[STORAGE_TOKEN_SCOPE];
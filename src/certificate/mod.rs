@@ -0,0 +1,289 @@
+//! Loads a client certificate and its RSA private key from PEM or PKCS#12
+//! data, and signs with the key.
+//!
+//! Parsing, decryption and PKCS#12 integrity checks are done by OpenSSL.
+
+use azure_core::{
+    credentials::Secret,
+    error::{Error, ErrorKind},
+};
+use openssl::{
+    error::ErrorStack,
+    hash::MessageDigest,
+    pkcs12::Pkcs12,
+    pkey::{Id, PKey, Private},
+    rsa::Padding,
+    sign::{RsaPssSaltlen, Signer},
+    x509::X509,
+};
+
+/// A certificate with its RSA private key, and any other certificates of its
+/// chain that were in the same file.
+#[derive(Debug)]
+pub struct ClientCertificate {
+    key: PKey<Private>,
+    /// DER-encoded certificates, starting with the one for `key`.
+    chain: Vec<Vec<u8>>,
+    /// The SHA-256 hash of the DER-encoded certificate for `key`.
+    thumbprint_sha256: Vec<u8>,
+}
+
+impl ClientCertificate {
+    /// Load a PEM or PKCS#12 certificate.  The password decrypts a PKCS#12
+    /// archive or an encrypted PEM private key.  The MAC of a PKCS#12 archive
+    /// is verified with the same password.
+    pub fn from_bytes(bytes: &[u8], password: Option<&Secret>) -> azure_core::Result<Self> {
+        let (key, mut certificates) = match std::str::from_utf8(bytes) {
+            Ok(text) if text.contains("-----BEGIN ") => read_pem(bytes, password)?,
+            _ => read_pkcs12(bytes, password)?,
+        };
+
+        if key.id() != Id::RSA {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                "the certificate's private key is not an RSA key",
+            ));
+        }
+
+        let leaf = certificates
+            .iter()
+            .position(|certificate| {
+                certificate
+                    .public_key()
+                    .is_ok_and(|public_key| public_key.public_eq(&key))
+            })
+            .ok_or_else(|| {
+                Error::with_message(
+                    ErrorKind::Credential,
+                    "the certificate file does not contain a certificate for its private key",
+                )
+            })?;
+        let leaf = certificates.remove(leaf);
+        let thumbprint_sha256 = leaf
+            .digest(MessageDigest::sha256())
+            .map_err(|error| credential_error(error, "unable to hash the certificate"))?
+            .to_vec();
+        let chain = std::iter::once(leaf)
+            .chain(certificates)
+            .map(|certificate| certificate.to_der())
+            .collect::<Result<_, _>>()
+            .map_err(|error| credential_error(error, "unable to encode the certificate chain"))?;
+
+        Ok(Self {
+            key,
+            chain,
+            thumbprint_sha256,
+        })
+    }
+
+    /// The DER-encoded certificate for the private key, followed by the rest
+    /// of its chain.
+    pub fn chain(&self) -> &[Vec<u8>] {
+        &self.chain
+    }
+
+    /// The SHA-256 hash of the DER-encoded certificate.
+    pub fn thumbprint_sha256(&self) -> &[u8] {
+        &self.thumbprint_sha256
+    }
+
+    /// Sign `message` with SHA-256 and the given RSA padding, which is either
+    /// PKCS#1 v1.5 or PSS with a salt as long as the digest.
+    pub fn sign(&self, padding: Padding, message: &[u8]) -> azure_core::Result<Vec<u8>> {
+        let sign = || {
+            let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+            signer.set_rsa_padding(padding)?;
+            if padding == Padding::PKCS1_PSS {
+                signer.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+                signer.set_rsa_mgf1_md(MessageDigest::sha256())?;
+            }
+            signer.update(message)?;
+            signer.sign_to_vec()
+        };
+        sign().map_err(|error| {
+            credential_error(error, "unable to sign with the certificate's private key")
+        })
+    }
+
+    #[cfg(test)]
+    pub const fn private_key(&self) -> &PKey<Private> {
+        &self.key
+    }
+}
+
+fn read_pem(
+    bytes: &[u8],
+    password: Option<&Secret>,
+) -> azure_core::Result<(PKey<Private>, Vec<X509>)> {
+    let certificates = X509::stack_from_pem(bytes)
+        .map_err(|error| credential_error(error, "unable to read the PEM certificates"))?;
+    // The callback supplies the password, if any, instead of OpenSSL
+    // prompting on the terminal for an encrypted key.
+    let key = PKey::private_key_from_pem_callback(bytes, |buffer| {
+        let password = password.map_or(&[][..], |password| password.secret().as_bytes());
+        let target = buffer.get_mut(..password.len()).ok_or_else(ErrorStack::get)?;
+        target.copy_from_slice(password);
+        Ok(password.len())
+    })
+    .map_err(|error| {
+        credential_error(
+            error,
+            "unable to read the PEM private key. Check that the file contains one and, if it is encrypted, that the password is correct",
+        )
+    })?;
+    Ok((key, certificates))
+}
+
+fn read_pkcs12(
+    bytes: &[u8],
+    password: Option<&Secret>,
+) -> azure_core::Result<(PKey<Private>, Vec<X509>)> {
+    let parsed = Pkcs12::from_der(bytes)
+        .and_then(|archive| archive.parse2(password.map_or("", Secret::secret)))
+        .map_err(|error| {
+            credential_error(
+                error,
+                "unable to read the PKCS#12 certificate. Check that the password is correct",
+            )
+        })?;
+    let key = parsed.pkey.ok_or_else(|| {
+        Error::with_message(
+            ErrorKind::Credential,
+            "the certificate file does not contain a private key",
+        )
+    })?;
+    let certificates = parsed
+        .cert
+        .into_iter()
+        .chain(parsed.ca.into_iter().flatten())
+        .collect();
+    Ok((key, certificates))
+}
+
+fn credential_error(error: ErrorStack, message: &'static str) -> Error {
+    Error::with_error(ErrorKind::Credential, error, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure_core::base64;
+    use openssl::sign::Verifier;
+
+    const PEM: &str = include_str!("testdata/client.pem");
+    const PKCS1_PEM: &str = include_str!("testdata/client-rsa.pem");
+    const ENCRYPTED_PEM: &str = include_str!("testdata/client-encrypted.pem");
+    const PKCS12: &[u8] = include_bytes!("testdata/client.p12");
+    const PASSWORD: &str = "fixture-password";
+    /// `openssl x509 -outform der | openssl dgst -sha256 -binary` of the
+    /// client certificate, base64url-encoded.
+    const THUMBPRINT: &str = "BOLodeMQqYzgPbLQuU-cp4iPvn1Mc9U40vUSDdRlF_E";
+
+    fn password() -> Secret {
+        Secret::new(PASSWORD)
+    }
+
+    #[test]
+    fn loads_pem_with_chain() -> azure_core::Result<()> {
+        let certificate = ClientCertificate::from_bytes(PEM.as_bytes(), None)?;
+
+        // The CA certificate comes first in the file, but the leaf matching
+        // the private key is placed first in the chain.
+        assert_eq!(certificate.chain().len(), 2);
+        assert_eq!(
+            base64::encode_url_safe(certificate.thumbprint_sha256()),
+            THUMBPRINT
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn loads_pkcs1_and_encrypted_pem_keys() -> azure_core::Result<()> {
+        let pkcs1 = ClientCertificate::from_bytes(PKCS1_PEM.as_bytes(), None)?;
+        let encrypted = ClientCertificate::from_bytes(ENCRYPTED_PEM.as_bytes(), Some(&password()))?;
+
+        assert_eq!(
+            base64::encode_url_safe(pkcs1.thumbprint_sha256()),
+            THUMBPRINT
+        );
+        assert_eq!(
+            base64::encode_url_safe(encrypted.thumbprint_sha256()),
+            THUMBPRINT
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn encrypted_pem_key_requires_password() {
+        let result = ClientCertificate::from_bytes(ENCRYPTED_PEM.as_bytes(), None);
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("password")
+        ));
+    }
+
+    #[test]
+    fn loads_pkcs12_with_chain() -> azure_core::Result<()> {
+        let certificate = ClientCertificate::from_bytes(PKCS12, Some(&password()))?;
+
+        assert_eq!(certificate.chain().len(), 2);
+        assert_eq!(
+            base64::encode_url_safe(certificate.thumbprint_sha256()),
+            THUMBPRINT
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_password_is_a_credential_error() {
+        let result = ClientCertificate::from_bytes(PKCS12, Some(&Secret::new("wrong")));
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("password")
+        ));
+    }
+
+    #[test]
+    fn tampered_pkcs12_fails_mac_verification() {
+        // The archive ends with the MAC digest, an 8-byte salt and the
+        // iteration count, so this flips a byte of the digest.
+        let mut tampered = PKCS12.to_vec();
+        let index = tampered.len() - 20;
+        if let Some(byte) = tampered.get_mut(index) {
+            *byte ^= 0xff;
+        }
+
+        let result = ClientCertificate::from_bytes(&tampered, Some(&password()));
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+    }
+
+    #[test]
+    fn signatures_verify_with_the_certificate_key() -> azure_core::Result<()> {
+        let certificate = ClientCertificate::from_bytes(PEM.as_bytes(), None)?;
+        let message = b"header.claims";
+
+        for padding in [Padding::PKCS1_PSS, Padding::PKCS1] {
+            let signature = certificate.sign(padding, message)?;
+
+            let verify = || {
+                let mut verifier =
+                    Verifier::new(MessageDigest::sha256(), certificate.private_key())?;
+                verifier.set_rsa_padding(padding)?;
+                if padding == Padding::PKCS1_PSS {
+                    verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+                }
+                verifier.verify_oneshot(&signature, message)
+            };
+            assert!(matches!(verify(), Ok(true)));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+//! Authenticate a service principal with a client assertion from a callback.
+//!
+//! A [`ClientAssertionCredential`] calls an async callback for a signed JWT,
+//! such as one issued by an in-house signer or another OIDC identity
+//! provider, and redeems it with the `client_credentials` grant.  The
+//! assertion is reused until shortly before its `exp` claim, and tokens are
+//! cached until they need refreshing.  Assertions without a readable `exp`
+//! claim are requested again for every token.
+
+use crate::{
+    authority_host::AuthorityHost,
+    cache::TokenCache,
+    token_endpoint::{self, CLIENT_ASSERTION_TYPE},
+};
+use async_lock::Mutex;
+use azure_core::{
+    base64,
+    credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::{ClientOptions, Url},
+    json::from_json,
+    time::{Duration, OffsetDateTime},
+};
+use serde::Deserialize;
+use std::{fmt, pin::Pin, sync::Arc};
+use url::form_urlencoded;
+
+/// The audience Microsoft Entra ID expects of federated ID tokens.
+pub(crate) const TOKEN_EXCHANGE_AUDIENCE: &str = "api://AzureADTokenExchange";
+/// How long before its expiry an assertion is requested again.
+const ASSERTION_EXPIRY_MARGIN: Duration = Duration::minutes(1);
+
+type AssertionFuture = Pin<Box<dyn Future<Output = azure_core::Result<String>> + Send>>;
+type AssertionCallback = Box<dyn Fn() -> AssertionFuture + Send + Sync>;
+
+/// Options for constructing a [`ClientAssertionCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct ClientAssertionCredentialOptions {
+    /// Options for the HTTP pipeline used to request tokens.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+}
+
+#[derive(Deserialize)]
+struct AssertionClaims {
+    exp: Option<i64>,
+}
+
+/// Enables authentication to Azure Active Directory using a client assertion
+/// returned by a callback.
+pub struct ClientAssertionCredential {
+    client_id: String,
+    endpoint: Url,
+    client_options: ClientOptions,
+    callback: AssertionCallback,
+    /// The last assertion and when it expires.
+    assertion: Mutex<Option<(Secret, OffsetDateTime)>>,
+    cache: TokenCache,
+}
+
+impl ClientAssertionCredential {
+    /// Create a new `ClientAssertionCredential` that calls `callback` for
+    /// each new assertion.
+    pub fn new<T, C, F, Fut>(
+        tenant_id: T,
+        client_id: C,
+        callback: F,
+        options: Option<ClientAssertionCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = azure_core::Result<String>> + Send + 'static,
+    {
+        let ClientAssertionCredentialOptions {
+            client_options,
+            authority_host,
+        } = options.unwrap_or_default();
+        let endpoint =
+            AuthorityHost::or_from_env(authority_host)?.endpoint(tenant_id.as_ref(), "token")?;
+
+        Ok(Arc::new(Self {
+            client_id: client_id.into(),
+            endpoint,
+            client_options,
+            callback: Box::new(move || Box::pin(callback())),
+            assertion: Mutex::new(None),
+            cache: TokenCache::new(),
+        }))
+    }
+
+    /// The cached assertion, or a new one from the callback.
+    async fn assertion(&self) -> azure_core::Result<Secret> {
+        let mut cached = self.assertion.lock().await;
+        if let Some((assertion, expires_on)) = cached.as_ref()
+            && *expires_on > OffsetDateTime::now_utc() + ASSERTION_EXPIRY_MARGIN
+        {
+            return Ok(assertion.clone());
+        }
+
+        let assertion = (self.callback)().await.map_err(|error| {
+            Error::with_error(
+                ErrorKind::Credential,
+                error,
+                "ClientAssertionCredential failed to get a client assertion",
+            )
+        })?;
+        let assertion = Secret::new(assertion);
+        *cached =
+            assertion_expiry(assertion.secret()).map(|expires_on| (assertion.clone(), expires_on));
+        Ok(assertion)
+    }
+
+    async fn get_access_token(
+        &self,
+        scopes: &[&str],
+        _options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let assertion = self.assertion().await?;
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("client_id", &self.client_id)
+            .append_pair("client_assertion_type", CLIENT_ASSERTION_TYPE)
+            .append_pair("client_assertion", assertion.secret())
+            .append_pair("grant_type", "client_credentials")
+            .append_pair("scope", &scopes.join(" "))
+            .finish();
+
+        token_endpoint::request_token(
+            "ClientAssertionCredential",
+            &self.endpoint,
+            body,
+            &self.client_options,
+        )
+        .await?
+        .map_err(|response| response.into_error("ClientAssertionCredential"))
+    }
+}
+
+/// The `exp` claim of a JWT, if it has one.
+fn assertion_expiry(assertion: &str) -> Option<OffsetDateTime> {
+    let claims = assertion.split('.').nth(1)?;
+    let claims: AssertionClaims = from_json(base64::decode_url_safe(claims).ok()?).ok()?;
+    OffsetDateTime::from_unix_timestamp(claims.exp?).ok()
+}
+
+impl fmt::Debug for ClientAssertionCredential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientAssertionCredential")
+            .field("client_id", &self.client_id)
+            .field("endpoint", &self.endpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for ClientAssertionCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.cache
+            .get_token(scopes, options, |s, o| self.get_access_token(s, o))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestServer, http_response};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const INVALID_CLIENT_BODY: &str =
+        r#"{"error":"invalid_client","error_description":"AADSTS700024: Assertion is not valid."}"#;
+
+    fn jwt(expires_on: OffsetDateTime) -> String {
+        format!(
+            "{}.{}.fake-signature",
+            base64::encode_url_safe(r#"{"alg":"RS256","typ":"JWT"}"#),
+            base64::encode_url_safe(format!(
+                r#"{{"aud":"api://AzureADTokenExchange","exp":{}}}"#,
+                expires_on.unix_timestamp()
+            )),
+        )
+    }
+
+    fn credential(
+        server: &TestServer,
+        expires_on: OffsetDateTime,
+        calls: &Arc<AtomicUsize>,
+    ) -> azure_core::Result<Arc<ClientAssertionCredential>> {
+        let calls = calls.clone();
+        ClientAssertionCredential::new(
+            "fake-tenant",
+            "fake-client",
+            move || {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let assertion = jwt(expires_on);
+                async move { Ok(assertion) }
+            },
+            Some(ClientAssertionCredentialOptions {
+                authority_host: Some(server.authority_host()?),
+                ..ClientAssertionCredentialOptions::default()
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn redeems_and_caches_assertion() -> azure_core::Result<()> {
+        let server = TestServer::entra().await?;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let expires_on = OffsetDateTime::now_utc() + Duration::minutes(10);
+        let credential = credential(&server, expires_on, &calls)?;
+
+        let token = credential.get_token(&["first-scope"], None).await?;
+        credential.get_token(&["first-scope"], None).await?;
+        credential.get_token(&["second-scope"], None).await?;
+
+        assert_eq!(token.token.secret(), "fake-token");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        let request = requests.first().map(String::as_str).unwrap_or_default();
+        assert!(request.starts_with("POST /fake-tenant/oauth2/v2.0/token "));
+        let body = request.split_once("\r\n\r\n").map_or("", |(_, body)| body);
+        let form: Vec<(String, String)> = form_urlencoded::parse(body.as_bytes())
+            .into_owned()
+            .collect();
+        for (name, value) in [
+            ("grant_type", "client_credentials"),
+            ("client_id", "fake-client"),
+            ("client_assertion_type", CLIENT_ASSERTION_TYPE),
+            ("client_assertion", jwt(expires_on).as_str()),
+            ("scope", "first-scope"),
+        ] {
+            assert!(
+                form.contains(&(name.to_owned(), value.to_owned())),
+                "{name}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expiring_assertion_is_requested_again() -> azure_core::Result<()> {
+        let server = TestServer::entra().await?;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let credential = credential(
+            &server,
+            OffsetDateTime::now_utc() + Duration::seconds(30),
+            &calls,
+        )?;
+
+        credential.get_token(&["first-scope"], None).await?;
+        credential.get_token(&["second-scope"], None).await?;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_token_endpoint_errors() -> azure_core::Result<()> {
+        let server = TestServer::start(Some(&http_response(
+            "401 Unauthorized",
+            "application/json",
+            INVALID_CLIENT_BODY,
+        )))
+        .await?;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let credential = credential(
+            &server,
+            OffsetDateTime::now_utc() + Duration::minutes(10),
+            &calls,
+        )?;
+
+        let result = credential.get_token(&["fake-scope"], None).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("AADSTS700024")
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_callback_errors() -> azure_core::Result<()> {
+        let credential = ClientAssertionCredential::new(
+            "fake-tenant",
+            "fake-client",
+            || async { Err(Error::with_message(ErrorKind::Io, "signer unavailable")) },
+            None,
+        )?;
+
+        let result = credential.get_token(&["fake-scope"], None).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,405 @@
+//! Authenticate a service principal with a client certificate.
+//!
+//! The credential signs a client assertion JWT with the certificate's RSA
+//! private key and redeems it with the client credentials grant.  The
+//! certificate is loaded from a PEM file containing the certificate and its
+//! private key, or from a PKCS#12 (`.pfx`/`.p12`) archive, using OpenSSL.
+//! Which encryption algorithms are supported for keys and archives depends on
+//! the OpenSSL build; OpenSSL 3 only supports the legacy RC2 and 3DES
+//! algorithms with its legacy provider.
+//!
+//! Requires the `client_certificate` feature.
+
+use crate::{
+    authority_host::AuthorityHost,
+    cache::TokenCache,
+    certificate::ClientCertificate,
+    token_endpoint::{self, CLIENT_ASSERTION_TYPE},
+};
+use azure_core::{
+    Uuid, base64,
+    credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::{ClientOptions, Url},
+    json::to_json,
+    time::{Duration, OffsetDateTime},
+};
+use openssl::rsa::Padding;
+use serde::Serialize;
+use std::{fs, path::Path, sync::Arc};
+use url::form_urlencoded;
+
+/// How long a client assertion is valid for.
+const ASSERTION_LIFETIME: Duration = Duration::minutes(10);
+
+/// The algorithm used to sign the client assertion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SigningAlgorithm {
+    /// RSASSA-PSS with SHA-256.
+    #[default]
+    Ps256,
+    /// RSASSA-PKCS1-v1_5 with SHA-256.
+    Rs256,
+}
+
+impl SigningAlgorithm {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Ps256 => "PS256",
+            Self::Rs256 => "RS256",
+        }
+    }
+
+    const fn padding(self) -> Padding {
+        match self {
+            Self::Ps256 => Padding::PKCS1_PSS,
+            Self::Rs256 => Padding::PKCS1,
+        }
+    }
+}
+
+/// Options for constructing a [`ClientCertificateCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct ClientCertificateCredentialOptions {
+    /// Options for the HTTP pipeline used to request tokens.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+    /// Decrypts a PKCS#12 archive or an encrypted PEM private key.
+    pub password: Option<Secret>,
+    /// Include the certificate chain in the `x5c` header of the client
+    /// assertion, as required for subject name and issuer authentication.
+    pub send_certificate_chain: bool,
+    /// Defaults to [`SigningAlgorithm::Ps256`].
+    pub signing_algorithm: SigningAlgorithm,
+}
+
+#[derive(Serialize)]
+struct AssertionHeader<'a> {
+    alg: &'a str,
+    typ: &'a str,
+    #[serde(rename = "x5t#S256")]
+    thumbprint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    x5c: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct AssertionClaims<'a> {
+    aud: &'a str,
+    iss: &'a str,
+    sub: &'a str,
+    jti: String,
+    iat: i64,
+    nbf: i64,
+    exp: i64,
+}
+
+#[derive(Debug)]
+/// Enables authentication to Azure Active Directory using a client certificate.
+pub struct ClientCertificateCredential {
+    client_id: String,
+    endpoint: Url,
+    certificate: ClientCertificate,
+    client_options: ClientOptions,
+    send_certificate_chain: bool,
+    signing_algorithm: SigningAlgorithm,
+    cache: TokenCache,
+}
+
+impl ClientCertificateCredential {
+    /// Create a new `ClientCertificateCredential` from PEM or PKCS#12 data.
+    pub fn new<T, C>(
+        tenant_id: T,
+        client_id: C,
+        certificate: &[u8],
+        options: Option<ClientCertificateCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+    {
+        let ClientCertificateCredentialOptions {
+            client_options,
+            authority_host,
+            password,
+            send_certificate_chain,
+            signing_algorithm,
+        } = options.unwrap_or_default();
+        let certificate = ClientCertificate::from_bytes(certificate, password.as_ref())?;
+        let endpoint =
+            AuthorityHost::or_from_env(authority_host)?.endpoint(tenant_id.as_ref(), "token")?;
+
+        Ok(Arc::new(Self {
+            client_id: client_id.into(),
+            endpoint,
+            certificate,
+            client_options,
+            send_certificate_chain,
+            signing_algorithm,
+            cache: TokenCache::new(),
+        }))
+    }
+
+    /// Create a new `ClientCertificateCredential` from a PEM or PKCS#12 file.
+    pub fn from_file<T, C, P>(
+        tenant_id: T,
+        client_id: C,
+        path: P,
+        options: Option<ClientCertificateCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let certificate = fs::read(path).map_err(|error| {
+            Error::with_error(
+                ErrorKind::Credential,
+                error,
+                format!("unable to read certificate {}", path.display()),
+            )
+        })?;
+        Self::new(tenant_id, client_id, &certificate, options)
+    }
+
+    /// Build and sign the client assertion JWT.
+    fn client_assertion(&self) -> azure_core::Result<String> {
+        let header = AssertionHeader {
+            alg: self.signing_algorithm.name(),
+            typ: "JWT",
+            thumbprint: base64::encode_url_safe(self.certificate.thumbprint_sha256()),
+            x5c: self.send_certificate_chain.then(|| {
+                self.certificate
+                    .chain()
+                    .iter()
+                    .map(base64::encode)
+                    .collect()
+            }),
+        };
+        let now = OffsetDateTime::now_utc();
+        let claims = AssertionClaims {
+            aud: self.endpoint.as_str(),
+            iss: &self.client_id,
+            sub: &self.client_id,
+            jti: Uuid::new_v4().to_string(),
+            iat: now.unix_timestamp(),
+            nbf: now.unix_timestamp(),
+            exp: (now + ASSERTION_LIFETIME).unix_timestamp(),
+        };
+
+        let message = format!(
+            "{}.{}",
+            base64::encode_url_safe(to_json(&header)?),
+            base64::encode_url_safe(to_json(&claims)?)
+        );
+        let signature = self
+            .certificate
+            .sign(self.signing_algorithm.padding(), message.as_bytes())?;
+        Ok(format!("{message}.{}", base64::encode_url_safe(signature)))
+    }
+
+    async fn get_access_token(
+        &self,
+        scopes: &[&str],
+        _options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("client_id", &self.client_id)
+            .append_pair("scope", &scopes.join(" "))
+            .append_pair("grant_type", "client_credentials")
+            .append_pair("client_assertion_type", CLIENT_ASSERTION_TYPE)
+            .append_pair("client_assertion", &self.client_assertion()?)
+            .finish();
+
+        token_endpoint::request_token(
+            "ClientCertificateCredential",
+            &self.endpoint,
+            body,
+            &self.client_options,
+        )
+        .await?
+        .map_err(|response| response.into_error("ClientCertificateCredential"))
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for ClientCertificateCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.cache
+            .get_token(scopes, options, |s, o| self.get_access_token(s, o))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockPolicy, TOKEN_BODY};
+    use azure_core::{http::StatusCode, json::from_json};
+    use openssl::{
+        hash::MessageDigest,
+        sign::{RsaPssSaltlen, Verifier},
+    };
+    use serde::Deserialize;
+
+    const PEM: &[u8] = include_bytes!("certificate/testdata/client.pem");
+
+    #[derive(Deserialize)]
+    struct SentHeader {
+        alg: String,
+        #[serde(rename = "x5t#S256")]
+        thumbprint: String,
+        x5c: Option<Vec<String>>,
+    }
+
+    #[derive(Deserialize)]
+    struct SentClaims {
+        aud: String,
+        iss: String,
+        sub: String,
+    }
+
+    fn credential(
+        policy: &Arc<MockPolicy>,
+        send_certificate_chain: bool,
+        signing_algorithm: SigningAlgorithm,
+    ) -> azure_core::Result<Arc<ClientCertificateCredential>> {
+        ClientCertificateCredential::new(
+            "fake-tenant",
+            "fake-client",
+            PEM,
+            Some(ClientCertificateCredentialOptions {
+                client_options: policy.client_options(),
+                authority_host: Some(AuthorityHost::Public),
+                send_certificate_chain,
+                signing_algorithm,
+                ..ClientCertificateCredentialOptions::default()
+            }),
+        )
+    }
+
+    /// Request a token and return the decoded header and claims of the client
+    /// assertion the token endpoint received, after checking its signature.
+    async fn sent_assertion(
+        credential: &ClientCertificateCredential,
+        policy: &MockPolicy,
+        padding: Padding,
+    ) -> azure_core::Result<(SentHeader, SentClaims)> {
+        let token = credential.get_token(&["fake-scope"], None).await?;
+        assert_eq!(token.token.secret(), "fake-token");
+
+        let bodies = policy.bodies();
+        let body = bodies
+            .first()
+            .ok_or_else(|| Error::with_message(ErrorKind::Other, "no token request was sent"))?;
+        let form: Vec<(String, String)> = form_urlencoded::parse(body.as_bytes())
+            .into_owned()
+            .collect();
+        assert!(form.contains(&(
+            String::from("grant_type"),
+            String::from("client_credentials")
+        )));
+        assert!(form.contains(&(
+            String::from("client_assertion_type"),
+            String::from(CLIENT_ASSERTION_TYPE)
+        )));
+        let assertion = form
+            .iter()
+            .find_map(|(name, value)| (name == "client_assertion").then_some(value))
+            .ok_or_else(|| Error::with_message(ErrorKind::Other, "no client assertion"))?;
+
+        let (message, signature) = assertion
+            .rsplit_once('.')
+            .ok_or_else(|| Error::with_message(ErrorKind::Other, "malformed assertion"))?;
+        let signature = base64::decode_url_safe(signature)?;
+        let verify = || {
+            let mut verifier = Verifier::new(
+                MessageDigest::sha256(),
+                credential.certificate.private_key(),
+            )?;
+            verifier.set_rsa_padding(padding)?;
+            if padding == Padding::PKCS1_PSS {
+                verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+            }
+            verifier.verify_oneshot(&signature, message.as_bytes())
+        };
+        if !matches!(verify(), Ok(true)) {
+            return Err(Error::with_message(ErrorKind::Other, "invalid signature"));
+        }
+
+        let (header, claims) = message
+            .split_once('.')
+            .ok_or_else(|| Error::with_message(ErrorKind::Other, "malformed assertion"))?;
+        Ok((
+            from_json(base64::decode_url_safe(header)?)?,
+            from_json(base64::decode_url_safe(claims)?)?,
+        ))
+    }
+
+    #[tokio::test]
+    async fn signs_ps256_assertion_with_thumbprint() -> azure_core::Result<()> {
+        let policy = MockPolicy::new(vec![("/oauth2/v2.0/token", TOKEN_BODY)]);
+        let credential = credential(&policy, false, SigningAlgorithm::Ps256)?;
+
+        let (header, claims) = sent_assertion(&credential, &policy, Padding::PKCS1_PSS).await?;
+
+        assert_eq!(header.alg, "PS256");
+        assert_eq!(
+            header.thumbprint,
+            "BOLodeMQqYzgPbLQuU-cp4iPvn1Mc9U40vUSDdRlF_E"
+        );
+        assert!(header.x5c.is_none());
+        assert_eq!(claims.iss, "fake-client");
+        assert_eq!(claims.sub, "fake-client");
+        assert_eq!(
+            claims.aud,
+            "https://login.microsoftonline.com/fake-tenant/oauth2/v2.0/token"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn signs_rs256_assertion_with_chain() -> azure_core::Result<()> {
+        let policy = MockPolicy::new(vec![("/oauth2/v2.0/token", TOKEN_BODY)]);
+        let credential = credential(&policy, true, SigningAlgorithm::Rs256)?;
+
+        let (header, _) = sent_assertion(&credential, &policy, Padding::PKCS1).await?;
+
+        assert_eq!(header.alg, "RS256");
+        let chain: Vec<String> = credential
+            .certificate
+            .chain()
+            .iter()
+            .map(base64::encode)
+            .collect();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(header.x5c, Some(chain));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn token_endpoint_errors_are_reported() -> azure_core::Result<()> {
+        let policy = MockPolicy::with_status(vec![(
+            "/oauth2/v2.0/token",
+            StatusCode::Unauthorized,
+            r#"{"error":"invalid_client","error_description":"AADSTS700027: The certificate with identifier used to sign the client assertion is not registered on application."}"#,
+        )]);
+        let credential = credential(&policy, false, SigningAlgorithm::Ps256)?;
+
+        let error = credential.get_token(&["fake-scope"], None).await;
+
+        assert!(matches!(
+            error,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("AADSTS700027")
+        ));
+        Ok(())
+    }
+}
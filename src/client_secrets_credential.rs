@@ -0,0 +1,335 @@
+//! Authenticate a service principal with any of several client secrets.
+//!
+//! While an app registration's secret is rotated there is a window in which
+//! either the old or the new secret may be the valid one.  A
+//! [`ClientSecretsCredential`] holds an ordered list of secrets, newest first.
+//! When the token endpoint rejects a secret with `invalid_client`, the next one
+//! is tried, and the secret that was accepted is tried first from then on.  A
+//! warning is logged whenever a secret other than the first is accepted, as a
+//! reminder to finish the rotation.
+
+use crate::{
+    authority_host::AuthorityHost,
+    cache::TokenCache,
+    token_endpoint::{self, TokenErrorResponse},
+};
+use azure_core::{
+    credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::{ClientOptions, Url},
+};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use tracing::{debug, warn};
+use url::form_urlencoded;
+
+/// The OAuth error returned for an invalid or expired client secret.
+const INVALID_CLIENT: &str = "invalid_client";
+
+/// Builds a [`ClientSecretsCredential`].
+#[derive(Debug, Clone)]
+pub struct ClientSecretsCredentialBuilder {
+    tenant_id: String,
+    client_id: String,
+    secrets: Vec<Secret>,
+    client_options: ClientOptions,
+    authority_host: Option<AuthorityHost>,
+}
+
+impl ClientSecretsCredentialBuilder {
+    /// Create a builder for the application `client_id` in `tenant_id`.
+    pub fn new<T, C>(tenant_id: T, client_id: C) -> Self
+    where
+        T: Into<String>,
+        C: Into<String>,
+    {
+        Self {
+            tenant_id: tenant_id.into(),
+            client_id: client_id.into(),
+            secrets: Vec::new(),
+            client_options: ClientOptions::default(),
+            authority_host: None,
+        }
+    }
+
+    #[must_use]
+    /// Add a secret, tried after the ones already added.
+    pub fn with_secret(mut self, secret: Secret) -> Self {
+        self.secrets.push(secret);
+        self
+    }
+
+    #[must_use]
+    /// Add several secrets, in the order they are tried.
+    pub fn with_secrets<I>(mut self, secrets: I) -> Self
+    where
+        I: IntoIterator<Item = Secret>,
+    {
+        self.secrets.extend(secrets);
+        self
+    }
+
+    #[must_use]
+    /// Set the options for the HTTP pipeline used to request tokens.
+    pub fn with_client_options(mut self, client_options: ClientOptions) -> Self {
+        self.client_options = client_options;
+        self
+    }
+
+    #[must_use]
+    /// Set the authority host.  Defaults to `AZURE_AUTHORITY_HOST`, then to
+    /// the public cloud.
+    pub fn with_authority_host(mut self, authority_host: AuthorityHost) -> Self {
+        self.authority_host = Some(authority_host);
+        self
+    }
+
+    /// Build the [`ClientSecretsCredential`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no secrets were added, if any secret is empty, or
+    /// if the authority host is invalid.
+    pub fn build(self) -> azure_core::Result<Arc<ClientSecretsCredential>> {
+        if self.secrets.is_empty() {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                "no client secrets specified",
+            ));
+        }
+        if self.secrets.iter().any(|secret| secret.secret().is_empty()) {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                "client secrets must not be empty",
+            ));
+        }
+        let endpoint =
+            AuthorityHost::or_from_env(self.authority_host)?.endpoint(&self.tenant_id, "token")?;
+
+        Ok(Arc::new(ClientSecretsCredential {
+            client_id: self.client_id,
+            secrets: self.secrets,
+            endpoint,
+            client_options: self.client_options,
+            active: AtomicUsize::new(0),
+            cache: TokenCache::new(),
+        }))
+    }
+}
+
+/// Enables authentication to Azure Active Directory using any of an ordered
+/// list of client secrets.
+#[derive(Debug)]
+pub struct ClientSecretsCredential {
+    client_id: String,
+    secrets: Vec<Secret>,
+    endpoint: Url,
+    client_options: ClientOptions,
+    /// The index of the secret that was last accepted.
+    active: AtomicUsize,
+    cache: TokenCache,
+}
+
+impl ClientSecretsCredential {
+    /// Create a builder for the application `client_id` in `tenant_id`.
+    pub fn builder<T, C>(tenant_id: T, client_id: C) -> ClientSecretsCredentialBuilder
+    where
+        T: Into<String>,
+        C: Into<String>,
+    {
+        ClientSecretsCredentialBuilder::new(tenant_id, client_id)
+    }
+
+    async fn get_access_token(
+        &self,
+        scopes: &[&str],
+        _options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let active = self.active.load(Ordering::Relaxed);
+        let order = std::iter::once(active).chain((0..self.secrets.len()).filter(|i| *i != active));
+
+        let mut rejected = None;
+        for index in order {
+            let Some(secret) = self.secrets.get(index) else {
+                continue;
+            };
+            match self.request_token(scopes, secret).await? {
+                Ok(token) => {
+                    self.active.store(index, Ordering::Relaxed);
+                    if index > 0 {
+                        warn!(
+                            "ClientSecretsCredential authenticated with client secret {} of {}; finish rotating to the first secret",
+                            index + 1,
+                            self.secrets.len()
+                        );
+                    }
+                    return Ok(token);
+                }
+                Err(error) => {
+                    debug!(
+                        "client secret {} of {} was rejected",
+                        index + 1,
+                        self.secrets.len()
+                    );
+                    rejected = Some(error);
+                }
+            }
+        }
+
+        let error = rejected.map_or_else(String::new, |error| {
+            format!(" {}: {}", error.error, error.error_description)
+        });
+        Err(Error::with_message(
+            ErrorKind::Credential,
+            format!(
+                "ClientSecretsCredential authentication failed. None of the {} client secrets was accepted.{error}",
+                self.secrets.len()
+            ),
+        ))
+    }
+
+    /// Request a token with `secret`, returning the error response if the
+    /// secret was rejected as `invalid_client`.
+    async fn request_token(
+        &self,
+        scopes: &[&str],
+        secret: &Secret,
+    ) -> azure_core::Result<Result<AccessToken, TokenErrorResponse>> {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("client_id", &self.client_id)
+            .append_pair("client_secret", secret.secret())
+            .append_pair("grant_type", "client_credentials")
+            .append_pair("scope", &scopes.join(" "))
+            .finish();
+
+        match token_endpoint::request_token(
+            "ClientSecretsCredential",
+            &self.endpoint,
+            body,
+            &self.client_options,
+        )
+        .await?
+        {
+            Ok(token) => Ok(Ok(token)),
+            Err(response) => match response.parse::<TokenErrorResponse>() {
+                Some(error) if error.error == INVALID_CLIENT => Ok(Err(error)),
+                _ => Err(response.into_error("ClientSecretsCredential")),
+            },
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for ClientSecretsCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.cache
+            .get_token(scopes, options, |s, o| self.get_access_token(s, o))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockPolicy, TOKEN_BODY};
+    use azure_core::http::StatusCode;
+
+    const INVALID_CLIENT_RESPONSE: &str = r#"{"error":"invalid_client","error_description":"AADSTS7000215: Invalid client secret provided.","error_codes":[7000215]}"#;
+
+    fn credential(policy: &Arc<MockPolicy>) -> azure_core::Result<Arc<ClientSecretsCredential>> {
+        ClientSecretsCredential::builder("fake-tenant", "fake-client")
+            .with_secrets([Secret::new("new-secret"), Secret::new("old-secret")])
+            .with_client_options(policy.client_options())
+            .build()
+    }
+
+    fn sent_secrets(policy: &MockPolicy) -> Vec<String> {
+        policy
+            .bodies()
+            .iter()
+            .filter_map(|body| {
+                form_urlencoded::parse(body.as_bytes())
+                    .find(|(name, _)| name == "client_secret")
+                    .map(|(_, value)| value.into_owned())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn falls_back_and_remembers_accepted_secret() -> azure_core::Result<()> {
+        let policy = MockPolicy::with_body_responses(
+            vec![(
+                "client_secret=new-secret",
+                StatusCode::Unauthorized,
+                INVALID_CLIENT_RESPONSE,
+            )],
+            vec![("/oauth2/v2.0/token", StatusCode::Ok, TOKEN_BODY)],
+        );
+        let credential = credential(&policy)?;
+
+        let token = credential.get_token(&["first-scope"], None).await?;
+        credential.get_token(&["second-scope"], None).await?;
+
+        assert_eq!(token.token.secret(), "fake-token");
+        assert_eq!(
+            sent_secrets(&policy),
+            vec!["new-secret", "old-secret", "old-secret"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_not_retried() -> azure_core::Result<()> {
+        let policy = MockPolicy::with_status(vec![(
+            "/oauth2/v2.0/token",
+            StatusCode::BadRequest,
+            r#"{"error":"invalid_scope","error_description":"AADSTS70011: The provided value for scope is not valid."}"#,
+        )]);
+        let credential = credential(&policy)?;
+
+        let result = credential.get_token(&["fake-scope"], None).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("invalid_scope")
+        ));
+        assert_eq!(sent_secrets(&policy), vec!["new-secret"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_when_every_secret_is_rejected() -> azure_core::Result<()> {
+        let policy = MockPolicy::with_status(vec![(
+            "/oauth2/v2.0/token",
+            StatusCode::Unauthorized,
+            INVALID_CLIENT_RESPONSE,
+        )]);
+        let credential = credential(&policy)?;
+
+        let result = credential.get_token(&["fake-scope"], None).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if error.to_string().contains("None of the 2 client secrets")
+        ));
+        assert_eq!(sent_secrets(&policy), vec!["new-secret", "old-secret"]);
+        Ok(())
+    }
+
+    #[test]
+    fn requires_a_secret() {
+        assert!(
+            ClientSecretsCredential::builder("fake-tenant", "fake-client")
+                .build()
+                .is_err()
+        );
+    }
+}
@@ -1,14 +1,32 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    azure_powershell_credential::{AzurePowerShellCredential, AzurePowerShellCredentialOptions},
+    azureauth_cli_credentials::{AzureauthCliCredential, AzureauthCliCredentialOptions},
+    visual_studio_code_credential::{
+        VisualStudioCodeCredential, VisualStudioCodeCredentialOptions, default_auth_record_path,
+    },
+};
 use crate::{
     chained_token_credential::{
         ChainedTokenCredential, ChainedTokenCredentialOptions, format_aggregate_error,
     },
+    devicecode_credentials::{DeviceCodeCredential, DeviceCodeCredentialOptions},
+    diagnostics::{self, DiagnosticReport, EnvironmentVariableReport, SourceReport},
     environment_credential::{
-        EnvironmentCredential, EnvironmentSettings, create_workload_identity_credential,
+        AZURE_TOKEN_CREDENTIALS, EnvironmentCredential, EnvironmentSettings,
+        create_workload_identity_credential, managed_identity_credential_options,
     },
+    github_actions_credential::{self, GitHubActionsCredential, GitHubActionsCredentialOptions},
+    hosting_environment::HostingEnvironment,
+    imds::{self, ProbedManagedIdentityCredential},
+    tenant::{TenantCredential, TenantPolicy, parse_allowed_tenants},
 };
+use async_lock::{Mutex, OnceCell};
 use azure_core::{
     credentials::{AccessToken, TokenCredential, TokenRequestOptions},
     error::{Error, ErrorKind},
+    http::ClientOptions,
+    time::Duration,
 };
 #[cfg(not(target_arch = "wasm32"))]
 use azure_identity::{
@@ -16,20 +34,100 @@ use azure_identity::{
     AzureDeveloperCliCredentialOptions,
 };
 use azure_identity::{
-    ClientSecretCredentialOptions, ManagedIdentityCredential, ManagedIdentityCredentialOptions,
-    WorkloadIdentityCredentialOptions,
+    ClientAssertionCredentialOptions, ClientSecretCredentialOptions, ManagedIdentityCredential,
+    ManagedIdentityCredentialOptions, WorkloadIdentityCredentialOptions,
 };
-use std::sync::Arc;
+use std::{env, path::Path, sync::Arc};
 
+/// A credential source that can be part of a [`DefaultAzureCredential`] chain.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum DefaultAzureCredentialSource {
+#[non_exhaustive]
+pub enum DefaultAzureCredentialSource {
+    /// Environment-backed authentication.
     Environment,
+    /// `WorkloadIdentityCredential`.
     WorkloadIdentity,
+    /// [`GitHubActionsCredential`].  Added after workload identity only when
+    /// `ACTIONS_ID_TOKEN_REQUEST_URL` and `ACTIONS_ID_TOKEN_REQUEST_TOKEN` are
+    /// set as the chain is built.
+    GitHubActions,
+    /// `ManagedIdentityCredential`.
     ManagedIdentity,
+    /// `AzureCliCredential`.
     #[cfg(not(target_arch = "wasm32"))]
     AzureCli,
+    /// `AzureDeveloperCliCredential`.
     #[cfg(not(target_arch = "wasm32"))]
     AzureDeveloperCli,
+    /// [`AzurePowerShellCredential`].
+    #[cfg(not(target_arch = "wasm32"))]
+    AzurePowerShell,
+    /// [`VisualStudioCodeCredential`].
+    #[cfg(not(target_arch = "wasm32"))]
+    VisualStudioCode,
+    /// [`AzureauthCliCredential`].  Not included unless enabled on the builder.
+    #[cfg(not(target_arch = "wasm32"))]
+    AzureauthCli,
+    /// [`DeviceCodeCredential`].  Not included unless enabled on the builder.
+    DeviceCode,
+}
+
+/// Where an opt-in or custom credential source is placed in the
+/// [`DefaultAzureCredential`] chain.
+///
+/// If the referenced source is not part of the chain, the new source is
+/// placed at the end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefaultAzureCredentialSourcePosition {
+    /// Attempt the source before any other.
+    First,
+    /// Attempt the source after every other.
+    Last,
+    /// Attempt the source immediately before the given source.
+    Before(DefaultAzureCredentialSource),
+    /// Attempt the source immediately after the given source.
+    After(DefaultAzureCredentialSource),
+}
+
+impl DefaultAzureCredentialSource {
+    /// The credential type name accepted by `AZURE_TOKEN_CREDENTIALS`.
+    pub(crate) fn credential_name(self) -> &'static str {
+        match self {
+            Self::Environment => "EnvironmentCredential",
+            Self::WorkloadIdentity => "WorkloadIdentityCredential",
+            Self::GitHubActions => "GitHubActionsCredential",
+            Self::ManagedIdentity => "ManagedIdentityCredential",
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::AzureCli => "AzureCliCredential",
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::AzureDeveloperCli => "AzureDeveloperCliCredential",
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::AzurePowerShell => "AzurePowerShellCredential",
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::VisualStudioCode => "VisualStudioCodeCredential",
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::AzureauthCli => "AzureauthCliCredential",
+            Self::DeviceCode => "DeviceCodeCredential",
+        }
+    }
+
+    /// Whether the source relies on a developer's interactive sign-in rather
+    /// than on configuration provided by the hosting environment.
+    fn is_developer_tool(self) -> bool {
+        match self {
+            Self::Environment
+            | Self::WorkloadIdentity
+            | Self::GitHubActions
+            | Self::ManagedIdentity => false,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::AzureCli
+            | Self::AzureDeveloperCli
+            | Self::AzurePowerShell
+            | Self::VisualStudioCode
+            | Self::AzureauthCli => true,
+            Self::DeviceCode => true,
+        }
+    }
 }
 
 fn default_azure_credential_sources() -> Vec<DefaultAzureCredentialSource> {
@@ -42,18 +140,153 @@ fn default_azure_credential_sources() -> Vec<DefaultAzureCredentialSource> {
     {
         sources.push(DefaultAzureCredentialSource::AzureCli);
         sources.push(DefaultAzureCredentialSource::AzureDeveloperCli);
+        sources.push(DefaultAzureCredentialSource::AzurePowerShell);
+        sources.push(DefaultAzureCredentialSource::VisualStudioCode);
     }
     sources
 }
 
-fn append_source<T>(
+/// Every source, including the ones that must be enabled explicitly.
+fn all_azure_credential_sources() -> Vec<DefaultAzureCredentialSource> {
+    let mut sources = default_azure_credential_sources();
+    sources.push(DefaultAzureCredentialSource::GitHubActions);
+    #[cfg(not(target_arch = "wasm32"))]
+    sources.push(DefaultAzureCredentialSource::AzureauthCli);
+    sources.push(DefaultAzureCredentialSource::DeviceCode);
+    sources
+}
+
+/// An entry in the chain being built: either a built-in source or a
+/// credential provided by the caller.
+#[derive(Debug)]
+enum ChainEntry {
+    BuiltIn(DefaultAzureCredentialSource),
+    Custom(Arc<dyn TokenCredential>),
+}
+
+impl ChainEntry {
+    fn is_source(&self, source: DefaultAzureCredentialSource) -> bool {
+        matches!(self, Self::BuiltIn(existing) if *existing == source)
+    }
+}
+
+/// The index at which an entry should be inserted for `position`, falling
+/// back to the end when the anchor is not present.
+fn insertion_index<T>(
+    entries: &[T],
+    position: DefaultAzureCredentialSourcePosition,
+    is_source: impl Fn(&T, DefaultAzureCredentialSource) -> bool,
+) -> usize {
+    let index = match position {
+        DefaultAzureCredentialSourcePosition::First => Some(0),
+        DefaultAzureCredentialSourcePosition::Last => None,
+        DefaultAzureCredentialSourcePosition::Before(anchor) => {
+            entries.iter().position(|entry| is_source(entry, anchor))
+        }
+        DefaultAzureCredentialSourcePosition::After(anchor) => entries
+            .iter()
+            .position(|entry| is_source(entry, anchor))
+            .map(|index| index + 1),
+    };
+    index.unwrap_or(entries.len())
+}
+
+fn insert_source(
+    sources: &mut Vec<DefaultAzureCredentialSource>,
+    source: DefaultAzureCredentialSource,
+    position: DefaultAzureCredentialSourcePosition,
+) {
+    sources.retain(|existing| *existing != source);
+    let index = insertion_index(sources, position, |existing, anchor| *existing == anchor);
+    sources.insert(index, source);
+}
+
+/// The tenant and client used by an opt-in interactive credential source.
+#[derive(Debug, Clone)]
+struct InteractiveCredentialConfig {
+    tenant_id: String,
+    client_id: String,
+}
+
+/// Restrict `sources` to the ones selected by an `AZURE_TOKEN_CREDENTIALS` value.
+///
+/// The value is either `dev`, `prod`, or the name of a single credential type,
+/// compared case-insensitively.  An empty value leaves the sources unchanged.
+fn select_token_credentials(
+    mut sources: Vec<DefaultAzureCredentialSource>,
+    value: &str,
+) -> azure_core::Result<Vec<DefaultAzureCredentialSource>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(sources);
+    }
+
+    if value.eq_ignore_ascii_case("dev") {
+        sources.retain(|source| source.is_developer_tool());
+    } else if value.eq_ignore_ascii_case("prod") {
+        sources.retain(|source| !source.is_developer_tool());
+    } else {
+        let known = all_azure_credential_sources();
+        let Some(selected) = known
+            .iter()
+            .find(|source| source.credential_name().eq_ignore_ascii_case(value))
+        else {
+            let names = known
+                .iter()
+                .map(|source| source.credential_name())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                format!(
+                    "invalid {AZURE_TOKEN_CREDENTIALS} value '{value}'. Expected 'dev', 'prod', or one of: {names}"
+                ),
+            ));
+        };
+        sources.retain(|source| source == selected);
+    }
+
+    Ok(sources)
+}
+
+fn not_configured(source: DefaultAzureCredentialSource) -> Error {
+    Error::with_message(
+        ErrorKind::Credential,
+        format!(
+            "{} was not configured with a tenant and client",
+            source.credential_name()
+        ),
+    )
+}
+
+/// Stands in for a source that could not be constructed when construction is
+/// deferred, so that the construction error is reported by the chain.
+#[derive(Debug)]
+struct UnavailableCredential {
+    message: String,
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for UnavailableCredential {
+    async fn get_token(
+        &self,
+        _scopes: &[&str],
+        _options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        Err(Error::with_message(
+            ErrorKind::Credential,
+            self.message.clone(),
+        ))
+    }
+}
+
+fn append_source(
     credential: &mut ChainedTokenCredential,
-    result: azure_core::Result<Arc<T>>,
+    result: azure_core::Result<Arc<dyn TokenCredential>>,
     errors: &mut Vec<Error>,
     source_count: &mut usize,
-) where
-    T: TokenCredential + 'static,
-{
+) {
     match result {
         Ok(source) => {
             credential.add_source(source);
@@ -68,28 +301,69 @@ fn append_source<T>(
 #[derive(Debug)]
 pub struct DefaultAzureCredentialBuilder {
     sources: Vec<DefaultAzureCredentialSource>,
+    /// Whether to add the GitHub Actions source when the job can request ID
+    /// tokens.
+    detect_github_actions: bool,
+    tenant_id: Option<String>,
+    additionally_allowed_tenants: Option<Vec<String>>,
+    custom_sources: Vec<(
+        DefaultAzureCredentialSourcePosition,
+        Arc<dyn TokenCredential>,
+    )>,
+    token_credentials: Option<String>,
+    environment: Option<EnvironmentSettings>,
+    client_options: Option<ClientOptions>,
     chained_token_credential_options: Option<ChainedTokenCredentialOptions>,
     workload_identity_credential_options: Option<WorkloadIdentityCredentialOptions>,
     client_secret_credential_options: Option<ClientSecretCredentialOptions>,
     managed_identity_credential_options: Option<ManagedIdentityCredentialOptions>,
+    managed_identity_probe_timeout: Duration,
     #[cfg(not(target_arch = "wasm32"))]
     azure_cli_credential_options: Option<AzureCliCredentialOptions>,
     #[cfg(not(target_arch = "wasm32"))]
     azure_developer_cli_credential_options: Option<AzureDeveloperCliCredentialOptions>,
+    #[cfg(not(target_arch = "wasm32"))]
+    azure_powershell_credential_options: Option<AzurePowerShellCredentialOptions>,
+    #[cfg(not(target_arch = "wasm32"))]
+    visual_studio_code_credential_options: Option<VisualStudioCodeCredentialOptions>,
+    #[cfg(not(target_arch = "wasm32"))]
+    azureauth_cli_credential: Option<InteractiveCredentialConfig>,
+    #[cfg(not(target_arch = "wasm32"))]
+    azureauth_cli_credential_options: Option<AzureauthCliCredentialOptions>,
+    device_code_credential: Option<InteractiveCredentialConfig>,
+    defer_construction: bool,
 }
 
 impl Default for DefaultAzureCredentialBuilder {
     fn default() -> Self {
         Self {
             sources: default_azure_credential_sources(),
+            detect_github_actions: true,
+            tenant_id: None,
+            additionally_allowed_tenants: None,
+            custom_sources: Vec::new(),
+            token_credentials: None,
+            environment: None,
+            client_options: None,
             chained_token_credential_options: None,
             workload_identity_credential_options: None,
             client_secret_credential_options: None,
             managed_identity_credential_options: None,
+            managed_identity_probe_timeout: imds::DEFAULT_PROBE_TIMEOUT,
             #[cfg(not(target_arch = "wasm32"))]
             azure_cli_credential_options: None,
             #[cfg(not(target_arch = "wasm32"))]
             azure_developer_cli_credential_options: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            azure_powershell_credential_options: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            visual_studio_code_credential_options: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            azureauth_cli_credential: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            azureauth_cli_credential_options: None,
+            device_code_credential: None,
+            defer_construction: false,
         }
     }
 }
@@ -117,6 +391,15 @@ impl DefaultAzureCredentialBuilder {
         self
     }
 
+    #[must_use]
+    /// Exclude the GitHub Actions credential source.
+    pub fn exclude_github_actions_credential(mut self) -> Self {
+        self.detect_github_actions = false;
+        self.sources
+            .retain(|source| *source != DefaultAzureCredentialSource::GitHubActions);
+        self
+    }
+
     #[must_use]
     /// Exclude the managed identity credential source.
     pub fn exclude_managed_identity_credential(mut self) -> Self {
@@ -125,6 +408,163 @@ impl DefaultAzureCredentialBuilder {
         self
     }
 
+    #[must_use]
+    /// Replace the built-in credential sources with `sources`, attempted in
+    /// the given order.
+    ///
+    /// Sources that are not listed are excluded, including
+    /// [`DefaultAzureCredentialSource::GitHubActions`] in a GitHub Actions job.
+    /// Listing an opt-in source such as
+    /// [`DefaultAzureCredentialSource::DeviceCode`] does not configure it; use
+    /// its `include_*` method instead.
+    pub fn with_source_order<I>(mut self, sources: I) -> Self
+    where
+        I: IntoIterator<Item = DefaultAzureCredentialSource>,
+    {
+        self.detect_github_actions = false;
+        self.sources.clear();
+        for source in sources {
+            insert_source(
+                &mut self.sources,
+                source,
+                DefaultAzureCredentialSourcePosition::Last,
+            );
+        }
+        self
+    }
+
+    #[must_use]
+    /// Replace the built-in credential sources with the ones suited to
+    /// `environment`, as listed by [`HostingEnvironment::sources`].
+    ///
+    /// Use [`detect`](crate::hosting_environment::detect) to find the environment the
+    /// process is running in.
+    pub fn with_hosting_environment(self, environment: HostingEnvironment) -> Self {
+        self.with_source_order(environment.sources())
+    }
+
+    #[must_use]
+    /// Add a custom credential source at the end of the chain.
+    ///
+    /// Custom sources are attempted alongside the built-in sources and are not
+    /// affected by `AZURE_TOKEN_CREDENTIALS`.
+    pub fn push_custom_source(mut self, credential: Arc<dyn TokenCredential>) -> Self {
+        self.custom_sources
+            .push((DefaultAzureCredentialSourcePosition::Last, credential));
+        self
+    }
+
+    #[must_use]
+    /// Add a custom credential source immediately before `anchor`.
+    ///
+    /// If `anchor` is not part of the chain when it is built, the custom
+    /// source is placed at the end.
+    pub fn insert_source_before(
+        mut self,
+        anchor: DefaultAzureCredentialSource,
+        credential: Arc<dyn TokenCredential>,
+    ) -> Self {
+        self.custom_sources.push((
+            DefaultAzureCredentialSourcePosition::Before(anchor),
+            credential,
+        ));
+        self
+    }
+
+    #[must_use]
+    /// Add a custom credential source immediately after `anchor`.
+    ///
+    /// If `anchor` is not part of the chain when it is built, the custom
+    /// source is placed at the end.
+    pub fn insert_source_after(
+        mut self,
+        anchor: DefaultAzureCredentialSource,
+        credential: Arc<dyn TokenCredential>,
+    ) -> Self {
+        self.custom_sources.push((
+            DefaultAzureCredentialSourcePosition::After(anchor),
+            credential,
+        ));
+        self
+    }
+
+    #[must_use]
+    /// Authenticate in `tenant_id` by default.
+    ///
+    /// The tenant overrides `AZURE_TENANT_ID` for the environment, workload
+    /// identity, GitHub Actions and managed identity sources, and the tenant
+    /// given to `include_device_code_credential` and
+    /// `include_azureauth_cli_credential`.  The Azure CLI, Azure Developer CLI,
+    /// Azure `PowerShell` and Visual Studio Code sources use it unless their
+    /// own options set one.
+    ///
+    /// When not set, `AZURE_TENANT_ID` is the tenant that
+    /// [`with_additionally_allowed_tenants`](Self::with_additionally_allowed_tenants)
+    /// extends.
+    pub fn with_tenant_id<T>(mut self, tenant_id: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.tenant_id = Some(tenant_id.into());
+        self
+    }
+
+    #[must_use]
+    /// Allow token requests for tenants other than the configured tenant.
+    ///
+    /// A tenant is requested by adding a
+    /// [`RequestedTenant`](crate::tenant::RequestedTenant) to the request
+    /// context.  Each source then authenticates in the requested tenant, except
+    /// the managed identity source, which can only authenticate in the tenant
+    /// of its identity and fails so that the chain moves on.  Requests for any
+    /// other tenant fail with a credential error before any source is
+    /// attempted.  `*` allows every tenant.  Overrides the
+    /// `;`-separated `AZURE_ADDITIONALLY_ALLOWED_TENANTS` environment variable.
+    pub fn with_additionally_allowed_tenants<I, T>(mut self, tenants: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.additionally_allowed_tenants = Some(tenants.into_iter().map(Into::into).collect());
+        self
+    }
+
+    #[must_use]
+    /// Restrict the credential sources in the same way as the
+    /// `AZURE_TOKEN_CREDENTIALS` environment variable, which this value overrides.
+    ///
+    /// Accepts `dev` for developer tool credentials, `prod` for environment,
+    /// workload identity and managed identity credentials, or the name of a
+    /// single credential type such as `ManagedIdentityCredential`.
+    pub fn with_token_credentials<S>(mut self, value: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.token_credentials = Some(value.into());
+        self
+    }
+
+    #[must_use]
+    /// Read the `AZURE_*` configuration from `settings` instead of the process
+    /// environment.
+    ///
+    /// The settings configure the environment, workload identity and GitHub
+    /// Actions sources, the managed identity client ID, the tenant,
+    /// `AZURE_TOKEN_CREDENTIALS` and the authority host.  The variables set by
+    /// the hosting platform, such as `IDENTITY_ENDPOINT` and
+    /// `ACTIONS_ID_TOKEN_REQUEST_URL`, are still read from the process
+    /// environment.
+    pub fn with_environment_settings(mut self, settings: EnvironmentSettings) -> Self {
+        self.environment = Some(settings);
+        self
+    }
+
+    fn environment_settings(&self) -> EnvironmentSettings {
+        self.environment
+            .clone()
+            .unwrap_or_else(EnvironmentSettings::from_os_env)
+    }
+
     #[must_use]
     /// Configure the chained credential behavior.
     pub fn with_chained_token_credential_options(
@@ -145,6 +585,19 @@ impl DefaultAzureCredentialBuilder {
         self
     }
 
+    #[must_use]
+    /// Set the HTTP client options shared by every source that sends requests
+    /// itself: the environment, workload identity, managed identity, Visual
+    /// Studio Code and device code sources.
+    ///
+    /// Options configured for an individual source take precedence and are
+    /// used as given.  Sources that delegate to a command-line tool ignore
+    /// these options.
+    pub fn with_client_options(mut self, options: ClientOptions) -> Self {
+        self.client_options = Some(options);
+        self
+    }
+
     #[must_use]
     /// Configure the environment credential source.
     pub fn with_client_secret_credential_options(
@@ -157,6 +610,10 @@ impl DefaultAzureCredentialBuilder {
 
     #[must_use]
     /// Configure the managed identity credential source.
+    ///
+    /// Without explicit options, a user-assigned identity is selected from
+    /// `AZURE_MANAGED_IDENTITY_RESOURCE_ID`, `AZURE_MANAGED_IDENTITY_OBJECT_ID`
+    /// or `AZURE_CLIENT_ID`, in that order.
     pub fn with_managed_identity_credential_options(
         mut self,
         options: ManagedIdentityCredentialOptions,
@@ -165,6 +622,19 @@ impl DefaultAzureCredentialBuilder {
         self
     }
 
+    #[must_use]
+    /// Set how long the managed identity source waits for IMDS to respond
+    /// before it is treated as unavailable.  Defaults to one second.
+    ///
+    /// The probe runs on the first token request.  A successful probe is
+    /// cached; a failed one is repeated on the next request.
+    /// It is skipped when `IDENTITY_ENDPOINT` or `MSI_ENDPOINT` selects a
+    /// managed identity source other than IMDS.
+    pub fn with_managed_identity_probe_timeout(mut self, timeout: Duration) -> Self {
+        self.managed_identity_probe_timeout = timeout;
+        self
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     #[must_use]
     /// Exclude the Azure CLI credential source.
@@ -202,80 +672,247 @@ impl DefaultAzureCredentialBuilder {
         self
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    /// Exclude the Azure `PowerShell` credential source.
+    pub fn exclude_azure_powershell_credential(mut self) -> Self {
+        self.sources
+            .retain(|source| *source != DefaultAzureCredentialSource::AzurePowerShell);
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    /// Configure the Azure `PowerShell` credential source.
+    pub fn with_azure_powershell_credential_options(
+        mut self,
+        options: AzurePowerShellCredentialOptions,
+    ) -> Self {
+        self.azure_powershell_credential_options = Some(options);
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    /// Exclude the Visual Studio Code credential source.
+    pub fn exclude_visual_studio_code_credential(mut self) -> Self {
+        self.sources
+            .retain(|source| *source != DefaultAzureCredentialSource::VisualStudioCode);
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    /// Configure the Visual Studio Code credential source.
+    pub fn with_visual_studio_code_credential_options(
+        mut self,
+        options: VisualStudioCodeCredentialOptions,
+    ) -> Self {
+        self.visual_studio_code_credential_options = Some(options);
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    /// Include the [`AzureauthCliCredential`] source at the given position.
+    ///
+    /// This source is not part of the chain unless enabled.
+    pub fn include_azureauth_cli_credential<T, C>(
+        mut self,
+        tenant_id: T,
+        client_id: C,
+        position: DefaultAzureCredentialSourcePosition,
+    ) -> Self
+    where
+        T: Into<String>,
+        C: Into<String>,
+    {
+        self.azureauth_cli_credential = Some(InteractiveCredentialConfig {
+            tenant_id: tenant_id.into(),
+            client_id: client_id.into(),
+        });
+        insert_source(
+            &mut self.sources,
+            DefaultAzureCredentialSource::AzureauthCli,
+            position,
+        );
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    /// Configure the azureauth CLI credential source.
+    pub fn with_azureauth_cli_credential_options(
+        mut self,
+        options: AzureauthCliCredentialOptions,
+    ) -> Self {
+        self.azureauth_cli_credential_options = Some(options);
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    /// Exclude the azureauth CLI credential source.
+    pub fn exclude_azureauth_cli_credential(mut self) -> Self {
+        self.sources
+            .retain(|source| *source != DefaultAzureCredentialSource::AzureauthCli);
+        self
+    }
+
+    #[must_use]
+    /// Include the [`DeviceCodeCredential`] source at the given position.
+    ///
+    /// This source is not part of the chain unless enabled.  The device code
+    /// flow prompts on stderr and waits for the user to sign in.
+    pub fn include_device_code_credential<T, C>(
+        mut self,
+        tenant_id: T,
+        client_id: C,
+        position: DefaultAzureCredentialSourcePosition,
+    ) -> Self
+    where
+        T: Into<String>,
+        C: Into<String>,
+    {
+        self.device_code_credential = Some(InteractiveCredentialConfig {
+            tenant_id: tenant_id.into(),
+            client_id: client_id.into(),
+        });
+        insert_source(
+            &mut self.sources,
+            DefaultAzureCredentialSource::DeviceCode,
+            position,
+        );
+        self
+    }
+
+    #[must_use]
+    /// Exclude the device code credential source.
+    pub fn exclude_device_code_credential(mut self) -> Self {
+        self.sources
+            .retain(|source| *source != DefaultAzureCredentialSource::DeviceCode);
+        self
+    }
+
+    #[must_use]
+    /// Defer constructing the credential sources until the first token
+    /// request, so that [`build`](Self::build) always succeeds.
+    ///
+    /// Errors from constructing the sources are reported by `get_token`
+    /// instead.  Sources that cannot be constructed are kept in the chain and
+    /// contribute their construction error to the aggregate error.
+    pub fn defer_construction(mut self) -> Self {
+        self.defer_construction = true;
+        self
+    }
+
     /// Build a new [`DefaultAzureCredential`].
     pub fn build(self) -> azure_core::Result<Arc<DefaultAzureCredential>> {
-        let Self {
-            sources,
-            chained_token_credential_options,
-            workload_identity_credential_options,
-            client_secret_credential_options,
-            managed_identity_credential_options,
-            #[cfg(not(target_arch = "wasm32"))]
-            azure_cli_credential_options,
-            #[cfg(not(target_arch = "wasm32"))]
-            azure_developer_cli_credential_options,
-        } = self;
+        let tenant_policy = self.tenant_policy(&self.environment_settings());
+        let state = if self.defer_construction {
+            CredentialState::Deferred {
+                builder: Mutex::new(Some(Box::new(self))),
+                credential: OnceCell::new(),
+            }
+        } else {
+            CredentialState::Built(self.build_chain()?)
+        };
+        Ok(Arc::new(DefaultAzureCredential {
+            state,
+            tenant_policy,
+        }))
+    }
+
+    fn tenant_policy(&self, environment: &EnvironmentSettings) -> TenantPolicy {
+        TenantPolicy::new(
+            self.tenant_id
+                .clone()
+                .or_else(|| environment.tenant_id().map(ToOwned::to_owned)),
+            self.additionally_allowed_tenants
+                .clone()
+                .unwrap_or_else(|| {
+                    environment
+                        .additionally_allowed_tenants()
+                        .map(parse_allowed_tenants)
+                        .unwrap_or_default()
+                }),
+        )
+    }
+
+    /// The builder's tenant, unless the source's own options set one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn source_tenant_id(&self, tenant_id: Option<String>) -> Option<String> {
+        tenant_id.or_else(|| self.tenant_id.clone())
+    }
+
+    /// The built-in sources, with the GitHub Actions source after workload
+    /// identity when `lookup` finds the variables the job needs to request ID
+    /// tokens.
+    fn chain_sources(
+        &self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Vec<DefaultAzureCredentialSource> {
+        let mut sources = self.sources.clone();
+        if self.detect_github_actions
+            && !sources.contains(&DefaultAzureCredentialSource::GitHubActions)
+            && github_actions_credential::can_request_id_tokens(lookup)
+        {
+            let position = if sources.contains(&DefaultAzureCredentialSource::WorkloadIdentity) {
+                DefaultAzureCredentialSourcePosition::After(
+                    DefaultAzureCredentialSource::WorkloadIdentity,
+                )
+            } else {
+                DefaultAzureCredentialSourcePosition::Before(
+                    DefaultAzureCredentialSource::ManagedIdentity,
+                )
+            };
+            insert_source(
+                &mut sources,
+                DefaultAzureCredentialSource::GitHubActions,
+                position,
+            );
+        }
+        sources
+    }
+
+    fn build_chain(mut self) -> azure_core::Result<ChainedTokenCredential> {
+        let environment = self.environment_settings();
+        let sources = self.chain_sources(|name| env::var(name).ok());
+        let sources = match self
+            .token_credentials
+            .as_deref()
+            .or_else(|| environment.token_credentials())
+        {
+            Some(value) => select_token_credentials(sources, value)?,
+            None => sources,
+        };
+        let entries = self.chain_entries(sources);
 
-        if sources.is_empty() {
+        if entries.is_empty() {
             return Err(Error::with_message(
                 ErrorKind::Credential,
                 "No credential sources were enabled for authentication.",
             ));
         }
 
-        let mut credential = ChainedTokenCredential::new(chained_token_credential_options);
-        let environment = EnvironmentSettings::from_os_env();
-        let mut workload_identity_credential_options = workload_identity_credential_options;
-        let mut client_secret_credential_options = client_secret_credential_options;
-        let mut managed_identity_credential_options = managed_identity_credential_options;
-        #[cfg(not(target_arch = "wasm32"))]
-        let mut azure_cli_credential_options = azure_cli_credential_options;
-        #[cfg(not(target_arch = "wasm32"))]
-        let mut azure_developer_cli_credential_options = azure_developer_cli_credential_options;
+        let mut credential =
+            ChainedTokenCredential::new(self.chained_token_credential_options.take());
         let mut errors = Vec::new();
         let mut source_count = 0usize;
 
-        for source in sources {
-            match source {
-                DefaultAzureCredentialSource::Environment => append_source(
-                    &mut credential,
-                    EnvironmentCredential::from_settings(
-                        &environment,
-                        client_secret_credential_options.take(),
-                    ),
-                    &mut errors,
-                    &mut source_count,
-                ),
-                DefaultAzureCredentialSource::WorkloadIdentity => append_source(
-                    &mut credential,
-                    create_workload_identity_credential(
-                        &environment,
-                        workload_identity_credential_options.take(),
-                    ),
-                    &mut errors,
-                    &mut source_count,
-                ),
-                DefaultAzureCredentialSource::ManagedIdentity => append_source(
-                    &mut credential,
-                    ManagedIdentityCredential::new(managed_identity_credential_options.take()),
-                    &mut errors,
-                    &mut source_count,
-                ),
-                #[cfg(not(target_arch = "wasm32"))]
-                DefaultAzureCredentialSource::AzureCli => append_source(
-                    &mut credential,
-                    AzureCliCredential::new(azure_cli_credential_options.take()),
-                    &mut errors,
-                    &mut source_count,
-                ),
-                #[cfg(not(target_arch = "wasm32"))]
-                DefaultAzureCredentialSource::AzureDeveloperCli => append_source(
-                    &mut credential,
-                    AzureDeveloperCliCredential::new(azure_developer_cli_credential_options.take()),
-                    &mut errors,
-                    &mut source_count,
-                ),
-            }
+        for entry in entries {
+            let result = match entry {
+                ChainEntry::BuiltIn(source) => self.create_source(source, &environment),
+                ChainEntry::Custom(source) => Ok(source),
+            };
+            let result = match result {
+                Err(error) if self.defer_construction => Ok(Arc::new(UnavailableCredential {
+                    message: format_aggregate_error(&[error]),
+                })
+                    as Arc<dyn TokenCredential>),
+                result => result,
+            };
+            append_source(&mut credential, result, &mut errors, &mut source_count);
         }
 
         if source_count == 0 {
@@ -288,44 +925,507 @@ impl DefaultAzureCredentialBuilder {
             ));
         }
 
-        Ok(Arc::new(DefaultAzureCredential { credential }))
+        Ok(credential)
     }
 
-    #[cfg(test)]
-    fn included(&self) -> Vec<DefaultAzureCredentialSource> {
-        self.sources.clone()
-    }
-}
+    /// Inspect each credential source without acquiring tokens.
+    ///
+    /// The report lists the relevant environment variables with secrets
+    /// redacted, whether the developer tools are on `PATH`, whether the
+    /// federated token file exists and whether IMDS is reachable, along with
+    /// hints for the sources that are not expected to work.
+    pub async fn diagnose(&self) -> DiagnosticReport {
+        let settings = self.environment_settings();
+        let environment = diagnostics::environment_variables(&settings);
+        let chain_sources = self.chain_sources(|name| env::var(name).ok());
+        let (selected, token_credentials_error) = self
+            .token_credentials
+            .as_deref()
+            .or_else(|| settings.token_credentials())
+            .map_or_else(
+                || (chain_sources.clone(), None),
+                |value| match select_token_credentials(chain_sources.clone(), value) {
+                    Ok(selected) => (selected, None),
+                    Err(error) => (Vec::new(), Some(error.to_string())),
+                },
+            );
 
-/// Recreates `DefaultAzureCredential` using the currently supported Rust credential types.
-///
-/// The following credential sources are attempted in order on non-`wasm32` targets:
-/// - environment-backed authentication (`ClientSecretCredential`)
-/// - `WorkloadIdentityCredential`
-/// - `ManagedIdentityCredential`
-/// - `AzureCliCredential`
-/// - `AzureDeveloperCliCredential`
-///
-/// On `wasm32`, the following credential sources are attempted in order:
-/// - environment-backed authentication (`ClientSecretCredential`)
-/// - `WorkloadIdentityCredential`
-/// - `ManagedIdentityCredential`
-///
-/// `AzurePowerShellCredential` is not currently implemented in this crate.
-#[derive(Debug)]
-pub struct DefaultAzureCredential {
-    credential: ChainedTokenCredential,
-}
+        let mut sources = Vec::with_capacity(chain_sources.len());
+        for source in &chain_sources {
+            let mut report = self.diagnose_source(*source, &settings, &environment).await;
+            if !selected.contains(source) {
+                report.exclude();
+            }
+            sources.push(report);
+        }
 
-impl DefaultAzureCredential {
-    /// Create a `DefaultAzureCredential` with the default chain order.
-    pub fn new() -> azure_core::Result<Arc<Self>> {
-        DefaultAzureCredentialBuilder::new().build()
+        DiagnosticReport {
+            environment,
+            token_credentials_error,
+            sources,
+            custom_sources: self.custom_sources.len(),
+        }
     }
 
-    #[must_use]
-    /// Create a builder for a customized `DefaultAzureCredential`.
-    pub fn builder() -> DefaultAzureCredentialBuilder {
+    async fn diagnose_source(
+        &self,
+        source: DefaultAzureCredentialSource,
+        settings: &EnvironmentSettings,
+        environment: &[EnvironmentVariableReport],
+    ) -> SourceReport {
+        match source {
+            DefaultAzureCredentialSource::Environment => {
+                diagnostics::check_environment(environment)
+            }
+            DefaultAzureCredentialSource::WorkloadIdentity => {
+                let token_file = self
+                    .workload_identity_credential_options
+                    .as_ref()
+                    .and_then(|options| options.token_file_path.clone())
+                    .or_else(|| settings.federated_token_file().map(Path::to_path_buf));
+                diagnostics::check_workload_identity(environment, token_file.as_deref())
+            }
+            DefaultAzureCredentialSource::GitHubActions => {
+                diagnostics::check_github_actions(environment)
+            }
+            DefaultAzureCredentialSource::ManagedIdentity => {
+                diagnostics::check_managed_identity(
+                    environment,
+                    imds::IMDS_ENDPOINT,
+                    self.managed_identity_probe_timeout,
+                    self.shared_client_options(),
+                )
+                .await
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::AzureCli => diagnostics::check_executable(
+                source,
+                &["az"],
+                "install the Azure CLI and run `az login`",
+            ),
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::AzureDeveloperCli => diagnostics::check_executable(
+                source,
+                &["azd"],
+                "install the Azure Developer CLI and run `azd auth login`",
+            ),
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::AzurePowerShell => diagnostics::check_executable(
+                source,
+                &["pwsh", "powershell"],
+                "install PowerShell and the Az.Accounts module, then run `Connect-AzAccount`",
+            ),
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::VisualStudioCode => {
+                let auth_record = self
+                    .visual_studio_code_credential_options
+                    .as_ref()
+                    .and_then(|options| options.auth_record_path.clone())
+                    .or_else(default_auth_record_path);
+                diagnostics::check_file(
+                    source,
+                    auth_record.as_deref(),
+                    "sign in with the Azure Resources extension in Visual Studio Code",
+                )
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::AzureauthCli => {
+                if self.azureauth_cli_credential.is_some() {
+                    diagnostics::check_executable(source, &["azureauth"], "install azureauth")
+                } else {
+                    diagnostics::check_configured(
+                        source,
+                        false,
+                        "enable it with include_azureauth_cli_credential",
+                    )
+                }
+            }
+            DefaultAzureCredentialSource::DeviceCode => diagnostics::check_configured(
+                source,
+                self.device_code_credential.is_some(),
+                "enable it with include_device_code_credential",
+            ),
+        }
+    }
+
+    /// Place the custom sources among the selected built-in sources.  Each
+    /// custom source is positioned relative to the chain as built so far.
+    fn chain_entries(&mut self, sources: Vec<DefaultAzureCredentialSource>) -> Vec<ChainEntry> {
+        let mut entries = sources
+            .into_iter()
+            .map(ChainEntry::BuiltIn)
+            .collect::<Vec<_>>();
+        for (position, credential) in self.custom_sources.drain(..) {
+            let index = insertion_index(&entries, position, ChainEntry::is_source);
+            entries.insert(index, ChainEntry::Custom(credential));
+        }
+        entries
+    }
+
+    fn create_source(
+        &mut self,
+        source: DefaultAzureCredentialSource,
+        environment: &EnvironmentSettings,
+    ) -> azure_core::Result<Arc<dyn TokenCredential>> {
+        let name = source.credential_name();
+        match source {
+            DefaultAzureCredentialSource::Environment => {
+                self.create_environment_credential(environment)
+            }
+            DefaultAzureCredentialSource::WorkloadIdentity => {
+                self.create_workload_identity_credential(environment)
+            }
+            DefaultAzureCredentialSource::GitHubActions => {
+                self.create_github_actions_credential(environment)
+            }
+            DefaultAzureCredentialSource::ManagedIdentity => {
+                self.create_managed_identity_credential(environment)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::AzureCli => {
+                let mut options = self.azure_cli_credential_options.take().unwrap_or_default();
+                options.tenant_id = self.source_tenant_id(options.tenant_id);
+                Ok(TenantCredential::new(
+                    name,
+                    options.tenant_id.clone(),
+                    Box::new(move |tenant_id| {
+                        let mut options = options.clone();
+                        options.tenant_id = tenant_id.map(ToOwned::to_owned).or(options.tenant_id);
+                        Ok(AzureCliCredential::new(Some(options))?)
+                    }),
+                )?)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::AzureDeveloperCli => {
+                let mut options = self
+                    .azure_developer_cli_credential_options
+                    .take()
+                    .unwrap_or_default();
+                options.tenant_id = self.source_tenant_id(options.tenant_id);
+                Ok(TenantCredential::new(
+                    name,
+                    options.tenant_id.clone(),
+                    Box::new(move |tenant_id| {
+                        let mut options = options.clone();
+                        options.tenant_id = tenant_id.map(ToOwned::to_owned).or(options.tenant_id);
+                        Ok(AzureDeveloperCliCredential::new(Some(options))?)
+                    }),
+                )?)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::AzurePowerShell => {
+                let mut options = self
+                    .azure_powershell_credential_options
+                    .take()
+                    .unwrap_or_default();
+                options.tenant_id = self.source_tenant_id(options.tenant_id);
+                Ok(TenantCredential::new(
+                    name,
+                    options.tenant_id.clone(),
+                    Box::new(move |tenant_id| {
+                        let mut options = options.clone();
+                        options.tenant_id = tenant_id.map(ToOwned::to_owned).or(options.tenant_id);
+                        Ok(AzurePowerShellCredential::new(Some(options))?)
+                    }),
+                )?)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::VisualStudioCode => {
+                let mut options = self
+                    .visual_studio_code_credential_options
+                    .take()
+                    .unwrap_or_else(|| VisualStudioCodeCredentialOptions {
+                        client_options: self.shared_client_options(),
+                        ..VisualStudioCodeCredentialOptions::default()
+                    });
+                options.tenant_id = self.source_tenant_id(options.tenant_id);
+                Ok(TenantCredential::new(
+                    name,
+                    options.tenant_id.clone(),
+                    Box::new(move |tenant_id| {
+                        let mut options = options.clone();
+                        options.tenant_id = tenant_id.map(ToOwned::to_owned).or(options.tenant_id);
+                        Ok(VisualStudioCodeCredential::new(Some(options))?)
+                    }),
+                )?)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            DefaultAzureCredentialSource::AzureauthCli => self.create_azureauth_cli_credential(),
+            DefaultAzureCredentialSource::DeviceCode => {
+                self.create_device_code_credential(environment)
+            }
+        }
+    }
+
+    fn create_environment_credential(
+        &mut self,
+        environment: &EnvironmentSettings,
+    ) -> azure_core::Result<Arc<dyn TokenCredential>> {
+        let client_options = self.client_secret_credential_options.take().map_or_else(
+            || self.shared_client_options(),
+            |options| options.client_options,
+        );
+        let environment = environment.with_tenant_id(self.tenant_id.as_deref());
+        Ok(TenantCredential::new(
+            DefaultAzureCredentialSource::Environment.credential_name(),
+            environment.tenant_id().map(ToOwned::to_owned),
+            Box::new(move |tenant_id| {
+                Ok(EnvironmentCredential::from_settings(
+                    &environment.with_tenant_id(tenant_id),
+                    Some(ClientSecretCredentialOptions {
+                        client_options: client_options.clone(),
+                    }),
+                )?)
+            }),
+        )?)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn create_azureauth_cli_credential(&mut self) -> azure_core::Result<Arc<dyn TokenCredential>> {
+        let source = DefaultAzureCredentialSource::AzureauthCli;
+        let config = self.interactive_config(source, self.azureauth_cli_credential.clone())?;
+        let options = self.azureauth_cli_credential_options.take();
+        Ok(TenantCredential::new(
+            source.credential_name(),
+            Some(config.tenant_id.clone()),
+            Box::new(move |tenant_id| {
+                Ok(AzureauthCliCredential::new_with_options(
+                    tenant_id.unwrap_or(&config.tenant_id),
+                    config.client_id.as_str(),
+                    options.clone(),
+                )?)
+            }),
+        )?)
+    }
+
+    fn create_device_code_credential(
+        &self,
+        environment: &EnvironmentSettings,
+    ) -> azure_core::Result<Arc<dyn TokenCredential>> {
+        let source = DefaultAzureCredentialSource::DeviceCode;
+        let config = self.interactive_config(source, self.device_code_credential.clone())?;
+        let options = DeviceCodeCredentialOptions {
+            client_options: self.shared_client_options(),
+            authority_host: environment.authority_host()?,
+        };
+        Ok(TenantCredential::new(
+            source.credential_name(),
+            Some(config.tenant_id.clone()),
+            Box::new(move |tenant_id| {
+                Ok(DeviceCodeCredential::new_with_options(
+                    tenant_id.unwrap_or(&config.tenant_id),
+                    config.client_id.as_str(),
+                    Some(options.clone()),
+                )?)
+            }),
+        )?)
+    }
+
+    /// The configuration of an opt-in interactive source, authenticating in
+    /// the builder's tenant when one is set.
+    fn interactive_config(
+        &self,
+        source: DefaultAzureCredentialSource,
+        config: Option<InteractiveCredentialConfig>,
+    ) -> azure_core::Result<InteractiveCredentialConfig> {
+        let config = config.ok_or_else(|| not_configured(source))?;
+        Ok(InteractiveCredentialConfig {
+            tenant_id: self.tenant_id.clone().unwrap_or(config.tenant_id),
+            ..config
+        })
+    }
+
+    fn create_workload_identity_credential(
+        &mut self,
+        environment: &EnvironmentSettings,
+    ) -> azure_core::Result<Arc<dyn TokenCredential>> {
+        let WorkloadIdentityCredentialOptions {
+            credential_options,
+            client_id,
+            tenant_id,
+            token_file_path,
+            ..
+        } = self
+            .workload_identity_credential_options
+            .take()
+            .unwrap_or_else(|| WorkloadIdentityCredentialOptions {
+                credential_options: ClientAssertionCredentialOptions {
+                    client_options: self.shared_client_options(),
+                },
+                ..WorkloadIdentityCredentialOptions::default()
+            });
+        let client_options = credential_options.client_options;
+        let environment = environment.with_tenant_id(self.tenant_id.as_deref());
+        let home_tenant_id = tenant_id
+            .clone()
+            .or_else(|| environment.tenant_id().map(ToOwned::to_owned));
+        Ok(TenantCredential::new(
+            DefaultAzureCredentialSource::WorkloadIdentity.credential_name(),
+            home_tenant_id,
+            Box::new(move |requested| {
+                Ok(create_workload_identity_credential(
+                    &environment,
+                    Some(WorkloadIdentityCredentialOptions {
+                        credential_options: ClientAssertionCredentialOptions {
+                            client_options: client_options.clone(),
+                        },
+                        client_id: client_id.clone(),
+                        tenant_id: requested
+                            .map(ToOwned::to_owned)
+                            .or_else(|| tenant_id.clone()),
+                        token_file_path: token_file_path.clone(),
+                    }),
+                )?)
+            }),
+        )?)
+    }
+
+    fn create_github_actions_credential(
+        &self,
+        environment: &EnvironmentSettings,
+    ) -> azure_core::Result<Arc<dyn TokenCredential>> {
+        let environment = environment.with_tenant_id(self.tenant_id.as_deref());
+        let (Some(tenant_id), Some(client_id)) = (environment.tenant_id(), environment.client_id())
+        else {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                "GitHubActionsCredential requires AZURE_TENANT_ID and AZURE_CLIENT_ID",
+            ));
+        };
+        let (tenant_id, client_id) = (tenant_id.to_owned(), client_id.to_owned());
+        let options = GitHubActionsCredentialOptions {
+            client_options: self.shared_client_options(),
+            authority_host: environment.authority_host()?,
+        };
+        Ok(TenantCredential::new(
+            DefaultAzureCredentialSource::GitHubActions.credential_name(),
+            Some(tenant_id.clone()),
+            Box::new(move |requested| {
+                Ok(GitHubActionsCredential::new(
+                    requested.unwrap_or(&tenant_id),
+                    client_id.clone(),
+                    Some(options.clone()),
+                )?)
+            }),
+        )?)
+    }
+
+    /// Managed identities authenticate in the tenant they belong to, so
+    /// requests for any tenant other than the configured one are rejected.
+    fn create_managed_identity_credential(
+        &mut self,
+        environment: &EnvironmentSettings,
+    ) -> azure_core::Result<Arc<dyn TokenCredential>> {
+        let options = self
+            .managed_identity_credential_options
+            .take()
+            .unwrap_or_else(|| ManagedIdentityCredentialOptions {
+                client_options: self.shared_client_options(),
+                ..managed_identity_credential_options(environment, None)
+            });
+        let client_options = options.client_options.clone();
+        let mut credential: Arc<dyn TokenCredential> =
+            ManagedIdentityCredential::new(Some(options))?;
+        if imds::uses_imds() {
+            credential = Arc::new(ProbedManagedIdentityCredential::new(
+                credential,
+                imds::IMDS_ENDPOINT.to_owned(),
+                self.managed_identity_probe_timeout,
+                client_options,
+            ));
+        }
+        let tenant_id = self
+            .tenant_id
+            .clone()
+            .or_else(|| environment.tenant_id().map(ToOwned::to_owned));
+        Ok(TenantCredential::fixed(
+            DefaultAzureCredentialSource::ManagedIdentity.credential_name(),
+            tenant_id,
+            credential,
+        ))
+    }
+
+    fn shared_client_options(&self) -> ClientOptions {
+        self.client_options.clone().unwrap_or_default()
+    }
+
+    #[cfg(test)]
+    fn included(&self) -> Vec<DefaultAzureCredentialSource> {
+        self.sources.clone()
+    }
+}
+
+/// Recreates `DefaultAzureCredential` using the currently supported Rust credential types.
+///
+/// The following credential sources are attempted in order on non-`wasm32` targets:
+/// - environment-backed authentication (`ClientSecretCredential`)
+/// - `WorkloadIdentityCredential`
+/// - [`GitHubActionsCredential`], only when `ACTIONS_ID_TOKEN_REQUEST_URL` and
+///   `ACTIONS_ID_TOKEN_REQUEST_TOKEN` are set
+/// - `ManagedIdentityCredential`, after a short IMDS availability probe
+/// - `AzureCliCredential`
+/// - `AzureDeveloperCliCredential`
+/// - [`AzurePowerShellCredential`]
+/// - [`VisualStudioCodeCredential`]
+///
+/// On `wasm32`, the following credential sources are attempted in order:
+/// - environment-backed authentication (`ClientSecretCredential`)
+/// - `WorkloadIdentityCredential`
+/// - [`GitHubActionsCredential`], only when `ACTIONS_ID_TOKEN_REQUEST_URL` and
+///   `ACTIONS_ID_TOKEN_REQUEST_TOKEN` are set
+/// - `ManagedIdentityCredential`
+///
+/// [`AzureauthCliCredential`] and [`DeviceCodeCredential`] can be added to the
+/// chain at a chosen position through
+/// [`DefaultAzureCredentialBuilder::include_azureauth_cli_credential`] and
+/// [`DefaultAzureCredentialBuilder::include_device_code_credential`].
+/// Your own credentials can be inserted with
+/// [`DefaultAzureCredentialBuilder::insert_source_before`],
+/// [`DefaultAzureCredentialBuilder::insert_source_after`] and
+/// [`DefaultAzureCredentialBuilder::push_custom_source`], and the built-in
+/// sources reordered with [`DefaultAzureCredentialBuilder::with_source_order`].
+///
+/// When authentication fails, [`DefaultAzureCredentialBuilder::diagnose`]
+/// explains which sources are usable and how to fix the others.
+///
+/// Setting `AZURE_TOKEN_CREDENTIALS` to `dev`, `prod`, or a single credential
+/// type name restricts the sources that are attempted.  See
+/// [`DefaultAzureCredentialBuilder::with_token_credentials`].
+///
+/// Tokens can only be requested for the configured tenant or for tenants
+/// allowed through
+/// [`DefaultAzureCredentialBuilder::with_additionally_allowed_tenants`] or
+/// `AZURE_ADDITIONALLY_ALLOWED_TENANTS`.  Each source authenticates in the requested
+/// tenant, except the managed identity source, which only authenticates in the
+/// tenant of its identity.
+///
+/// With [`DefaultAzureCredentialBuilder::defer_construction`], the sources are
+/// constructed on the first token request and construction errors are
+/// reported there.
+#[derive(Debug)]
+pub struct DefaultAzureCredential {
+    state: CredentialState,
+    tenant_policy: TenantPolicy,
+}
+
+#[derive(Debug)]
+enum CredentialState {
+    Built(ChainedTokenCredential),
+    Deferred {
+        builder: Mutex<Option<Box<DefaultAzureCredentialBuilder>>>,
+        credential: OnceCell<Result<ChainedTokenCredential, String>>,
+    },
+}
+
+impl DefaultAzureCredential {
+    /// Create a `DefaultAzureCredential` with the default chain order.
+    pub fn new() -> azure_core::Result<Arc<Self>> {
+        DefaultAzureCredentialBuilder::new().build()
+    }
+
+    #[must_use]
+    /// Create a builder for a customized `DefaultAzureCredential`.
+    pub fn builder() -> DefaultAzureCredentialBuilder {
         DefaultAzureCredentialBuilder::new()
     }
 }
@@ -344,13 +1444,68 @@ impl TokenCredential for DefaultAzureCredential {
         scopes: &[&str],
         options: Option<TokenRequestOptions<'_>>,
     ) -> azure_core::Result<AccessToken> {
-        TokenCredential::get_token(&self.credential, scopes, options).await
+        self.tenant_policy.check(options.as_ref())?;
+        let credential = match &self.state {
+            CredentialState::Built(credential) => credential,
+            CredentialState::Deferred {
+                builder,
+                credential,
+            } => {
+                let credential = credential
+                    .get_or_init(|| async {
+                        let builder = builder.lock().await.take().ok_or_else(|| {
+                            "DefaultAzureCredential construction was interrupted".to_owned()
+                        })?;
+                        builder
+                            .build_chain()
+                            .map_err(|error| format_aggregate_error(&[error]))
+                    })
+                    .await;
+                match credential {
+                    Ok(credential) => credential,
+                    Err(message) => {
+                        return Err(Error::with_message(ErrorKind::Credential, message.clone()));
+                    }
+                }
+            }
+        };
+        TokenCredential::get_token(credential, scopes, options).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::MockPolicy;
+    use azure_core::{credentials::Secret, time::OffsetDateTime};
+
+    #[derive(Debug)]
+    struct StaticCredential(&'static str);
+
+    #[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+    impl TokenCredential for StaticCredential {
+        async fn get_token(
+            &self,
+            _scopes: &[&str],
+            _options: Option<TokenRequestOptions<'_>>,
+        ) -> azure_core::Result<AccessToken> {
+            Ok(AccessToken {
+                token: Secret::new(self.0),
+                expires_on: OffsetDateTime::now_utc() + azure_core::time::Duration::hours(1),
+            })
+        }
+    }
+
+    fn entry_names(entries: &[ChainEntry]) -> Vec<String> {
+        entries
+            .iter()
+            .map(|entry| match entry {
+                ChainEntry::BuiltIn(source) => source.credential_name().to_owned(),
+                ChainEntry::Custom(credential) => format!("{credential:?}"),
+            })
+            .collect()
+    }
 
     #[test]
     fn default_builder_included_credential_types() {
@@ -364,6 +1519,8 @@ mod tests {
                 DefaultAzureCredentialSource::ManagedIdentity,
                 DefaultAzureCredentialSource::AzureCli,
                 DefaultAzureCredentialSource::AzureDeveloperCli,
+                DefaultAzureCredentialSource::AzurePowerShell,
+                DefaultAzureCredentialSource::VisualStudioCode,
             ]
         );
         #[cfg(target_arch = "wasm32")]
@@ -377,16 +1534,84 @@ mod tests {
         );
     }
 
+    fn github_actions_variables(name: &str) -> Option<String> {
+        match name {
+            "ACTIONS_ID_TOKEN_REQUEST_URL" => Some("https://example.com/idtoken".to_owned()),
+            "ACTIONS_ID_TOKEN_REQUEST_TOKEN" => Some("fake-request-token".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn github_actions_source_is_added_after_workload_identity_in_a_job() {
+        let builder = DefaultAzureCredentialBuilder::new();
+
+        let sources = builder.chain_sources(github_actions_variables);
+
+        assert_eq!(
+            sources.get(..4),
+            Some(
+                &[
+                    DefaultAzureCredentialSource::Environment,
+                    DefaultAzureCredentialSource::WorkloadIdentity,
+                    DefaultAzureCredentialSource::GitHubActions,
+                    DefaultAzureCredentialSource::ManagedIdentity,
+                ][..]
+            )
+        );
+    }
+
+    #[test]
+    fn github_actions_source_is_skipped_without_id_token_variables() {
+        let builder = DefaultAzureCredentialBuilder::new();
+
+        let sources = builder.chain_sources(|name| {
+            (name == "ACTIONS_ID_TOKEN_REQUEST_URL")
+                .then(|| "https://example.com/idtoken".to_owned())
+        });
+
+        assert!(!sources.contains(&DefaultAzureCredentialSource::GitHubActions));
+        assert_eq!(sources, builder.included());
+    }
+
+    #[test]
+    fn excluded_github_actions_source_is_not_added_in_a_job() {
+        let builder = DefaultAzureCredentialBuilder::new().exclude_github_actions_credential();
+
+        let sources = builder.chain_sources(github_actions_variables);
+
+        assert!(!sources.contains(&DefaultAzureCredentialSource::GitHubActions));
+    }
+
+    #[test]
+    fn github_actions_source_uses_environment_settings() {
+        let result = DefaultAzureCredentialBuilder::new()
+            .with_source_order([DefaultAzureCredentialSource::GitHubActions])
+            .with_environment_settings(EnvironmentSettings::from_map([(
+                "AZURE_CLIENT_ID",
+                "fake-client",
+            )]))
+            .build();
+
+        assert!(matches!(
+            result,
+            Err(ref error) if error.to_string().contains("GitHubActionsCredential requires AZURE_TENANT_ID")
+        ));
+    }
+
     #[test]
     fn excluded_credential_types() {
         let builder = DefaultAzureCredentialBuilder::new()
             .exclude_environment_credential()
             .exclude_workload_identity_credential()
+            .exclude_github_actions_credential()
             .exclude_managed_identity_credential();
         #[cfg(not(target_arch = "wasm32"))]
         let builder = builder
             .exclude_azure_cli_credential()
-            .exclude_azure_developer_cli_credential();
+            .exclude_azure_developer_cli_credential()
+            .exclude_azure_powershell_credential()
+            .exclude_visual_studio_code_credential();
 
         assert!(builder.included().is_empty());
     }
@@ -396,11 +1621,14 @@ mod tests {
         let builder = DefaultAzureCredentialBuilder::new()
             .exclude_environment_credential()
             .exclude_workload_identity_credential()
+            .exclude_github_actions_credential()
             .exclude_managed_identity_credential();
         #[cfg(not(target_arch = "wasm32"))]
         let builder = builder
             .exclude_azure_cli_credential()
-            .exclude_azure_developer_cli_credential();
+            .exclude_azure_developer_cli_credential()
+            .exclude_azure_powershell_credential()
+            .exclude_visual_studio_code_credential();
 
         let result = builder.build();
 
@@ -409,4 +1637,568 @@ mod tests {
             Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
         ));
     }
+
+    #[test]
+    fn token_credentials_prod_selects_deployed_sources() -> azure_core::Result<()> {
+        let sources = select_token_credentials(default_azure_credential_sources(), "prod")?;
+
+        assert_eq!(
+            sources,
+            vec![
+                DefaultAzureCredentialSource::Environment,
+                DefaultAzureCredentialSource::WorkloadIdentity,
+                DefaultAzureCredentialSource::ManagedIdentity,
+            ]
+        );
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn token_credentials_dev_selects_developer_tools() -> azure_core::Result<()> {
+        let sources = select_token_credentials(default_azure_credential_sources(), " Dev ")?;
+
+        assert_eq!(
+            sources,
+            vec![
+                DefaultAzureCredentialSource::AzureCli,
+                DefaultAzureCredentialSource::AzureDeveloperCli,
+                DefaultAzureCredentialSource::AzurePowerShell,
+                DefaultAzureCredentialSource::VisualStudioCode,
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn token_credentials_selects_single_credential() -> azure_core::Result<()> {
+        let sources = select_token_credentials(
+            default_azure_credential_sources(),
+            "managedidentitycredential",
+        )?;
+
+        assert_eq!(sources, vec![DefaultAzureCredentialSource::ManagedIdentity]);
+        Ok(())
+    }
+
+    #[test]
+    fn token_credentials_empty_value_keeps_sources() -> azure_core::Result<()> {
+        let sources = select_token_credentials(default_azure_credential_sources(), "")?;
+
+        assert_eq!(sources, default_azure_credential_sources());
+        Ok(())
+    }
+
+    #[test]
+    fn token_credentials_unknown_value_returns_credential_error() {
+        let result = select_token_credentials(default_azure_credential_sources(), "SomethingElse");
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+    }
+
+    #[test]
+    fn build_with_unknown_token_credentials_returns_credential_error() {
+        let result = DefaultAzureCredentialBuilder::new()
+            .with_token_credentials("SomethingElse")
+            .build();
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+    }
+
+    #[test]
+    fn build_reads_token_credentials_from_environment_settings() {
+        let result = DefaultAzureCredentialBuilder::new()
+            .with_environment_settings(EnvironmentSettings::from_map([(
+                AZURE_TOKEN_CREDENTIALS,
+                "SomethingElse",
+            )]))
+            .build();
+
+        assert!(matches!(
+            result,
+            Err(ref error) if error.to_string().contains("SomethingElse")
+        ));
+    }
+
+    #[test]
+    fn build_with_token_credentials_for_excluded_source_returns_credential_error() {
+        let result = DefaultAzureCredentialBuilder::new()
+            .exclude_managed_identity_credential()
+            .with_token_credentials("ManagedIdentityCredential")
+            .build();
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn opt_in_sources_are_placed_at_requested_positions() {
+        let builder = DefaultAzureCredentialBuilder::new()
+            .include_azureauth_cli_credential(
+                "fake-tenant",
+                "fake-client",
+                DefaultAzureCredentialSourcePosition::After(DefaultAzureCredentialSource::AzureCli),
+            )
+            .include_device_code_credential(
+                "fake-tenant",
+                "fake-client",
+                DefaultAzureCredentialSourcePosition::Last,
+            );
+
+        assert_eq!(
+            builder.included(),
+            vec![
+                DefaultAzureCredentialSource::Environment,
+                DefaultAzureCredentialSource::WorkloadIdentity,
+                DefaultAzureCredentialSource::ManagedIdentity,
+                DefaultAzureCredentialSource::AzureCli,
+                DefaultAzureCredentialSource::AzureauthCli,
+                DefaultAzureCredentialSource::AzureDeveloperCli,
+                DefaultAzureCredentialSource::AzurePowerShell,
+                DefaultAzureCredentialSource::VisualStudioCode,
+                DefaultAzureCredentialSource::DeviceCode,
+            ]
+        );
+    }
+
+    #[test]
+    fn including_opt_in_source_again_moves_it() {
+        let builder = DefaultAzureCredentialBuilder::new()
+            .include_device_code_credential(
+                "fake-tenant",
+                "fake-client",
+                DefaultAzureCredentialSourcePosition::Last,
+            )
+            .include_device_code_credential(
+                "fake-tenant",
+                "fake-client",
+                DefaultAzureCredentialSourcePosition::Before(
+                    DefaultAzureCredentialSource::ManagedIdentity,
+                ),
+            );
+
+        let included = builder.included();
+        assert_eq!(
+            included
+                .iter()
+                .filter(|source| **source == DefaultAzureCredentialSource::DeviceCode)
+                .count(),
+            1
+        );
+        assert_eq!(
+            included.get(2),
+            Some(&DefaultAzureCredentialSource::DeviceCode)
+        );
+    }
+
+    #[test]
+    fn build_with_only_device_code_credential() -> azure_core::Result<()> {
+        let builder = DefaultAzureCredentialBuilder::new()
+            .exclude_environment_credential()
+            .exclude_workload_identity_credential()
+            .exclude_github_actions_credential()
+            .exclude_managed_identity_credential();
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = builder
+            .exclude_azure_cli_credential()
+            .exclude_azure_developer_cli_credential()
+            .exclude_azure_powershell_credential()
+            .exclude_visual_studio_code_credential();
+
+        builder
+            .include_device_code_credential(
+                "fake-tenant",
+                "fake-client",
+                DefaultAzureCredentialSourcePosition::First,
+            )
+            .build()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_credentials_dev_keeps_opt_in_sources() -> azure_core::Result<()> {
+        let mut sources = default_azure_credential_sources();
+        insert_source(
+            &mut sources,
+            DefaultAzureCredentialSource::DeviceCode,
+            DefaultAzureCredentialSourcePosition::First,
+        );
+
+        let sources = select_token_credentials(sources, "DeviceCodeCredential")?;
+
+        assert_eq!(sources, vec![DefaultAzureCredentialSource::DeviceCode]);
+        Ok(())
+    }
+
+    #[test]
+    fn custom_sources_are_placed_relative_to_built_in_sources() {
+        let mut builder = DefaultAzureCredentialBuilder::new()
+            .with_source_order([
+                DefaultAzureCredentialSource::ManagedIdentity,
+                DefaultAzureCredentialSource::Environment,
+            ])
+            .push_custom_source(Arc::new(StaticCredential("last")))
+            .insert_source_before(
+                DefaultAzureCredentialSource::Environment,
+                Arc::new(StaticCredential("before-environment")),
+            )
+            .insert_source_after(
+                DefaultAzureCredentialSource::ManagedIdentity,
+                Arc::new(StaticCredential("after-managed-identity")),
+            )
+            .insert_source_after(
+                DefaultAzureCredentialSource::WorkloadIdentity,
+                Arc::new(StaticCredential("missing-anchor")),
+            );
+
+        let sources = builder.included();
+        let entries = builder.chain_entries(sources);
+
+        assert_eq!(
+            entry_names(&entries),
+            vec![
+                "ManagedIdentityCredential",
+                "StaticCredential(\"after-managed-identity\")",
+                "StaticCredential(\"before-environment\")",
+                "EnvironmentCredential",
+                "StaticCredential(\"last\")",
+                "StaticCredential(\"missing-anchor\")",
+            ]
+        );
+    }
+
+    #[test]
+    fn source_order_replaces_built_in_sources() {
+        let builder = DefaultAzureCredentialBuilder::new().with_source_order([
+            DefaultAzureCredentialSource::ManagedIdentity,
+            DefaultAzureCredentialSource::Environment,
+            DefaultAzureCredentialSource::ManagedIdentity,
+        ]);
+
+        assert_eq!(
+            builder.included(),
+            vec![
+                DefaultAzureCredentialSource::Environment,
+                DefaultAzureCredentialSource::ManagedIdentity,
+            ]
+        );
+    }
+
+    #[test]
+    fn hosting_environment_orders_sources() {
+        let builder = DefaultAzureCredentialBuilder::new()
+            .with_hosting_environment(HostingEnvironment::AksWorkloadIdentity);
+
+        assert_eq!(
+            builder.included(),
+            vec![
+                DefaultAzureCredentialSource::WorkloadIdentity,
+                DefaultAzureCredentialSource::Environment,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn build_with_only_custom_source() -> azure_core::Result<()> {
+        let credential = DefaultAzureCredentialBuilder::new()
+            .with_source_order([])
+            .with_token_credentials("prod")
+            .push_custom_source(Arc::new(StaticCredential("custom")))
+            .build()?;
+
+        let token = credential
+            .get_token(&["https://management.azure.com/.default"], None)
+            .await?;
+
+        assert_eq!(token.token.secret(), "custom");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn client_options_reach_device_code_source() -> azure_core::Result<()> {
+        let policy = MockPolicy::new(vec![
+            (
+                "/devicecode",
+                r#"{"device_code":"device","user_code":"USER","verification_uri":"https://microsoft.com/devicelogin","expires_in":900,"interval":0,"message":"sign in"}"#,
+            ),
+            (
+                "/token",
+                r#"{"token_type":"Bearer","scope":"https://management.azure.com/.default","expires_in":3600,"access_token":"device-code-token"}"#,
+            ),
+        ]);
+        let credential = DefaultAzureCredentialBuilder::new()
+            .with_source_order([])
+            .with_token_credentials("DeviceCodeCredential")
+            .include_device_code_credential(
+                "fake-tenant",
+                "fake-client",
+                DefaultAzureCredentialSourcePosition::First,
+            )
+            .with_client_options(policy.client_options())
+            .build()?;
+
+        let token = credential
+            .get_token(&["https://management.azure.com/.default"], None)
+            .await?;
+
+        assert_eq!(token.token.secret(), "device-code-token");
+        assert_eq!(
+            policy.requests(),
+            vec![
+                "https://login.microsoftonline.com/fake-tenant/oauth2/v2.0/devicecode",
+                "https://login.microsoftonline.com/fake-tenant/oauth2/v2.0/token",
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn diagnose_reports_sources_in_order() {
+        let report = DefaultAzureCredentialBuilder::new()
+            .with_source_order([
+                DefaultAzureCredentialSource::DeviceCode,
+                DefaultAzureCredentialSource::Environment,
+            ])
+            .with_token_credentials("EnvironmentCredential")
+            .push_custom_source(Arc::new(StaticCredential("custom")))
+            .diagnose()
+            .await;
+
+        let statuses = report
+            .sources
+            .iter()
+            .map(|source| (source.source, source.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses.first(),
+            Some(&(
+                DefaultAzureCredentialSource::DeviceCode,
+                diagnostics::SourceStatus::Excluded
+            ))
+        );
+        assert_eq!(
+            statuses.get(1).map(|(source, _)| *source),
+            Some(DefaultAzureCredentialSource::Environment)
+        );
+        assert_eq!(report.custom_sources, 1);
+        assert!(report.has_ready_source());
+    }
+
+    #[tokio::test]
+    async fn diagnose_reads_environment_settings() {
+        let report = DefaultAzureCredentialBuilder::new()
+            .with_source_order([DefaultAzureCredentialSource::Environment])
+            .with_environment_settings(EnvironmentSettings::from_map([
+                ("AZURE_TENANT_ID", "fake-tenant"),
+                ("AZURE_CLIENT_ID", "fake-client"),
+                ("AZURE_CLIENT_SECRET", "fake-secret"),
+            ]))
+            .diagnose()
+            .await;
+
+        assert!(report.has_ready_source());
+        assert!(report.environment.iter().any(|variable| {
+            variable.name == "AZURE_CLIENT_ID" && variable.value.as_deref() == Some("fake-client")
+        }));
+        assert!(
+            !report
+                .environment
+                .iter()
+                .any(|variable| variable.value.as_deref() == Some("fake-secret"))
+        );
+    }
+
+    #[tokio::test]
+    async fn deferred_build_reports_errors_on_get_token() -> azure_core::Result<()> {
+        let credential = DefaultAzureCredentialBuilder::new()
+            .with_token_credentials("SomethingElse")
+            .defer_construction()
+            .build()?;
+
+        let result = credential
+            .get_token(&["https://management.azure.com/.default"], None)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("SomethingElse")
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deferred_construction_errors_join_the_aggregate_error() -> azure_core::Result<()> {
+        let credential = DefaultAzureCredentialBuilder::new()
+            .with_source_order([DefaultAzureCredentialSource::DeviceCode])
+            .defer_construction()
+            .build()?;
+
+        let result = credential
+            .get_token(&["https://management.azure.com/.default"], None)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("DeviceCodeCredential was not configured")
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn deferred_chain_continues_past_unconstructed_sources() -> azure_core::Result<()> {
+        let credential = DefaultAzureCredentialBuilder::new()
+            .with_source_order([DefaultAzureCredentialSource::DeviceCode])
+            .push_custom_source(Arc::new(StaticCredential("custom")))
+            .defer_construction()
+            .build()?;
+
+        let token = credential
+            .get_token(&["https://management.azure.com/.default"], None)
+            .await?;
+
+        assert_eq!(token.token.secret(), "custom");
+        Ok(())
+    }
+
+    fn tenant_request(tenant_id: &str) -> TokenRequestOptions<'static> {
+        let mut options = TokenRequestOptions::default();
+        options
+            .method_options
+            .context
+            .insert(crate::tenant::RequestedTenant::new(tenant_id));
+        options
+    }
+
+    #[tokio::test]
+    async fn requests_for_other_tenants_are_rejected() -> azure_core::Result<()> {
+        let credential = DefaultAzureCredentialBuilder::new()
+            .with_source_order([])
+            .with_tenant_id("home")
+            .with_additionally_allowed_tenants(["allowed"])
+            .push_custom_source(Arc::new(StaticCredential("custom")))
+            .build()?;
+        let scopes = ["https://management.azure.com/.default"];
+
+        credential
+            .get_token(&scopes, Some(tenant_request("home")))
+            .await?;
+        credential
+            .get_token(&scopes, Some(tenant_request("allowed")))
+            .await?;
+        let result = credential
+            .get_token(&scopes, Some(tenant_request("other")))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("'other' is not allowed")
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn device_code_source_authenticates_in_requested_tenant() -> azure_core::Result<()> {
+        let policy = MockPolicy::new(vec![
+            (
+                "/devicecode",
+                r#"{"device_code":"device","user_code":"USER","verification_uri":"https://microsoft.com/devicelogin","expires_in":900,"interval":0,"message":"sign in"}"#,
+            ),
+            (
+                "/token",
+                r#"{"token_type":"Bearer","scope":"https://management.azure.com/.default","expires_in":3600,"access_token":"device-code-token"}"#,
+            ),
+        ]);
+        let credential = DefaultAzureCredentialBuilder::new()
+            .with_source_order([])
+            .with_token_credentials("DeviceCodeCredential")
+            .include_device_code_credential(
+                "fake-tenant",
+                "fake-client",
+                DefaultAzureCredentialSourcePosition::First,
+            )
+            .with_additionally_allowed_tenants(["other-tenant"])
+            .with_client_options(policy.client_options())
+            .build()?;
+
+        credential
+            .get_token(
+                &["https://management.azure.com/.default"],
+                Some(tenant_request("other-tenant")),
+            )
+            .await?;
+
+        assert_eq!(
+            policy.requests(),
+            vec![
+                "https://login.microsoftonline.com/other-tenant/oauth2/v2.0/devicecode",
+                "https://login.microsoftonline.com/other-tenant/oauth2/v2.0/token",
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn environment_source_uses_builder_and_requested_tenants() -> azure_core::Result<()> {
+        let policy = MockPolicy::new(vec![(
+            "/token",
+            r#"{"token_type":"Bearer","expires_in":3600,"access_token":"environment-token"}"#,
+        )]);
+        let credential = DefaultAzureCredentialBuilder::new()
+            .with_source_order([DefaultAzureCredentialSource::Environment])
+            .with_environment_settings(EnvironmentSettings::from_map([
+                ("AZURE_TENANT_ID", "environment-tenant"),
+                ("AZURE_CLIENT_ID", "fake-client"),
+                ("AZURE_CLIENT_SECRET", "fake-secret"),
+            ]))
+            .with_tenant_id("home")
+            .with_additionally_allowed_tenants(["other"])
+            .with_client_options(policy.client_options())
+            .build()?;
+        let scopes = ["https://management.azure.com/.default"];
+
+        credential.get_token(&scopes, None).await?;
+        credential
+            .get_token(&scopes, Some(tenant_request("other")))
+            .await?;
+
+        assert_eq!(
+            policy.requests(),
+            vec![
+                "https://login.microsoftonline.com/home/oauth2/v2.0/token",
+                "https://login.microsoftonline.com/other/oauth2/v2.0/token",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn additionally_allowed_tenants_fall_back_to_environment() -> azure_core::Result<()> {
+        let environment = EnvironmentSettings::from_map([(
+            crate::tenant::AZURE_ADDITIONALLY_ALLOWED_TENANTS,
+            "first;second",
+        )]);
+        let policy = DefaultAzureCredentialBuilder::new().tenant_policy(&environment);
+
+        policy.check(Some(&tenant_request("second")))?;
+
+        let policy = DefaultAzureCredentialBuilder::new()
+            .with_additionally_allowed_tenants(["third"])
+            .tenant_policy(&environment);
+
+        assert!(policy.check(Some(&tenant_request("second"))).is_err());
+        Ok(())
+    }
 }
@@ -8,6 +8,7 @@
 //! You can learn more about this authorization flow [here](https://docs.microsoft.com/azure/active-directory/develop/v2-oauth2-device-code).
 mod device_code_responses;
 
+use crate::authority_host::AuthorityHost;
 use azure_core::{
     error::{Error, ErrorKind},
     http::{
@@ -24,6 +25,16 @@ use std::{borrow::Cow, pin::Pin};
 use time::Duration;
 use url::form_urlencoded;
 
+/// Options for [`start_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct StartOptions {
+    /// Options for the HTTP pipeline used to start the flow and poll for the
+    /// token.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+}
+
 /// Start the device authorization grant flow.
 /// The user has only 15 minutes to sign in (the usual value for `expires_in`).
 pub async fn start<'a, 'b, T>(
@@ -31,18 +42,32 @@ pub async fn start<'a, 'b, T>(
     client_id: &str,
     scopes: &'b [&'b str],
 ) -> azure_core::Result<DeviceCodePhaseOneResponse<'a>>
+where
+    T: Into<Cow<'a, str>>,
+{
+    start_with_options(tenant_id, client_id, scopes, StartOptions::default()).await
+}
+
+/// Start the device authorization grant flow, using the provided options.
+pub async fn start_with_options<'a, 'b, T>(
+    tenant_id: T,
+    client_id: &str,
+    scopes: &'b [&'b str],
+    options: StartOptions,
+) -> azure_core::Result<DeviceCodePhaseOneResponse<'a>>
 where
     T: Into<Cow<'a, str>>,
 {
     let tenant_id = tenant_id.into();
-    let url = &format!("https://login.microsoftonline.com/{tenant_id}/oauth2/v2.0/devicecode");
+    let authority_host = AuthorityHost::or_from_env(options.authority_host)?;
+    let url = authority_host.endpoint(&tenant_id, "devicecode")?;
 
     let encoded = form_urlencoded::Serializer::new(String::new())
         .append_pair("client_id", client_id)
         .append_pair("scope", &scopes.join(" "))
         .finish();
 
-    let rsp = post_form(url, encoded).await?;
+    let rsp = post_form(&options.client_options, url, encoded).await?;
     let rsp_status = rsp.status();
     if !rsp_status.is_success() {
         let rsp_body = rsp.into_body().into_string()?;
@@ -64,6 +89,8 @@ where
         message: device_code_response.message,
         tenant_id,
         client_id: client_id.to_string(),
+        client_options: options.client_options,
+        authority_host,
     })
 }
 
@@ -83,6 +110,10 @@ pub struct DeviceCodePhaseOneResponse<'a> {
     // does not implement Default, and it's in another crate
     #[serde(skip)]
     client_id: String,
+    #[serde(skip)]
+    client_options: ClientOptions,
+    #[serde(skip)]
+    authority_host: AuthorityHost,
 }
 
 impl DeviceCodePhaseOneResponse<'_> {
@@ -123,10 +154,10 @@ impl DeviceCodePhaseOneResponse<'_> {
                     return None;
                 };
 
-                let url = &format!(
-                    "https://login.microsoftonline.com/{}/oauth2/v2.0/token",
-                    self.tenant_id,
-                );
+                let url = match self.authority_host.endpoint(&self.tenant_id, "token") {
+                    Ok(url) => url,
+                    Err(error) => return Some((Err(error), NextState::Finish)),
+                };
 
                 // Throttle as specified by Azure. `slow_down` responses bump
                 // this by 5 seconds for the next iteration (see below).
@@ -138,7 +169,7 @@ impl DeviceCodePhaseOneResponse<'_> {
                     .append_pair("device_code", &self.device_code)
                     .finish();
 
-                match post_form(url, encoded).await {
+                match post_form(&self.client_options, url, encoded).await {
                     Ok(rsp) => {
                         let rsp_status = rsp.status();
                         let rsp_body = match rsp.into_body().into_string() {
@@ -186,10 +217,13 @@ impl DeviceCodePhaseOneResponse<'_> {
     }
 }
 
-async fn post_form(url: &str, form_body: String) -> azure_core::Result<RawResponse> {
-    let pipeline = Pipeline::new(None, None, ClientOptions::default(), vec![], vec![], None);
+async fn post_form(
+    client_options: &ClientOptions,
+    url: Url,
+    form_body: String,
+) -> azure_core::Result<RawResponse> {
+    let pipeline = Pipeline::new(None, None, client_options.clone(), vec![], vec![], None);
 
-    let url = Url::parse(url)?;
     let mut req = Request::new(url, Method::Post);
     req.insert_header(
         headers::CONTENT_TYPE,
@@ -217,6 +251,8 @@ async fn post_form(url: &str, form_body: String) -> azure_core::Result<RawRespon
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::MockPolicy;
+    use futures::StreamExt;
 
     fn require_send<T: Send>(_t: T) {}
 
@@ -224,4 +260,40 @@ mod tests {
     fn ensure_that_start_is_send() {
         require_send(start("UNUSED", "UNUSED", &[]));
     }
+
+    #[tokio::test]
+    async fn flow_uses_authority_host() -> azure_core::Result<()> {
+        let policy = MockPolicy::new(vec![
+            (
+                "/devicecode",
+                r#"{"device_code":"device","user_code":"USER","verification_uri":"https://microsoft.com/deviceloginchina","expires_in":900,"interval":0,"message":"sign in"}"#,
+            ),
+            (
+                "/token",
+                r#"{"token_type":"Bearer","scope":"openid","expires_in":3600,"access_token":"token"}"#,
+            ),
+        ]);
+
+        let flow = start_with_options(
+            "fake-tenant",
+            "fake-client",
+            &["openid"],
+            StartOptions {
+                client_options: policy.client_options(),
+                authority_host: Some(AuthorityHost::China),
+            },
+        )
+        .await?;
+        let authorization = flow.stream().next().await.transpose()?;
+
+        assert!(authorization.is_some());
+        assert_eq!(
+            policy.requests(),
+            vec![
+                "https://login.chinacloudapi.cn/fake-tenant/oauth2/v2.0/devicecode",
+                "https://login.chinacloudapi.cn/fake-tenant/oauth2/v2.0/token",
+            ]
+        );
+        Ok(())
+    }
 }
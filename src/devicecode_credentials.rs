@@ -1,19 +1,36 @@
-use crate::{cache::TokenCache, device_code::start, refresh_token::exchange};
+use crate::{
+    authority_host::AuthorityHost,
+    cache::TokenCache,
+    device_code::{StartOptions, start_with_options},
+    refresh_token::{ExchangeOptions, exchange_with_options},
+};
 use async_lock::Mutex;
 use azure_core::{
     credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
     error::{Error, ErrorKind},
+    http::ClientOptions,
 };
 use futures::stream::StreamExt;
 use std::{collections::BTreeMap, str, sync::Arc, time::Duration};
 use time::OffsetDateTime;
 use tracing::debug;
 
+/// Options for constructing a [`DeviceCodeCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct DeviceCodeCredentialOptions {
+    /// Options for the HTTP pipeline used to sign in and refresh tokens.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+}
+
 #[derive(Debug)]
 /// Enables authentication to an Azure Client using a Device Code workflow.
 pub struct DeviceCodeCredential {
     tenant_id: String,
     client_id: String,
+    client_options: ClientOptions,
+    authority_host: AuthorityHost,
     cache: TokenCache,
     refresh_tokens: Mutex<BTreeMap<Vec<String>, Secret>>,
 }
@@ -25,9 +42,28 @@ impl DeviceCodeCredential {
         T: Into<String>,
         C: Into<String>,
     {
+        Self::new_with_options(tenant_id, client_id, None)
+    }
+
+    /// Create a new `DeviceCodeCredential` with the specified tenant ID, client ID, and options.
+    pub fn new_with_options<T, C>(
+        tenant_id: T,
+        client_id: C,
+        options: Option<DeviceCodeCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: Into<String>,
+        C: Into<String>,
+    {
+        let DeviceCodeCredentialOptions {
+            client_options,
+            authority_host,
+        } = options.unwrap_or_default();
         Ok(Arc::new(Self {
             tenant_id: tenant_id.into(),
             client_id: client_id.into(),
+            client_options,
+            authority_host: AuthorityHost::or_from_env(authority_host)?,
             cache: TokenCache::new(),
             refresh_tokens: Mutex::new(BTreeMap::new()),
         }))
@@ -41,11 +77,16 @@ impl DeviceCodeCredential {
         let scopes_owned = scopes.iter().map(ToString::to_string).collect::<Vec<_>>();
         let mut refresh_tokens = self.refresh_tokens.lock().await;
         if let Some(refresh_token) = refresh_tokens.remove(&scopes_owned) {
-            let response = exchange(
+            let response = exchange_with_options(
                 self.tenant_id.as_str(),
                 &self.client_id,
                 None,
                 &refresh_token,
+                ExchangeOptions {
+                    client_options: self.client_options.clone(),
+                    authority_host: Some(self.authority_host.clone()),
+                    ..ExchangeOptions::default()
+                },
             )
             .await?;
             let token = AccessToken {
@@ -56,7 +97,16 @@ impl DeviceCodeCredential {
             return Ok(token);
         }
 
-        let flow = start(self.tenant_id.clone(), self.client_id.as_str(), scopes).await?;
+        let flow = start_with_options(
+            self.tenant_id.clone(),
+            self.client_id.as_str(),
+            scopes,
+            StartOptions {
+                client_options: self.client_options.clone(),
+                authority_host: Some(self.authority_host.clone()),
+            },
+        )
+        .await?;
 
         eprintln!("{}", flow.message());
 
@@ -111,6 +161,6 @@ impl TokenCredential for DeviceCodeCredential {
     }
 }
 
-fn convert_expires_in(seconds: u64) -> OffsetDateTime {
+pub(crate) fn convert_expires_in(seconds: u64) -> OffsetDateTime {
     OffsetDateTime::now_utc() + Duration::new(seconds, 0)
 }
@@ -0,0 +1,752 @@
+//! Explain why [`DefaultAzureCredential`](crate::default_azure_credential::DefaultAzureCredential)
+//! can or cannot authenticate.
+//!
+//! A [`DiagnosticReport`] is produced by
+//! [`DefaultAzureCredentialBuilder::diagnose`](crate::default_azure_credential::DefaultAzureCredentialBuilder::diagnose).
+//! It inspects the environment, the tools on `PATH` and the managed identity
+//! endpoint without acquiring tokens, and renders as a human-readable summary
+//! with remediation hints through [`std::fmt::Display`].
+
+use crate::{
+    authority_host::AZURE_AUTHORITY_HOST,
+    default_azure_credential::DefaultAzureCredentialSource,
+    environment_credential::{
+        AZURE_CLIENT_CERTIFICATE_PASSWORD, AZURE_CLIENT_CERTIFICATE_PATH, AZURE_CLIENT_ID,
+        AZURE_CLIENT_SECRET, AZURE_CLIENT_SECRET_FILE, AZURE_CLIENT_SECRET_SECONDARY,
+        AZURE_CLIENT_SEND_CERTIFICATE_CHAIN, AZURE_FEDERATED_TOKEN_FILE,
+        AZURE_MANAGED_IDENTITY_OBJECT_ID, AZURE_MANAGED_IDENTITY_RESOURCE_ID, AZURE_PASSWORD,
+        AZURE_TENANT_ID, AZURE_TOKEN_CREDENTIALS, AZURE_USERNAME, EnvironmentSettings, REDACTED,
+        SettingSource,
+    },
+    github_actions_credential::{ACTIONS_ID_TOKEN_REQUEST_TOKEN, ACTIONS_ID_TOKEN_REQUEST_URL},
+    imds::{self, IDENTITY_ENDPOINT, MSI_ENDPOINT},
+    tenant::AZURE_ADDITIONALLY_ALLOWED_TENANTS,
+};
+use azure_core::{http::ClientOptions, time::Duration};
+use std::{env, fmt, path::Path};
+
+const IDENTITY_HEADER: &str = "IDENTITY_HEADER";
+const MSI_SECRET: &str = "MSI_SECRET";
+
+/// Environment variables reported by the diagnostics, and whether their
+/// values are secret.
+const ENVIRONMENT_VARIABLES: [(&str, bool); 22] = [
+    (AZURE_TENANT_ID, false),
+    (AZURE_CLIENT_ID, false),
+    (AZURE_CLIENT_SECRET, true),
+    (AZURE_CLIENT_SECRET_SECONDARY, true),
+    (AZURE_CLIENT_SECRET_FILE, false),
+    (AZURE_CLIENT_CERTIFICATE_PATH, false),
+    (AZURE_CLIENT_CERTIFICATE_PASSWORD, true),
+    (AZURE_CLIENT_SEND_CERTIFICATE_CHAIN, false),
+    (AZURE_USERNAME, false),
+    (AZURE_PASSWORD, true),
+    (AZURE_FEDERATED_TOKEN_FILE, false),
+    (AZURE_MANAGED_IDENTITY_OBJECT_ID, false),
+    (AZURE_MANAGED_IDENTITY_RESOURCE_ID, false),
+    (AZURE_TOKEN_CREDENTIALS, false),
+    (AZURE_ADDITIONALLY_ALLOWED_TENANTS, false),
+    (AZURE_AUTHORITY_HOST, false),
+    (IDENTITY_ENDPOINT, false),
+    (IDENTITY_HEADER, true),
+    (MSI_ENDPOINT, false),
+    (MSI_SECRET, true),
+    (ACTIONS_ID_TOKEN_REQUEST_URL, false),
+    (ACTIONS_ID_TOKEN_REQUEST_TOKEN, true),
+];
+
+/// Whether a credential source is expected to provide a token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SourceStatus {
+    /// The source is configured and is expected to be able to authenticate.
+    Ready,
+    /// The source is missing configuration or tooling and will fail.
+    Unavailable,
+    /// The source is filtered out by `AZURE_TOKEN_CREDENTIALS`.
+    Excluded,
+}
+
+impl fmt::Display for SourceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ready => "ready",
+            Self::Unavailable => "unavailable",
+            Self::Excluded => "excluded",
+        })
+    }
+}
+
+/// An environment variable consulted by the credential chain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnvironmentVariableReport {
+    /// The name of the variable.
+    pub name: &'static str,
+    /// The value of the variable, or `<redacted>` for secrets.  `None` when
+    /// the variable is not set.
+    pub value: Option<String>,
+    /// Where the value came from, such as a `.env` file.  `None` when the
+    /// variable is not set.
+    pub source: Option<SettingSource>,
+}
+
+/// The findings for a single credential source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceReport {
+    /// The credential source that was inspected.
+    pub source: DefaultAzureCredentialSource,
+    /// Whether the source is expected to provide a token.
+    pub status: SourceStatus,
+    /// What was found while inspecting the source.
+    pub details: Vec<String>,
+    /// How to make the source usable, if it is not.
+    pub remediation: Option<String>,
+}
+
+impl SourceReport {
+    fn ready(source: DefaultAzureCredentialSource, detail: String) -> Self {
+        Self {
+            source,
+            status: SourceStatus::Ready,
+            details: vec![detail],
+            remediation: None,
+        }
+    }
+
+    fn unavailable(
+        source: DefaultAzureCredentialSource,
+        detail: String,
+        remediation: &str,
+    ) -> Self {
+        Self {
+            source,
+            status: SourceStatus::Unavailable,
+            details: vec![detail],
+            remediation: Some(remediation.to_owned()),
+        }
+    }
+
+    pub(crate) fn exclude(&mut self) {
+        self.status = SourceStatus::Excluded;
+        self.details.push(format!(
+            "excluded by the {AZURE_TOKEN_CREDENTIALS} selection"
+        ));
+    }
+}
+
+/// A structured explanation of a `DefaultAzureCredential` configuration.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiagnosticReport {
+    /// The relevant environment variables, with secrets redacted.
+    pub environment: Vec<EnvironmentVariableReport>,
+    /// The error produced by an invalid `AZURE_TOKEN_CREDENTIALS` value.
+    pub token_credentials_error: Option<String>,
+    /// The built-in sources, in the order they are attempted.
+    pub sources: Vec<SourceReport>,
+    /// The number of custom sources added to the builder, which cannot be
+    /// inspected.
+    pub custom_sources: usize,
+}
+
+impl DiagnosticReport {
+    /// Whether at least one source is expected to provide a token.
+    #[must_use]
+    pub fn has_ready_source(&self) -> bool {
+        self.custom_sources > 0
+            || self
+                .sources
+                .iter()
+                .any(|source| source.status == SourceStatus::Ready)
+    }
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Environment variables:")?;
+        for variable in &self.environment {
+            match (&variable.value, &variable.source) {
+                (Some(value), Some(SettingSource::File(path))) => {
+                    writeln!(f, "  {}: {value} (from {})", variable.name, path.display())?;
+                }
+                (Some(value), _) => writeln!(f, "  {}: {value}", variable.name)?,
+                (None, _) => writeln!(f, "  {}: not set", variable.name)?,
+            }
+        }
+        if let Some(error) = &self.token_credentials_error {
+            writeln!(f, "  {error}")?;
+        }
+
+        writeln!(f, "Credential sources:")?;
+        if self.sources.is_empty() {
+            writeln!(f, "  none enabled")?;
+        }
+        for source in &self.sources {
+            writeln!(
+                f,
+                "  {}: {}",
+                source.source.credential_name(),
+                source.status
+            )?;
+            for detail in &source.details {
+                writeln!(f, "    {detail}")?;
+            }
+            if let Some(remediation) = &source.remediation {
+                writeln!(f, "    hint: {remediation}")?;
+            }
+        }
+        if self.custom_sources > 0 {
+            writeln!(f, "Custom sources: {}", self.custom_sources)?;
+        }
+        Ok(())
+    }
+}
+
+/// Platform variables that [`EnvironmentSettings`] does not model.  They are
+/// read by the credentials from the process environment, and reported from
+/// there.
+const PLATFORM_VARIABLES: [&str; 6] = [
+    IDENTITY_ENDPOINT,
+    IDENTITY_HEADER,
+    MSI_ENDPOINT,
+    MSI_SECRET,
+    ACTIONS_ID_TOKEN_REQUEST_URL,
+    ACTIONS_ID_TOKEN_REQUEST_TOKEN,
+];
+
+/// Read the environment variables consulted by the credential chain from
+/// `settings`, and the platform variables it doesn't model from the process
+/// environment.
+pub(crate) fn environment_variables(
+    settings: &EnvironmentSettings,
+) -> Vec<EnvironmentVariableReport> {
+    ENVIRONMENT_VARIABLES
+        .iter()
+        .map(|(name, secret)| {
+            let (value, source) = if PLATFORM_VARIABLES.contains(name) {
+                let value = env::var(name).ok();
+                let source = value.as_ref().map(|_| SettingSource::ProcessEnvironment);
+                (value, source)
+            } else {
+                (settings.variable(name), settings.source(name).cloned())
+            };
+            EnvironmentVariableReport {
+                name,
+                value: value.map(|value| if *secret { REDACTED.to_owned() } else { value }),
+                source,
+            }
+        })
+        .collect()
+}
+
+fn is_set(environment: &[EnvironmentVariableReport], name: &str) -> bool {
+    environment
+        .iter()
+        .any(|variable| variable.name == name && variable.value.is_some())
+}
+
+fn missing(environment: &[EnvironmentVariableReport], names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .filter(|name| !is_set(environment, name))
+        .map(ToString::to_string)
+        .collect()
+}
+
+pub(crate) fn check_environment(environment: &[EnvironmentVariableReport]) -> SourceReport {
+    let source = DefaultAzureCredentialSource::Environment;
+    let certificate = is_set(environment, AZURE_CLIENT_CERTIFICATE_PATH);
+    if certificate
+        && !cfg!(all(
+            feature = "client_certificate",
+            not(target_arch = "wasm32")
+        ))
+    {
+        return SourceReport::unavailable(
+            source,
+            format!(
+                "{AZURE_CLIENT_CERTIFICATE_PATH} is set, but client certificate authentication is not enabled"
+            ),
+            "enable the `client_certificate` feature, or unset AZURE_CLIENT_CERTIFICATE_PATH",
+        );
+    }
+    if is_set(environment, AZURE_CLIENT_SECRET_FILE)
+        && is_set(environment, AZURE_CLIENT_SECRET_SECONDARY)
+    {
+        return SourceReport::unavailable(
+            source,
+            format!(
+                "{AZURE_CLIENT_SECRET_SECONDARY} cannot be combined with {AZURE_CLIENT_SECRET_FILE}"
+            ),
+            "unset AZURE_CLIENT_SECRET_SECONDARY and rotate the secret by replacing the file",
+        );
+    }
+    let (credential, variables, remediation): (_, &[_], _) = if certificate {
+        (
+            "client certificate",
+            &[
+                AZURE_TENANT_ID,
+                AZURE_CLIENT_ID,
+                AZURE_CLIENT_CERTIFICATE_PATH,
+            ],
+            "set AZURE_TENANT_ID and AZURE_CLIENT_ID along with AZURE_CLIENT_CERTIFICATE_PATH to authenticate as a service principal with a certificate",
+        )
+    } else if is_set(environment, AZURE_CLIENT_SECRET_FILE) {
+        (
+            "client secret file",
+            &[AZURE_TENANT_ID, AZURE_CLIENT_ID, AZURE_CLIENT_SECRET_FILE],
+            "set AZURE_TENANT_ID and AZURE_CLIENT_ID along with AZURE_CLIENT_SECRET_FILE to authenticate as a service principal with the secret in that file",
+        )
+    } else if !is_set(environment, AZURE_CLIENT_SECRET) && is_set(environment, AZURE_USERNAME) {
+        (
+            "username and password",
+            &[
+                AZURE_TENANT_ID,
+                AZURE_CLIENT_ID,
+                AZURE_USERNAME,
+                AZURE_PASSWORD,
+            ],
+            "set AZURE_TENANT_ID, AZURE_CLIENT_ID, AZURE_USERNAME and AZURE_PASSWORD to authenticate as a user without multifactor authentication",
+        )
+    } else {
+        (
+            "client secret",
+            &[AZURE_TENANT_ID, AZURE_CLIENT_ID, AZURE_CLIENT_SECRET],
+            "set AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET or AZURE_CLIENT_CERTIFICATE_PATH to authenticate as a service principal",
+        )
+    };
+    let missing = missing(environment, variables);
+    if missing.is_empty() {
+        SourceReport::ready(source, format!("{credential} configuration found"))
+    } else {
+        SourceReport::unavailable(
+            source,
+            format!("missing {}", missing.join(", ")),
+            remediation,
+        )
+    }
+}
+
+pub(crate) fn check_workload_identity(
+    environment: &[EnvironmentVariableReport],
+    token_file: Option<&Path>,
+) -> SourceReport {
+    let source = DefaultAzureCredentialSource::WorkloadIdentity;
+    let remediation = "enable workload identity for the pod or set AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_FEDERATED_TOKEN_FILE";
+    let mut missing = missing(environment, &[AZURE_TENANT_ID, AZURE_CLIENT_ID]);
+    if token_file.is_none() {
+        missing.push(AZURE_FEDERATED_TOKEN_FILE.to_owned());
+    }
+    let Some(path) = token_file.filter(|_| missing.is_empty()) else {
+        return SourceReport::unavailable(
+            source,
+            format!("missing {}", missing.join(", ")),
+            remediation,
+        );
+    };
+
+    if path.is_file() {
+        SourceReport::ready(
+            source,
+            format!("federated token file {} exists", path.display()),
+        )
+    } else {
+        SourceReport::unavailable(
+            source,
+            format!("federated token file {} does not exist", path.display()),
+            remediation,
+        )
+    }
+}
+
+pub(crate) fn check_github_actions(environment: &[EnvironmentVariableReport]) -> SourceReport {
+    let source = DefaultAzureCredentialSource::GitHubActions;
+    let missing = missing(
+        environment,
+        &[
+            AZURE_TENANT_ID,
+            AZURE_CLIENT_ID,
+            ACTIONS_ID_TOKEN_REQUEST_URL,
+            ACTIONS_ID_TOKEN_REQUEST_TOKEN,
+        ],
+    );
+    if missing.is_empty() {
+        SourceReport::ready(
+            source,
+            "GitHub Actions ID token request is configured".to_owned(),
+        )
+    } else {
+        SourceReport::unavailable(
+            source,
+            format!("missing {}", missing.join(", ")),
+            "grant the workflow the `id-token: write` permission and set AZURE_TENANT_ID and AZURE_CLIENT_ID",
+        )
+    }
+}
+
+pub(crate) async fn check_managed_identity(
+    environment: &[EnvironmentVariableReport],
+    endpoint: &str,
+    timeout: Duration,
+    client_options: ClientOptions,
+) -> SourceReport {
+    let source = DefaultAzureCredentialSource::ManagedIdentity;
+    if let Some(name) = [IDENTITY_ENDPOINT, MSI_ENDPOINT]
+        .into_iter()
+        .find(|name| is_set(environment, name))
+    {
+        return SourceReport::ready(source, format!("managed identity endpoint set by {name}"));
+    }
+
+    if imds::is_reachable(endpoint, timeout, client_options).await {
+        SourceReport::ready(source, format!("IMDS responded at {endpoint}"))
+    } else {
+        SourceReport::unavailable(
+            source,
+            format!(
+                "IMDS did not respond at {endpoint} within {} ms",
+                timeout.whole_milliseconds()
+            ),
+            "managed identity is only available on Azure hosts; sign in with a developer tool when running locally",
+        )
+    }
+}
+
+/// Report whether one of `candidates` is on `PATH`.
+///
+/// Only the presence of the tool is checked; whether it is signed in is not.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn check_executable(
+    source: DefaultAzureCredentialSource,
+    candidates: &[&str],
+    remediation: &str,
+) -> SourceReport {
+    candidates
+        .iter()
+        .find_map(|name| find_executable(name))
+        .map_or_else(
+            || {
+                SourceReport::unavailable(
+                    source,
+                    format!("{} was not found on PATH", candidates.join(" or ")),
+                    remediation,
+                )
+            },
+            |path| {
+                SourceReport::ready(
+                    source,
+                    format!("found {}; sign-in state is not checked", path.display()),
+                )
+            },
+        )
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn check_file(
+    source: DefaultAzureCredentialSource,
+    path: Option<&Path>,
+    remediation: &str,
+) -> SourceReport {
+    match path {
+        Some(path) if path.is_file() => {
+            SourceReport::ready(source, format!("found {}", path.display()))
+        }
+        Some(path) => SourceReport::unavailable(
+            source,
+            format!("{} does not exist", path.display()),
+            remediation,
+        ),
+        None => SourceReport::unavailable(
+            source,
+            "the home directory could not be determined".to_owned(),
+            remediation,
+        ),
+    }
+}
+
+pub(crate) fn check_configured(
+    source: DefaultAzureCredentialSource,
+    configured: bool,
+    remediation: &str,
+) -> SourceReport {
+    if configured {
+        SourceReport::ready(source, "configured on the builder".to_owned())
+    } else {
+        SourceReport::unavailable(
+            source,
+            "not configured with a tenant and client".to_owned(),
+            remediation,
+        )
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn find_executable(name: &str) -> Option<std::path::PathBuf> {
+    #[cfg(windows)]
+    const EXTENSIONS: [&str; 4] = ["", ".exe", ".cmd", ".bat"];
+    #[cfg(not(windows))]
+    const EXTENSIONS: [&str; 1] = [""];
+
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path).find_map(|dir| {
+        EXTENSIONS
+            .iter()
+            .map(|extension| dir.join(format!("{name}{extension}")))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn environment(set: &[(&'static str, &str)]) -> Vec<EnvironmentVariableReport> {
+        ENVIRONMENT_VARIABLES
+            .iter()
+            .map(|(name, _)| EnvironmentVariableReport {
+                name,
+                value: set
+                    .iter()
+                    .find(|(set_name, _)| set_name == name)
+                    .map(|(_, value)| (*value).to_owned()),
+                source: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn environment_reports_missing_variables() {
+        let report = check_environment(&environment(&[(AZURE_TENANT_ID, "fake-tenant")]));
+
+        assert_eq!(report.status, SourceStatus::Unavailable);
+        assert_eq!(
+            report.details,
+            vec!["missing AZURE_CLIENT_ID, AZURE_CLIENT_SECRET"]
+        );
+        assert!(report.remediation.is_some());
+    }
+
+    #[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+    #[test]
+    fn environment_accepts_client_certificate() {
+        let report = check_environment(&environment(&[
+            (AZURE_TENANT_ID, "fake-tenant"),
+            (AZURE_CLIENT_ID, "fake-client"),
+            (AZURE_CLIENT_CERTIFICATE_PATH, "/fake/client.pem"),
+        ]));
+
+        assert_eq!(report.status, SourceStatus::Ready);
+        assert_eq!(
+            report.details,
+            vec!["client certificate configuration found"]
+        );
+    }
+
+    #[cfg(not(feature = "client_certificate"))]
+    #[test]
+    fn environment_client_certificate_requires_feature() {
+        let report = check_environment(&environment(&[
+            (AZURE_TENANT_ID, "fake-tenant"),
+            (AZURE_CLIENT_ID, "fake-client"),
+            (AZURE_CLIENT_CERTIFICATE_PATH, "/fake/client.pem"),
+        ]));
+
+        assert_eq!(report.status, SourceStatus::Unavailable);
+        assert!(
+            report
+                .remediation
+                .is_some_and(|remediation| remediation.contains("`client_certificate` feature"))
+        );
+    }
+
+    #[test]
+    fn environment_accepts_username_and_password() {
+        let report = check_environment(&environment(&[
+            (AZURE_TENANT_ID, "fake-tenant"),
+            (AZURE_CLIENT_ID, "fake-client"),
+            (AZURE_USERNAME, "user@example.com"),
+            (AZURE_PASSWORD, "fake-password"),
+        ]));
+
+        assert_eq!(report.status, SourceStatus::Ready);
+        assert_eq!(
+            report.details,
+            vec!["username and password configuration found"]
+        );
+    }
+
+    #[test]
+    fn environment_prefers_client_secret_file() {
+        let report = check_environment(&environment(&[
+            (AZURE_TENANT_ID, "fake-tenant"),
+            (AZURE_CLIENT_ID, "fake-client"),
+            (AZURE_CLIENT_SECRET_FILE, "/var/run/secrets/client-secret"),
+            (AZURE_USERNAME, "user@example.com"),
+            (AZURE_PASSWORD, "fake-password"),
+        ]));
+
+        assert_eq!(report.status, SourceStatus::Ready);
+        assert_eq!(
+            report.details,
+            vec!["client secret file configuration found"]
+        );
+    }
+
+    #[test]
+    fn environment_rejects_client_secret_file_with_secondary_secret() {
+        let report = check_environment(&environment(&[
+            (AZURE_TENANT_ID, "fake-tenant"),
+            (AZURE_CLIENT_ID, "fake-client"),
+            (AZURE_CLIENT_SECRET_FILE, "/var/run/secrets/client-secret"),
+            (AZURE_CLIENT_SECRET_SECONDARY, "fake-secret"),
+        ]));
+
+        assert_eq!(report.status, SourceStatus::Unavailable);
+        assert_eq!(
+            report.details,
+            vec!["AZURE_CLIENT_SECRET_SECONDARY cannot be combined with AZURE_CLIENT_SECRET_FILE"]
+        );
+    }
+
+    #[test]
+    fn environment_explains_username_and_password_setup() {
+        let report = check_environment(&environment(&[
+            (AZURE_TENANT_ID, "fake-tenant"),
+            (AZURE_CLIENT_ID, "fake-client"),
+            (AZURE_USERNAME, "user@example.com"),
+        ]));
+
+        assert_eq!(report.status, SourceStatus::Unavailable);
+        assert_eq!(report.details, vec!["missing AZURE_PASSWORD"]);
+        assert!(
+            report.remediation.is_some_and(
+                |remediation| remediation.contains("AZURE_USERNAME and AZURE_PASSWORD")
+            )
+        );
+    }
+
+    #[test]
+    fn environment_explains_client_secret_file_setup() {
+        let report = check_environment(&environment(&[(
+            AZURE_CLIENT_SECRET_FILE,
+            "/var/run/secrets/client-secret",
+        )]));
+
+        assert_eq!(report.status, SourceStatus::Unavailable);
+        assert_eq!(
+            report.details,
+            vec!["missing AZURE_TENANT_ID, AZURE_CLIENT_ID"]
+        );
+        assert!(report.remediation.is_some_and(|remediation| {
+            remediation.contains("along with AZURE_CLIENT_SECRET_FILE")
+        }));
+    }
+
+    #[test]
+    fn azure_variables_come_only_from_settings() {
+        let settings = EnvironmentSettings::from_map([(AZURE_TENANT_ID, "fake-tenant")]);
+
+        let environment = environment_variables(&settings);
+
+        let variable = |name| environment.iter().find(|variable| variable.name == name);
+        assert!(variable(AZURE_TENANT_ID).is_some_and(|variable| {
+            variable.value.as_deref() == Some("fake-tenant")
+                && variable.source == Some(SettingSource::Custom)
+        }));
+        assert!(
+            environment
+                .iter()
+                .filter(|variable| variable.name.starts_with("AZURE_")
+                    && variable.name != AZURE_TENANT_ID)
+                .all(|variable| variable.value.is_none() && variable.source.is_none())
+        );
+    }
+
+    #[test]
+    fn workload_identity_checks_token_file() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let token_file = dir.write("token", "fake-token")?;
+        let environment = environment(&[
+            (AZURE_TENANT_ID, "fake-tenant"),
+            (AZURE_CLIENT_ID, "fake-client"),
+        ]);
+
+        let report = check_workload_identity(&environment, Some(&token_file));
+        assert_eq!(report.status, SourceStatus::Ready);
+
+        let report = check_workload_identity(&environment, Some(&dir.path.join("missing")));
+        assert_eq!(report.status, SourceStatus::Unavailable);
+        Ok(())
+    }
+
+    #[test]
+    fn github_actions_reports_missing_id_token_request() {
+        let report = check_github_actions(&environment(&[
+            (AZURE_TENANT_ID, "fake-tenant"),
+            (AZURE_CLIENT_ID, "fake-client"),
+            (ACTIONS_ID_TOKEN_REQUEST_URL, "https://example.com/idtoken"),
+        ]));
+
+        assert_eq!(report.status, SourceStatus::Unavailable);
+        assert_eq!(
+            report.details,
+            vec!["missing ACTIONS_ID_TOKEN_REQUEST_TOKEN"]
+        );
+        assert!(
+            report
+                .remediation
+                .is_some_and(|remediation| remediation.contains("id-token: write"))
+        );
+    }
+
+    #[tokio::test]
+    async fn managed_identity_uses_endpoint_variables_without_probing() {
+        let report = check_managed_identity(
+            &environment(&[(IDENTITY_ENDPOINT, "http://localhost:42356/msi/token")]),
+            "http://127.0.0.1:9/",
+            Duration::ZERO,
+            ClientOptions::default(),
+        )
+        .await;
+
+        assert_eq!(report.status, SourceStatus::Ready);
+    }
+
+    #[tokio::test]
+    async fn managed_identity_unreachable_imds_is_unavailable() {
+        let report = check_managed_identity(
+            &environment(&[]),
+            "http://127.0.0.1:9/",
+            Duration::seconds(1),
+            ClientOptions::default(),
+        )
+        .await;
+
+        assert_eq!(report.status, SourceStatus::Unavailable);
+    }
+
+    #[test]
+    fn report_renders_redacted_values_and_hints() {
+        let mut environment = environment(&[(AZURE_TENANT_ID, "fake-tenant")]);
+        environment.push(EnvironmentVariableReport {
+            name: "SECRET",
+            value: Some(REDACTED.to_owned()),
+            source: Some(SettingSource::File(".env".into())),
+        });
+        let report = DiagnosticReport {
+            sources: vec![check_environment(&environment)],
+            environment,
+            token_credentials_error: None,
+            custom_sources: 0,
+        };
+
+        let rendered = report.to_string();
+
+        assert!(rendered.contains("AZURE_TENANT_ID: fake-tenant"));
+        assert!(rendered.contains("AZURE_CLIENT_SECRET: not set"));
+        assert!(rendered.contains("SECRET: <redacted> (from .env)"));
+        assert!(rendered.contains("EnvironmentCredential: unavailable"));
+        assert!(rendered.contains("hint: set AZURE_TENANT_ID"));
+        assert!(!report.has_ready_source());
+    }
+}
@@ -0,0 +1,129 @@
+//! A minimal parser for `.env` files.
+//!
+//! Each line is blank, a `#` comment, or `NAME=value`, optionally preceded by
+//! `export`.  Values may be wrapped in single quotes, taken literally, or in
+//! double quotes, which support the `\n`, `\r`, `\t`, `\"` and `\\` escapes.
+//! Unquoted values end at a ` #` comment and are trimmed.  Values spanning
+//! several lines and variable expansion are not supported.
+//!
+//! Errors name the file and line but never include its contents, which may
+//! hold secrets.
+
+use azure_core::error::{Error, ErrorKind};
+use std::{fs, path::Path};
+
+/// Read the variables defined in the file at `path`, in file order.
+pub fn read(path: &Path) -> azure_core::Result<Vec<(String, String)>> {
+    let contents = fs::read_to_string(path).map_err(|error| {
+        Error::with_error(
+            ErrorKind::Credential,
+            error,
+            format!("failed to read {}", path.display()),
+        )
+    })?;
+    parse(&contents).map_err(|line| {
+        Error::with_message(
+            ErrorKind::Credential,
+            format!("invalid syntax in {} on line {line}", path.display()),
+        )
+    })
+}
+
+/// Parse `contents`, returning the number of the first invalid line on error.
+fn parse(contents: &str) -> Result<Vec<(String, String)>, usize> {
+    let mut variables = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map_or(line, str::trim_start);
+        let (name, value) = line.split_once('=').ok_or(index + 1)?;
+        let name = name.trim_end();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            return Err(index + 1);
+        }
+        let value = parse_value(value.trim_start()).ok_or(index + 1)?;
+        variables.push((name.to_owned(), value));
+    }
+    Ok(variables)
+}
+
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(rest) = value.strip_prefix('\'') {
+        let (value, rest) = rest.split_once('\'')?;
+        return is_trailing_comment(rest).then(|| value.to_owned());
+    }
+
+    let Some(rest) = value.strip_prefix('"') else {
+        let end = value.find(" #").unwrap_or(value.len());
+        return value.get(..end).map(|value| value.trim_end().to_owned());
+    };
+    let mut parsed = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return is_trailing_comment(rest.get(index + 1..)?).then_some(parsed),
+            '\\' => parsed.push(match chars.next()?.1 {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                c @ ('"' | '\\') => c,
+                _ => return None,
+            }),
+            c => parsed.push(c),
+        }
+    }
+    None
+}
+
+fn is_trailing_comment(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoting_comments_and_export() {
+        let variables = parse(concat!(
+            "# a comment\n",
+            "\n",
+            "AZURE_TENANT_ID=fake-tenant\n",
+            "export AZURE_CLIENT_ID = fake-client # trailing comment\n",
+            "AZURE_CLIENT_SECRET='fake # secret'\n",
+            "AZURE_PASSWORD=\"line\\none \\\"quoted\\\"\"\n",
+            "AZURE_USERNAME=\n",
+        ));
+
+        assert_eq!(
+            variables,
+            Ok(vec![
+                ("AZURE_TENANT_ID".to_owned(), "fake-tenant".to_owned()),
+                ("AZURE_CLIENT_ID".to_owned(), "fake-client".to_owned()),
+                ("AZURE_CLIENT_SECRET".to_owned(), "fake # secret".to_owned()),
+                (
+                    "AZURE_PASSWORD".to_owned(),
+                    "line\none \"quoted\"".to_owned()
+                ),
+                ("AZURE_USERNAME".to_owned(), String::new()),
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_invalid_line_numbers() {
+        assert_eq!(parse("A=1\nnot a variable\n"), Err(2));
+        assert_eq!(parse("A=1\n\nB=\"unterminated\n"), Err(3));
+        assert_eq!(parse("A='quoted' trailing"), Err(1));
+    }
+}
@@ -1,52 +1,499 @@
 //! Recreates an `EnvironmentCredential`-style helper for service principal
 //! authentication from environment variables.
 //!
-//! This helper uses the following environment variables:
+//! This helper uses the following environment variables, which are read from
+//! the process environment unless an [`EnvironmentSettings`] is supplied:
 //! - `AZURE_TENANT_ID` identifies the tenant for both service principal and
 //!   workload identity configuration.
 //! - `AZURE_CLIENT_ID` identifies the application or workload identity client.
 //! - `AZURE_CLIENT_SECRET` configures service principal authentication for the
 //!   public [`EnvironmentCredential`] type.
+//! - `AZURE_CLIENT_SECRET_SECONDARY` holds a second client secret, tried when
+//!   `AZURE_CLIENT_SECRET` is rejected while the secrets are being rotated, as
+//!   described in [`client_secrets_credential`](crate::client_secrets_credential).
+//! - `AZURE_CLIENT_SECRET_FILE` points to a file holding the client secret,
+//!   used instead of `AZURE_CLIENT_SECRET` when both are set.  The file is
+//!   checked for changes before each token request, so that a rotated secret
+//!   is picked up without restarting.  Tokens are then requested with the new
+//!   secret, falling back to the previous one until the new one is accepted.
+//!   Setting `AZURE_CLIENT_SECRET_SECONDARY` as well is an error.
+//! - `AZURE_CLIENT_CERTIFICATE_PATH` points to a PEM or PKCS#12 certificate
+//!   used instead of `AZURE_CLIENT_SECRET` when both are set.
+//!   `AZURE_CLIENT_CERTIFICATE_PASSWORD` decrypts it, and setting
+//!   `AZURE_CLIENT_SEND_CERTIFICATE_CHAIN` to `1` or `true` sends the
+//!   certificate chain for subject name and issuer authentication.  Requires
+//!   the `client_certificate` feature, without which the credential reports
+//!   an error when the variable is set.  Not available on `wasm32`.
+//! - `AZURE_USERNAME` and `AZURE_PASSWORD` authenticate a user with the
+//!   resource owner password grant when neither a certificate nor a client
+//!   secret is configured.  This does not support multi-factor authentication
+//!   and is intended for legacy automation.
 //! - `AZURE_FEDERATED_TOKEN_FILE` points to the federated token file used when
 //!   configuring workload identity within this module.
+//! - `AZURE_MANAGED_IDENTITY_RESOURCE_ID` and `AZURE_MANAGED_IDENTITY_OBJECT_ID`
+//!   select a user-assigned managed identity by resource ID or object ID.
+//!   Otherwise `AZURE_CLIENT_ID` selects it by client ID.
+//! - `AZURE_AUTHORITY_HOST` selects the Microsoft Entra authority host, as
+//!   described in [`authority_host`](crate::authority_host).
+//! - `AZURE_ADDITIONALLY_ALLOWED_TENANTS` lists, separated by `;`, the tenants
+//!   other than `AZURE_TENANT_ID` that
+//!   [`DefaultAzureCredential`](crate::default_azure_credential::DefaultAzureCredential)
+//!   may request tokens for.
+//! - `AZURE_TOKEN_CREDENTIALS` restricts the sources used by
+//!   [`DefaultAzureCredential`](crate::default_azure_credential::DefaultAzureCredential).
+//!
+//! [`EnvironmentSettings::from_os_env_with_dotenv`] also reads these variables
+//! from a `.env` file, without exporting them into the process environment.
 
+#[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+use crate::client_certificate_credential::{
+    ClientCertificateCredential, ClientCertificateCredentialOptions,
+};
+use crate::{
+    authority_host::{AZURE_AUTHORITY_HOST, AuthorityHost},
+    client_secrets_credential::ClientSecretsCredential,
+    dotenv,
+    tenant::AZURE_ADDITIONALLY_ALLOWED_TENANTS,
+    username_password_credential::{UsernamePasswordCredential, UsernamePasswordCredentialOptions},
+};
+use async_lock::Mutex;
 use azure_core::{
     credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
     error::{Error, ErrorKind},
+    http::ClientOptions,
 };
 use azure_identity::{
-    ClientSecretCredential, ClientSecretCredentialOptions, WorkloadIdentityCredential,
-    WorkloadIdentityCredentialOptions,
+    ClientSecretCredential, ClientSecretCredentialOptions, ManagedIdentityCredentialOptions,
+    UserAssignedId, WorkloadIdentityCredential, WorkloadIdentityCredentialOptions,
 };
-use std::{env, path::PathBuf, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use tracing::debug;
 
-const AZURE_CLIENT_ID: &str = "AZURE_CLIENT_ID";
-const AZURE_CLIENT_SECRET: &str = "AZURE_CLIENT_SECRET";
-const AZURE_FEDERATED_TOKEN_FILE: &str = "AZURE_FEDERATED_TOKEN_FILE";
-const AZURE_TENANT_ID: &str = "AZURE_TENANT_ID";
+pub(crate) const AZURE_CLIENT_CERTIFICATE_PASSWORD: &str = "AZURE_CLIENT_CERTIFICATE_PASSWORD";
+pub(crate) const AZURE_CLIENT_CERTIFICATE_PATH: &str = "AZURE_CLIENT_CERTIFICATE_PATH";
+pub(crate) const AZURE_CLIENT_ID: &str = "AZURE_CLIENT_ID";
+pub(crate) const AZURE_CLIENT_SECRET: &str = "AZURE_CLIENT_SECRET";
+pub(crate) const AZURE_CLIENT_SECRET_FILE: &str = "AZURE_CLIENT_SECRET_FILE";
+pub(crate) const AZURE_CLIENT_SECRET_SECONDARY: &str = "AZURE_CLIENT_SECRET_SECONDARY";
+pub(crate) const AZURE_CLIENT_SEND_CERTIFICATE_CHAIN: &str = "AZURE_CLIENT_SEND_CERTIFICATE_CHAIN";
+pub(crate) const AZURE_FEDERATED_TOKEN_FILE: &str = "AZURE_FEDERATED_TOKEN_FILE";
+pub(crate) const AZURE_MANAGED_IDENTITY_OBJECT_ID: &str = "AZURE_MANAGED_IDENTITY_OBJECT_ID";
+pub(crate) const AZURE_MANAGED_IDENTITY_RESOURCE_ID: &str = "AZURE_MANAGED_IDENTITY_RESOURCE_ID";
+pub(crate) const AZURE_PASSWORD: &str = "AZURE_PASSWORD";
+pub(crate) const AZURE_TENANT_ID: &str = "AZURE_TENANT_ID";
+pub(crate) const AZURE_TOKEN_CREDENTIALS: &str = "AZURE_TOKEN_CREDENTIALS";
+pub(crate) const AZURE_USERNAME: &str = "AZURE_USERNAME";
 
-#[derive(Debug, Clone, Default)]
-pub(crate) struct EnvironmentSettings {
+const DOTENV_FILE: &str = ".env";
+/// Replaces secret values in `Debug` output and diagnostics.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// The configuration read from `AZURE_*` environment variables.
+///
+/// By default the settings are read from the process environment with
+/// [`from_os_env`](Self::from_os_env).  Processes hosting several identities,
+/// and tests, can instead supply the values from a map, from variables with a
+/// custom prefix, or from any lookup function, and pass the settings to
+/// [`EnvironmentCredential::from_settings`] or
+/// [`DefaultAzureCredentialBuilder::with_environment_settings`](crate::default_azure_credential::DefaultAzureCredentialBuilder::with_environment_settings).
+///
+/// Secrets are redacted from the [`Debug`](std::fmt::Debug) output.
+#[derive(Clone, Default)]
+pub struct EnvironmentSettings {
+    additionally_allowed_tenants: Option<String>,
+    authority_host: Option<String>,
+    client_certificate_password: Option<String>,
+    client_certificate_path: Option<PathBuf>,
     client_id: Option<String>,
     client_secret: Option<String>,
+    client_secret_file: Option<PathBuf>,
+    client_secret_secondary: Option<String>,
     federated_token_file: Option<PathBuf>,
+    managed_identity_object_id: Option<String>,
+    managed_identity_resource_id: Option<String>,
+    password: Option<String>,
+    send_certificate_chain: Option<String>,
     tenant_id: Option<String>,
+    token_credentials: Option<String>,
+    username: Option<String>,
+    sources: BTreeMap<&'static str, SettingSource>,
+}
+
+/// Where the value of an [`EnvironmentSettings`] variable came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SettingSource {
+    /// The process environment.
+    ProcessEnvironment,
+    /// A `.env` file loaded by
+    /// [`EnvironmentSettings::from_os_env_with_dotenv`].
+    File(PathBuf),
+    /// A map or lookup function given to [`EnvironmentSettings`].
+    Custom,
+}
+
+impl fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ProcessEnvironment => f.write_str("process environment"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Custom => f.write_str("custom settings"),
+        }
+    }
+}
+
+/// Options for [`EnvironmentSettings::from_os_env_with_dotenv`].
+#[derive(Debug, Clone, Default)]
+pub struct DotenvOptions {
+    /// The file to load.  Defaults to `.env` in the current directory, which
+    /// is skipped when it doesn't exist.
+    pub path: Option<PathBuf>,
+    /// Let values from the file replace those set in the process environment.
+    pub override_process_env: bool,
 }
 
 impl EnvironmentSettings {
-    pub(crate) fn from_os_env() -> Self {
-        Self {
-            client_id: env::var(AZURE_CLIENT_ID).ok(),
-            client_secret: env::var(AZURE_CLIENT_SECRET).ok(),
+    /// Read the settings from the process environment.
+    #[must_use]
+    pub fn from_os_env() -> Self {
+        let mut settings = Self {
+            // Paths are read as `OsString`s so that they need not be UTF-8.
+            client_certificate_path: env::var_os(AZURE_CLIENT_CERTIFICATE_PATH).map(PathBuf::from),
+            client_secret_file: env::var_os(AZURE_CLIENT_SECRET_FILE).map(PathBuf::from),
             federated_token_file: env::var_os(AZURE_FEDERATED_TOKEN_FILE).map(PathBuf::from),
-            tenant_id: env::var(AZURE_TENANT_ID).ok(),
+            ..Self::from_sourced_lookup(|name| {
+                env::var(name)
+                    .ok()
+                    .map(|value| (value, SettingSource::ProcessEnvironment))
+            })
+        };
+        for (name, path) in [
+            (
+                AZURE_CLIENT_CERTIFICATE_PATH,
+                &settings.client_certificate_path,
+            ),
+            (AZURE_CLIENT_SECRET_FILE, &settings.client_secret_file),
+            (AZURE_FEDERATED_TOKEN_FILE, &settings.federated_token_file),
+        ] {
+            if path.is_some() {
+                settings
+                    .sources
+                    .insert(name, SettingSource::ProcessEnvironment);
+            }
         }
+        settings
+    }
+
+    /// Read the settings from the process environment and a `.env` file.
+    ///
+    /// Only `AZURE_*` variables are read from the file; the file is not
+    /// exported into the process environment.  Values set in the process
+    /// environment take precedence unless
+    /// [`override_process_env`](DotenvOptions::override_process_env) is set.
+    /// [`source`](Self::source) reports where each value came from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an explicit [`path`](DotenvOptions::path) cannot
+    /// be read, or if the file is not valid.  Errors never include the
+    /// contents of the file.
+    pub fn from_os_env_with_dotenv(options: Option<DotenvOptions>) -> azure_core::Result<Self> {
+        let DotenvOptions {
+            path,
+            override_process_env,
+        } = options.unwrap_or_default();
+        let process = Self::from_os_env();
+        let Some(path) = path.or_else(|| {
+            let path = PathBuf::from(DOTENV_FILE);
+            path.is_file().then_some(path)
+        }) else {
+            return Ok(process);
+        };
+
+        let variables = dotenv::read(&path)?
+            .into_iter()
+            .filter(|(name, _)| name.starts_with("AZURE_"))
+            .collect::<HashMap<_, _>>();
+        debug!(
+            "loaded {:?} from {}",
+            variables.keys().collect::<Vec<_>>(),
+            path.display()
+        );
+        let file = Self::from_sourced_lookup(|name| {
+            variables
+                .get(name)
+                .map(|value| (value.clone(), SettingSource::File(path.clone())))
+        });
+
+        Ok(if override_process_env {
+            file.or(process)
+        } else {
+            process.or(file)
+        })
+    }
+
+    /// Read the settings from process environment variables named with
+    /// `prefix` followed by the usual name, such as `MYAPP_AZURE_CLIENT_ID`
+    /// for the prefix `MYAPP_`.
+    #[must_use]
+    pub fn from_os_env_with_prefix(prefix: &str) -> Self {
+        Self::from_sourced_lookup(|name| {
+            env::var(format!("{prefix}{name}"))
+                .ok()
+                .map(|value| (value, SettingSource::ProcessEnvironment))
+        })
+    }
+
+    /// Read the settings from a map of environment variable names to values.
+    #[must_use]
+    pub fn from_map<I, K, V>(variables: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let variables = variables
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect::<HashMap<String, String>>();
+        Self::from_lookup(|name| variables.get(name).cloned())
+    }
+
+    /// Read the settings by calling `lookup` with each environment variable
+    /// name, such as `AZURE_CLIENT_ID`.
+    #[must_use]
+    pub fn from_lookup<F>(mut lookup: F) -> Self
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        Self::from_sourced_lookup(|name| lookup(name).map(|value| (value, SettingSource::Custom)))
+    }
+
+    fn from_sourced_lookup<F>(mut lookup: F) -> Self
+    where
+        F: FnMut(&str) -> Option<(String, SettingSource)>,
+    {
+        let mut sources = BTreeMap::new();
+        let mut get = |name: &'static str| {
+            lookup(name).map(|(value, source)| {
+                sources.insert(name, source);
+                value
+            })
+        };
+        let settings = Self {
+            additionally_allowed_tenants: get(AZURE_ADDITIONALLY_ALLOWED_TENANTS),
+            authority_host: get(AZURE_AUTHORITY_HOST),
+            client_certificate_password: get(AZURE_CLIENT_CERTIFICATE_PASSWORD),
+            client_certificate_path: get(AZURE_CLIENT_CERTIFICATE_PATH).map(PathBuf::from),
+            client_id: get(AZURE_CLIENT_ID),
+            client_secret: get(AZURE_CLIENT_SECRET),
+            client_secret_file: get(AZURE_CLIENT_SECRET_FILE).map(PathBuf::from),
+            client_secret_secondary: get(AZURE_CLIENT_SECRET_SECONDARY),
+            federated_token_file: get(AZURE_FEDERATED_TOKEN_FILE).map(PathBuf::from),
+            managed_identity_object_id: get(AZURE_MANAGED_IDENTITY_OBJECT_ID),
+            managed_identity_resource_id: get(AZURE_MANAGED_IDENTITY_RESOURCE_ID),
+            password: get(AZURE_PASSWORD),
+            send_certificate_chain: get(AZURE_CLIENT_SEND_CERTIFICATE_CHAIN),
+            tenant_id: get(AZURE_TENANT_ID),
+            token_credentials: get(AZURE_TOKEN_CREDENTIALS),
+            username: get(AZURE_USERNAME),
+            sources: BTreeMap::new(),
+        };
+        Self {
+            sources,
+            ..settings
+        }
+    }
+
+    /// Use the values of `self`, and those of `other` for variables `self`
+    /// doesn't set.
+    fn or(self, other: Self) -> Self {
+        let mut sources = other.sources;
+        sources.extend(self.sources);
+        Self {
+            additionally_allowed_tenants: self
+                .additionally_allowed_tenants
+                .or(other.additionally_allowed_tenants),
+            authority_host: self.authority_host.or(other.authority_host),
+            client_certificate_password: self
+                .client_certificate_password
+                .or(other.client_certificate_password),
+            client_certificate_path: self
+                .client_certificate_path
+                .or(other.client_certificate_path),
+            client_id: self.client_id.or(other.client_id),
+            client_secret: self.client_secret.or(other.client_secret),
+            client_secret_file: self.client_secret_file.or(other.client_secret_file),
+            client_secret_secondary: self
+                .client_secret_secondary
+                .or(other.client_secret_secondary),
+            federated_token_file: self.federated_token_file.or(other.federated_token_file),
+            managed_identity_object_id: self
+                .managed_identity_object_id
+                .or(other.managed_identity_object_id),
+            managed_identity_resource_id: self
+                .managed_identity_resource_id
+                .or(other.managed_identity_resource_id),
+            password: self.password.or(other.password),
+            send_certificate_chain: self.send_certificate_chain.or(other.send_certificate_chain),
+            tenant_id: self.tenant_id.or(other.tenant_id),
+            token_credentials: self.token_credentials.or(other.token_credentials),
+            username: self.username.or(other.username),
+            sources,
+        }
+    }
+
+    /// Where the value of the environment variable `name` came from, or
+    /// `None` when it is not set.
+    #[must_use]
+    pub fn source(&self, name: &str) -> Option<&SettingSource> {
+        self.sources.get(name)
+    }
+
+    /// The value of the environment variable `name`, if it is one of the
+    /// settings.
+    pub(crate) fn variable(&self, name: &str) -> Option<String> {
+        let path = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map(|path| path.to_string_lossy().into_owned())
+        };
+        match name {
+            AZURE_ADDITIONALLY_ALLOWED_TENANTS => self.additionally_allowed_tenants.clone(),
+            AZURE_AUTHORITY_HOST => self.authority_host.clone(),
+            AZURE_CLIENT_CERTIFICATE_PASSWORD => self.client_certificate_password.clone(),
+            AZURE_CLIENT_CERTIFICATE_PATH => path(&self.client_certificate_path),
+            AZURE_CLIENT_ID => self.client_id.clone(),
+            AZURE_CLIENT_SECRET => self.client_secret.clone(),
+            AZURE_CLIENT_SECRET_FILE => path(&self.client_secret_file),
+            AZURE_CLIENT_SECRET_SECONDARY => self.client_secret_secondary.clone(),
+            AZURE_FEDERATED_TOKEN_FILE => path(&self.federated_token_file),
+            AZURE_MANAGED_IDENTITY_OBJECT_ID => self.managed_identity_object_id.clone(),
+            AZURE_MANAGED_IDENTITY_RESOURCE_ID => self.managed_identity_resource_id.clone(),
+            AZURE_PASSWORD => self.password.clone(),
+            AZURE_CLIENT_SEND_CERTIFICATE_CHAIN => self.send_certificate_chain.clone(),
+            AZURE_TENANT_ID => self.tenant_id.clone(),
+            AZURE_TOKEN_CREDENTIALS => self.token_credentials.clone(),
+            AZURE_USERNAME => self.username.clone(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn federated_token_file(&self) -> Option<&Path> {
+        self.federated_token_file.as_deref()
+    }
+
+    pub(crate) fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    /// These settings with `AZURE_TENANT_ID` replaced by `tenant_id`, if set.
+    pub(crate) fn with_tenant_id(&self, tenant_id: Option<&str>) -> Self {
+        Self {
+            tenant_id: tenant_id
+                .map(ToOwned::to_owned)
+                .or_else(|| self.tenant_id.clone()),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn client_id(&self) -> Option<&str> {
+        self.client_id.as_deref()
+    }
+
+    pub(crate) fn additionally_allowed_tenants(&self) -> Option<&str> {
+        self.additionally_allowed_tenants.as_deref()
+    }
+
+    pub(crate) fn token_credentials(&self) -> Option<&str> {
+        self.token_credentials.as_deref()
+    }
+
+    /// The authority host selected by `AZURE_AUTHORITY_HOST`, if any.
+    pub(crate) fn authority_host(&self) -> azure_core::Result<Option<AuthorityHost>> {
+        self.authority_host
+            .as_deref()
+            .filter(|value| !value.is_empty())
+            .map(str::parse)
+            .transpose()
+    }
+
+    /// Whether `AZURE_CLIENT_SEND_CERTIFICATE_CHAIN` is `1` or `true`.
+    #[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+    fn send_certificate_chain(&self) -> bool {
+        self.send_certificate_chain
+            .as_deref()
+            .is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+    }
+
+    /// The user-assigned managed identity selected by the environment,
+    /// preferring a resource ID, then an object ID, then `AZURE_CLIENT_ID`.
+    fn user_assigned_id(&self) -> Option<UserAssignedId> {
+        self.managed_identity_resource_id
+            .clone()
+            .map(UserAssignedId::ResourceId)
+            .or_else(|| {
+                self.managed_identity_object_id
+                    .clone()
+                    .map(UserAssignedId::ObjectId)
+            })
+            .or_else(|| self.client_id.clone().map(UserAssignedId::ClientId))
+    }
+}
+
+impl fmt::Debug for EnvironmentSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redacted = |value: &Option<String>| value.as_ref().map(|_| REDACTED);
+        f.debug_struct("EnvironmentSettings")
+            .field(
+                "additionally_allowed_tenants",
+                &self.additionally_allowed_tenants,
+            )
+            .field("authority_host", &self.authority_host)
+            .field(
+                "client_certificate_password",
+                &redacted(&self.client_certificate_password),
+            )
+            .field("client_certificate_path", &self.client_certificate_path)
+            .field("client_id", &self.client_id)
+            .field("client_secret", &redacted(&self.client_secret))
+            .field("client_secret_file", &self.client_secret_file)
+            .field(
+                "client_secret_secondary",
+                &redacted(&self.client_secret_secondary),
+            )
+            .field("federated_token_file", &self.federated_token_file)
+            .field(
+                "managed_identity_object_id",
+                &self.managed_identity_object_id,
+            )
+            .field(
+                "managed_identity_resource_id",
+                &self.managed_identity_resource_id,
+            )
+            .field("password", &redacted(&self.password))
+            .field("send_certificate_chain", &self.send_certificate_chain)
+            .field("tenant_id", &self.tenant_id)
+            .field("token_credentials", &self.token_credentials)
+            .field("username", &self.username)
+            .field("sources", &self.sources)
+            .finish()
     }
 }
 
 #[derive(Debug)]
 enum EnvironmentCredentialSource {
     ClientSecret(Arc<ClientSecretCredential>),
+    ClientSecretFile(Arc<ClientSecretFileCredential>),
+    ClientSecrets(Arc<ClientSecretsCredential>),
+    #[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+    ClientCertificate(Arc<ClientCertificateCredential>),
+    UsernamePassword(Arc<UsernamePasswordCredential>),
 }
 
 #[derive(Debug)]
@@ -65,10 +512,75 @@ impl EnvironmentCredential {
         )
     }
 
-    pub(crate) fn from_settings(
+    /// Create an `EnvironmentCredential` from the given settings.
+    ///
+    /// A configured certificate takes precedence over a client secret file,
+    /// then a username and password when no client secret is set, then a
+    /// primary and secondary client secret, then a single client secret.  The
+    /// certificate and username and password sources use the `client_options`
+    /// of `client_secret_credential_options`.
+    pub fn from_settings(
         environment: &EnvironmentSettings,
         client_secret_credential_options: Option<ClientSecretCredentialOptions>,
     ) -> azure_core::Result<Arc<Self>> {
+        #[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+        if environment.client_certificate_path.is_some() {
+            let credential = create_client_certificate_credential(
+                environment,
+                client_secret_credential_options,
+            )?;
+            return Ok(Arc::new(Self {
+                source: EnvironmentCredentialSource::ClientCertificate(credential),
+            }));
+        }
+        #[cfg(not(all(feature = "client_certificate", not(target_arch = "wasm32"))))]
+        if environment.client_certificate_path.is_some() {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                format!(
+                    "{AZURE_CLIENT_CERTIFICATE_PATH} is set, but client certificate authentication requires the `client_certificate` feature"
+                ),
+            ));
+        }
+
+        if let Some(path) = &environment.client_secret_file {
+            if environment.client_secret_secondary.is_some() {
+                return Err(Error::with_message(
+                    ErrorKind::Credential,
+                    format!(
+                        "{AZURE_CLIENT_SECRET_SECONDARY} cannot be combined with {AZURE_CLIENT_SECRET_FILE}; rotate the secret by replacing the file instead"
+                    ),
+                ));
+            }
+            let credential = create_client_secret_file_credential(
+                environment,
+                path,
+                client_secret_credential_options,
+            )?;
+            return Ok(Arc::new(Self {
+                source: EnvironmentCredentialSource::ClientSecretFile(credential),
+            }));
+        }
+
+        if environment.client_secret.is_none()
+            && environment.username.is_some()
+            && environment.password.is_some()
+        {
+            let credential =
+                create_username_password_credential(environment, client_secret_credential_options)?;
+            return Ok(Arc::new(Self {
+                source: EnvironmentCredentialSource::UsernamePassword(credential),
+            }));
+        }
+
+        if environment.client_secret_secondary.is_some() {
+            let credential =
+                create_client_secrets_credential(environment, client_secret_credential_options)?;
+            return Ok(Arc::new(Self {
+                source: EnvironmentCredentialSource::ClientSecrets(credential),
+            }));
+        }
+
         let credential =
             create_client_secret_credential(environment, client_secret_credential_options)?;
         Ok(Arc::new(Self {
@@ -80,6 +592,11 @@ impl EnvironmentCredential {
     fn source_name(&self) -> &'static str {
         match &self.source {
             EnvironmentCredentialSource::ClientSecret(_) => "client_secret",
+            EnvironmentCredentialSource::ClientSecretFile(_) => "client_secret_file",
+            EnvironmentCredentialSource::ClientSecrets(_) => "client_secrets",
+            #[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+            EnvironmentCredentialSource::ClientCertificate(_) => "client_certificate",
+            EnvironmentCredentialSource::UsernamePassword(_) => "username_password",
         }
     }
 }
@@ -126,9 +643,25 @@ pub(crate) fn create_workload_identity_credential(
             })?,
     );
 
+    if let Some(authority_host) = environment.authority_host()? {
+        authority_host.apply_to(&mut options.credential_options.client_options);
+    }
+
     WorkloadIdentityCredential::new(Some(options))
 }
 
+/// Explicit managed identity options are used unchanged.  Otherwise the
+/// user-assigned identity is taken from the environment.
+pub(crate) fn managed_identity_credential_options(
+    environment: &EnvironmentSettings,
+    options: Option<ManagedIdentityCredentialOptions>,
+) -> ManagedIdentityCredentialOptions {
+    options.unwrap_or_else(|| ManagedIdentityCredentialOptions {
+        user_assigned_id: environment.user_assigned_id(),
+        ..ManagedIdentityCredentialOptions::default()
+    })
+}
+
 fn create_client_secret_credential(
     environment: &EnvironmentSettings,
     options: Option<ClientSecretCredentialOptions>,
@@ -137,12 +670,283 @@ fn create_client_secret_credential(
     let client_id = required_environment_value(AZURE_CLIENT_ID, environment.client_id.as_deref())?;
     let client_secret =
         required_environment_value(AZURE_CLIENT_SECRET, environment.client_secret.as_deref())?;
+    let mut options = options.unwrap_or_default();
+    if let Some(authority_host) = environment.authority_host()? {
+        authority_host.apply_to(&mut options.client_options);
+    }
 
     ClientSecretCredential::new(
         tenant_id,
         client_id.to_owned(),
         Secret::new(client_secret.to_owned()),
-        options,
+        Some(options),
+    )
+}
+
+fn create_client_secrets_credential(
+    environment: &EnvironmentSettings,
+    options: Option<ClientSecretCredentialOptions>,
+) -> azure_core::Result<Arc<ClientSecretsCredential>> {
+    let tenant_id = required_environment_value(AZURE_TENANT_ID, environment.tenant_id.as_deref())?;
+    let client_id = required_environment_value(AZURE_CLIENT_ID, environment.client_id.as_deref())?;
+    let client_secret =
+        required_environment_value(AZURE_CLIENT_SECRET, environment.client_secret.as_deref())?;
+    let client_secret_secondary = required_environment_value(
+        AZURE_CLIENT_SECRET_SECONDARY,
+        environment.client_secret_secondary.as_deref(),
+    )?;
+
+    let mut builder = ClientSecretsCredential::builder(tenant_id, client_id)
+        .with_secrets([
+            Secret::new(client_secret.to_owned()),
+            Secret::new(client_secret_secondary.to_owned()),
+        ])
+        .with_client_options(
+            options
+                .map(|options| options.client_options)
+                .unwrap_or_default(),
+        );
+    if let Some(authority_host) = environment.authority_host()? {
+        builder = builder.with_authority_host(authority_host);
+    }
+    builder.build()
+}
+
+fn create_client_secret_file_credential(
+    environment: &EnvironmentSettings,
+    path: &Path,
+    options: Option<ClientSecretCredentialOptions>,
+) -> azure_core::Result<Arc<ClientSecretFileCredential>> {
+    let tenant_id = required_environment_value(AZURE_TENANT_ID, environment.tenant_id.as_deref())?;
+    let client_id = required_environment_value(AZURE_CLIENT_ID, environment.client_id.as_deref())?;
+    let mut client_options = options.unwrap_or_default().client_options;
+    if let Some(authority_host) = environment.authority_host()? {
+        authority_host.apply_to(&mut client_options);
+    }
+
+    ClientSecretFileCredential::new(tenant_id, client_id, path, client_options)
+}
+
+/// Identifies a version of a file, so that rewriting or replacing it is
+/// noticed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileVersion {
+    modified: Option<SystemTime>,
+    len: u64,
+    #[cfg(unix)]
+    inode: u64,
+}
+
+impl FileVersion {
+    fn of(path: &Path) -> std::io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            #[cfg(unix)]
+            inode: std::os::unix::fs::MetadataExt::ino(&metadata),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct ClientSecretFileState {
+    version: FileVersion,
+    secret: Secret,
+    current: Arc<ClientSecretCredential>,
+    /// The credential using the secret read before `secret`, until `current`
+    /// has been accepted.
+    previous: Option<Arc<ClientSecretCredential>>,
+}
+
+/// A [`ClientSecretCredential`] whose secret is read from a file, and which is
+/// rebuilt when the file changes.
+#[derive(Debug)]
+struct ClientSecretFileCredential {
+    tenant_id: String,
+    client_id: String,
+    path: PathBuf,
+    client_options: ClientOptions,
+    state: Mutex<ClientSecretFileState>,
+}
+
+impl ClientSecretFileCredential {
+    fn new(
+        tenant_id: &str,
+        client_id: &str,
+        path: &Path,
+        client_options: ClientOptions,
+    ) -> azure_core::Result<Arc<Self>> {
+        let version = FileVersion::of(path).map_err(|error| read_error(path, error))?;
+        let secret = read_secret_file(path)?;
+        let current = ClientSecretCredential::new(
+            tenant_id,
+            client_id.to_owned(),
+            secret.clone(),
+            Some(ClientSecretCredentialOptions {
+                client_options: client_options.clone(),
+            }),
+        )?;
+
+        Ok(Arc::new(Self {
+            tenant_id: tenant_id.to_owned(),
+            client_id: client_id.to_owned(),
+            path: path.to_owned(),
+            client_options,
+            state: Mutex::new(ClientSecretFileState {
+                version,
+                secret,
+                current,
+                previous: None,
+            }),
+        }))
+    }
+
+    /// The current and previous credentials, after rebuilding the current one
+    /// if the secret file changed.
+    async fn credentials(
+        &self,
+    ) -> azure_core::Result<(
+        Arc<ClientSecretCredential>,
+        Option<Arc<ClientSecretCredential>>,
+    )> {
+        let mut state = self.state.lock().await;
+        // The file may briefly be missing while a secret mount is updated, so
+        // errors keep the current secret and the file is checked again later.
+        if let Ok(version) = FileVersion::of(&self.path)
+            && version != state.version
+        {
+            match read_secret_file(&self.path) {
+                Ok(secret) if secret == state.secret => state.version = version,
+                Ok(secret) => {
+                    debug!(
+                        "{} changed, using the new client secret",
+                        self.path.display()
+                    );
+                    let credential = ClientSecretCredential::new(
+                        &self.tenant_id,
+                        self.client_id.clone(),
+                        secret.clone(),
+                        Some(ClientSecretCredentialOptions {
+                            client_options: self.client_options.clone(),
+                        }),
+                    )?;
+                    state.previous = Some(std::mem::replace(&mut state.current, credential));
+                    state.secret = secret;
+                    state.version = version;
+                }
+                Err(error) => debug!("keeping the current client secret: {error}"),
+            }
+        }
+        Ok((state.current.clone(), state.previous.clone()))
+    }
+
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let (current, previous) = self.credentials().await?;
+        let error = match current.get_token(scopes, options.clone()).await {
+            Ok(token) => {
+                if previous.is_some() {
+                    let mut state = self.state.lock().await;
+                    if Arc::ptr_eq(&state.current, &current) {
+                        state.previous = None;
+                    }
+                }
+                return Ok(token);
+            }
+            Err(error) => error,
+        };
+
+        let Some(previous) = previous else {
+            return Err(error);
+        };
+        debug!("the new client secret was not accepted, using the previous client secret");
+        previous.get_token(scopes, options).await.map_err(|_| error)
+    }
+}
+
+fn read_secret_file(path: &Path) -> azure_core::Result<Secret> {
+    let secret = fs::read_to_string(path).map_err(|error| read_error(path, error))?;
+    let secret = secret.trim_end_matches(['\r', '\n']);
+    if secret.is_empty() {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            format!("{AZURE_CLIENT_SECRET_FILE} {} is empty", path.display()),
+        ));
+    }
+    Ok(Secret::new(secret.to_owned()))
+}
+
+fn read_error(path: &Path, error: std::io::Error) -> Error {
+    Error::with_error(
+        ErrorKind::Credential,
+        error,
+        format!(
+            "failed to read {AZURE_CLIENT_SECRET_FILE} {}",
+            path.display()
+        ),
+    )
+}
+
+#[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+fn create_client_certificate_credential(
+    environment: &EnvironmentSettings,
+    options: Option<ClientSecretCredentialOptions>,
+) -> azure_core::Result<Arc<ClientCertificateCredential>> {
+    let tenant_id = required_environment_value(AZURE_TENANT_ID, environment.tenant_id.as_deref())?;
+    let client_id = required_environment_value(AZURE_CLIENT_ID, environment.client_id.as_deref())?;
+    let path = environment
+        .client_certificate_path
+        .as_ref()
+        .ok_or_else(|| {
+            Error::with_message(
+                ErrorKind::Credential,
+                format!("{AZURE_CLIENT_CERTIFICATE_PATH} environment variable is not set"),
+            )
+        })?;
+
+    ClientCertificateCredential::from_file(
+        tenant_id,
+        client_id,
+        path,
+        Some(ClientCertificateCredentialOptions {
+            client_options: options
+                .map(|options| options.client_options)
+                .unwrap_or_default(),
+            password: environment
+                .client_certificate_password
+                .clone()
+                .map(Secret::new),
+            authority_host: environment.authority_host()?,
+            send_certificate_chain: environment.send_certificate_chain(),
+            ..ClientCertificateCredentialOptions::default()
+        }),
+    )
+}
+
+fn create_username_password_credential(
+    environment: &EnvironmentSettings,
+    options: Option<ClientSecretCredentialOptions>,
+) -> azure_core::Result<Arc<UsernamePasswordCredential>> {
+    let tenant_id = required_environment_value(AZURE_TENANT_ID, environment.tenant_id.as_deref())?;
+    let client_id = required_environment_value(AZURE_CLIENT_ID, environment.client_id.as_deref())?;
+    let username = required_environment_value(AZURE_USERNAME, environment.username.as_deref())?;
+    let password = required_environment_value(AZURE_PASSWORD, environment.password.as_deref())?;
+
+    UsernamePasswordCredential::new(
+        tenant_id,
+        client_id,
+        username,
+        Secret::new(password.to_owned()),
+        Some(UsernamePasswordCredentialOptions {
+            client_options: options
+                .map(|options| options.client_options)
+                .unwrap_or_default(),
+            authority_host: environment.authority_host()?,
+        }),
     )
 }
 
@@ -170,6 +974,19 @@ impl TokenCredential for EnvironmentCredential {
             EnvironmentCredentialSource::ClientSecret(credential) => {
                 credential.get_token(scopes, options).await
             }
+            EnvironmentCredentialSource::ClientSecretFile(credential) => {
+                credential.get_token(scopes, options).await
+            }
+            EnvironmentCredentialSource::ClientSecrets(credential) => {
+                credential.get_token(scopes, options).await
+            }
+            #[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+            EnvironmentCredentialSource::ClientCertificate(credential) => {
+                credential.get_token(scopes, options).await
+            }
+            EnvironmentCredentialSource::UsernamePassword(credential) => {
+                credential.get_token(scopes, options).await
+            }
         }
     }
 }
@@ -177,43 +994,267 @@ impl TokenCredential for EnvironmentCredential {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{
-        fs,
-        sync::atomic::{AtomicUsize, Ordering},
-    };
+    use crate::test_util::{MockPolicy, TOKEN_BODY, TempDir};
+    use azure_core::http::StatusCode;
 
-    static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    #[test]
+    fn settings_are_read_from_map_and_lookup() {
+        let settings = EnvironmentSettings::from_map([
+            ("AZURE_TENANT_ID", "fake-tenant"),
+            ("AZURE_CLIENT_ID", "fake-client"),
+            ("AZURE_FEDERATED_TOKEN_FILE", "/fake/token"),
+        ]);
+        assert_eq!(settings.tenant_id(), Some("fake-tenant"));
+        assert_eq!(
+            settings.variable(AZURE_CLIENT_ID).as_deref(),
+            Some("fake-client")
+        );
+        assert_eq!(
+            settings.federated_token_file(),
+            Some(Path::new("/fake/token"))
+        );
 
-    struct TempFile {
-        path: PathBuf,
+        let settings = EnvironmentSettings::from_lookup(|name| {
+            name.strip_prefix("AZURE_")
+                .filter(|name| *name == "CLIENT_ID")
+                .map(str::to_lowercase)
+        });
+        assert_eq!(
+            settings.variable(AZURE_CLIENT_ID).as_deref(),
+            Some("client_id")
+        );
+        assert_eq!(settings.tenant_id(), None);
     }
 
-    impl TempFile {
-        fn new(contents: &str) -> std::io::Result<Self> {
-            let suffix = TEMP_FILE_COUNTER.fetch_add(1, Ordering::SeqCst);
-            let path = env::temp_dir().join(format!("azure-identity-helpers-{suffix}.tmp"));
-            fs::write(&path, contents)?;
-            Ok(Self { path })
-        }
+    #[test]
+    fn settings_are_loaded_from_dotenv_file() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let file = dir.write(
+            DOTENV_FILE,
+            concat!(
+                "AZURE_TENANT_ID=file-tenant\n",
+                "AZURE_CLIENT_ID=file-client\n",
+                "OTHER_SETTING=ignored\n",
+            ),
+        )?;
+        let settings = EnvironmentSettings::from_os_env_with_dotenv(Some(DotenvOptions {
+            path: Some(file.clone()),
+            override_process_env: true,
+        }))?;
+
+        assert_eq!(settings.tenant_id(), Some("file-tenant"));
+        assert_eq!(
+            settings.source(AZURE_CLIENT_ID),
+            Some(&SettingSource::File(file))
+        );
+        assert_eq!(settings.source("OTHER_SETTING"), None);
+        Ok(())
     }
 
-    impl Drop for TempFile {
-        fn drop(&mut self) {
-            let _ = fs::remove_file(&self.path);
-        }
+    #[test]
+    fn process_settings_take_precedence_over_dotenv_file() {
+        let process = EnvironmentSettings::from_map([(AZURE_TENANT_ID, "process-tenant")]);
+        let file = EnvironmentSettings::from_sourced_lookup(|name| {
+            [AZURE_TENANT_ID, AZURE_CLIENT_ID]
+                .contains(&name)
+                .then(|| (format!("file-{name}"), SettingSource::File(".env".into())))
+        });
+
+        let settings = process.clone().or(file.clone());
+        assert_eq!(settings.tenant_id(), Some("process-tenant"));
+        assert_eq!(
+            settings.source(AZURE_TENANT_ID),
+            Some(&SettingSource::Custom)
+        );
+        assert_eq!(
+            settings.source(AZURE_CLIENT_ID),
+            Some(&SettingSource::File(".env".into()))
+        );
+
+        let settings = file.or(process);
+        assert_eq!(settings.tenant_id(), Some("file-AZURE_TENANT_ID"));
+    }
+
+    #[test]
+    fn missing_dotenv_file_is_an_error_without_contents() {
+        let result = EnvironmentSettings::from_os_env_with_dotenv(Some(DotenvOptions {
+            path: Some(env::temp_dir().join("azure-identity-helpers-missing.env")),
+            ..DotenvOptions::default()
+        }));
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+    }
+
+    fn secret_file_credential(
+        dir: &TempDir,
+        policy: &Arc<MockPolicy>,
+    ) -> azure_core::Result<Arc<EnvironmentCredential>> {
+        let path = dir.write("client-secret", "first-secret\n")?;
+        EnvironmentCredential::from_settings(
+            &EnvironmentSettings::from_map([
+                (AZURE_TENANT_ID, "fake-tenant"),
+                (AZURE_CLIENT_ID, "fake-client"),
+                (AZURE_CLIENT_SECRET, "ignored-secret"),
+                (AZURE_CLIENT_SECRET_FILE, &path.to_string_lossy()),
+            ]),
+            Some(ClientSecretCredentialOptions {
+                client_options: policy.client_options(),
+            }),
+        )
+    }
+
+    /// Replace the secret file the way a rotated secret mount does.
+    fn rotate_secret(dir: &TempDir, secret: &str) -> azure_core::Result<()> {
+        let path = dir.write("client-secret.new", secret)?;
+        fs::rename(path, dir.path.join("client-secret"))
+            .map_err(|error| Error::new(ErrorKind::Io, error))
+    }
+
+    fn sent_secrets(policy: &MockPolicy) -> Vec<String> {
+        policy
+            .bodies()
+            .iter()
+            .filter_map(|body| {
+                url::form_urlencoded::parse(body.as_bytes())
+                    .find(|(name, _)| name == "client_secret")
+                    .map(|(_, value)| value.into_owned())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn secondary_client_secret_is_tried_after_primary() -> azure_core::Result<()> {
+        let policy = MockPolicy::with_body_responses(
+            vec![(
+                "client_secret=primary-secret",
+                StatusCode::Unauthorized,
+                r#"{"error":"invalid_client","error_description":"AADSTS7000222: The provided client secret keys are expired."}"#,
+            )],
+            vec![("/oauth2/v2.0/token", StatusCode::Ok, TOKEN_BODY)],
+        );
+        let credential = EnvironmentCredential::from_settings(
+            &EnvironmentSettings::from_map([
+                (AZURE_TENANT_ID, "fake-tenant"),
+                (AZURE_CLIENT_ID, "fake-client"),
+                (AZURE_CLIENT_SECRET, "primary-secret"),
+                (AZURE_CLIENT_SECRET_SECONDARY, "secondary-secret"),
+            ]),
+            Some(ClientSecretCredentialOptions {
+                client_options: policy.client_options(),
+            }),
+        )?;
+        assert_eq!(credential.source_name(), "client_secrets");
+
+        credential.get_token(&["fake-scope"], None).await?;
+
+        assert_eq!(
+            sent_secrets(&policy),
+            vec!["primary-secret", "secondary-secret"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn client_secret_file_rotation_is_picked_up() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let policy = MockPolicy::new(vec![("/oauth2/v2.0/token", TOKEN_BODY)]);
+        let credential = secret_file_credential(&dir, &policy)?;
+        assert_eq!(credential.source_name(), "client_secret_file");
+
+        credential.get_token(&["fake-scope"], None).await?;
+        credential.get_token(&["fake-scope"], None).await?;
+        rotate_secret(&dir, "rotated-secret")?;
+        credential.get_token(&["fake-scope"], None).await?;
+
+        assert_eq!(
+            sent_secrets(&policy),
+            vec!["first-secret", "rotated-secret"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn client_secret_file_falls_back_to_previous_secret() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let policy = MockPolicy::with_body_responses(
+            vec![(
+                "client_secret=rejected-secret",
+                StatusCode::Unauthorized,
+                r#"{"error":"invalid_client","error_description":"AADSTS7000215: Invalid client secret provided."}"#,
+            )],
+            vec![("/oauth2/v2.0/token", StatusCode::Ok, TOKEN_BODY)],
+        );
+        let credential = secret_file_credential(&dir, &policy)?;
+        credential.get_token(&["first-scope"], None).await?;
+        rotate_secret(&dir, "rejected-secret")?;
+
+        let token = credential.get_token(&["second-scope"], None).await?;
+
+        assert_eq!(token.token.secret(), "fake-token");
+        assert_eq!(
+            sent_secrets(&policy),
+            vec!["first-secret", "rejected-secret", "first-secret"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn settings_debug_redacts_secrets() {
+        let settings = EnvironmentSettings::from_map([
+            ("AZURE_CLIENT_ID", "fake-client"),
+            ("AZURE_CLIENT_SECRET", "fake-secret"),
+            (
+                "AZURE_CLIENT_CERTIFICATE_PASSWORD",
+                "fake-certificate-password",
+            ),
+            ("AZURE_PASSWORD", "fake-password"),
+        ]);
+        let debug = format!("{settings:?}");
+
+        assert!(debug.contains("fake-client"));
+        assert!(debug.contains(REDACTED));
+        assert!(!debug.contains("fake-secret"));
+        assert!(!debug.contains("fake-certificate-password"));
+        assert!(!debug.contains("fake-password"));
+    }
+
+    #[test]
+    fn client_secret_file_rejects_secondary_secret() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.write("client-secret", "first-secret")?;
+        let result = EnvironmentCredential::from_settings(
+            &EnvironmentSettings::from_map([
+                (AZURE_TENANT_ID, "fake-tenant"),
+                (AZURE_CLIENT_ID, "fake-client"),
+                (AZURE_CLIENT_SECRET_FILE, &path.to_string_lossy()),
+                (AZURE_CLIENT_SECRET_SECONDARY, "secondary-secret"),
+            ]),
+            None,
+        );
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains(AZURE_CLIENT_SECRET_SECONDARY)
+        ));
+        Ok(())
     }
 
     #[test]
     fn environment_credential_uses_client_secret_even_with_federated_token()
     -> azure_core::Result<()> {
-        let token_file = TempFile::new("federated token")
-            .map_err(|error| Error::with_error(ErrorKind::Io, error, "creating temp token file"))?;
+        let dir = TempDir::new()?;
+        let token_file = dir.write("token", "federated token")?;
         let credential = EnvironmentCredential::from_settings(
             &EnvironmentSettings {
                 client_id: Some(String::from("fake-client")),
                 client_secret: Some(String::from("fake-secret")),
-                federated_token_file: Some(token_file.path.clone()),
+                federated_token_file: Some(token_file),
                 tenant_id: Some(String::from("fake-tenant")),
+                ..EnvironmentSettings::default()
             },
             None,
         )?;
@@ -224,14 +1265,14 @@ mod tests {
 
     #[test]
     fn workload_identity_credential_uses_federated_token_configuration() -> azure_core::Result<()> {
-        let token_file = TempFile::new("federated token")
-            .map_err(|error| Error::with_error(ErrorKind::Io, error, "creating temp token file"))?;
+        let dir = TempDir::new()?;
+        let token_file = dir.write("token", "federated token")?;
         create_workload_identity_credential(
             &EnvironmentSettings {
                 client_id: Some(String::from("fake-client")),
-                client_secret: None,
-                federated_token_file: Some(token_file.path.clone()),
+                federated_token_file: Some(token_file),
                 tenant_id: Some(String::from("fake-tenant")),
+                ..EnvironmentSettings::default()
             },
             None,
         )?;
@@ -244,9 +1285,8 @@ mod tests {
         let error = EnvironmentCredential::from_settings(
             &EnvironmentSettings {
                 client_id: Some(String::from("fake-client")),
-                client_secret: None,
-                federated_token_file: None,
                 tenant_id: Some(String::from("fake-tenant")),
+                ..EnvironmentSettings::default()
             },
             None,
         );
@@ -263,12 +1303,148 @@ mod tests {
             &EnvironmentSettings {
                 client_id: Some(String::from("fake-client")),
                 client_secret: Some(String::from("fake-secret")),
-                federated_token_file: None,
                 tenant_id: Some(String::from("fake-tenant")),
+                ..EnvironmentSettings::default()
+            },
+            None,
+        )?;
+
+        assert_eq!(credential.source_name(), "client_secret");
+        Ok(())
+    }
+
+    #[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+    #[test]
+    fn environment_credential_prefers_client_certificate() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let certificate = dir.write(
+            "client.pem",
+            include_str!("certificate/testdata/client.pem"),
+        )?;
+        let credential = EnvironmentCredential::from_settings(
+            &EnvironmentSettings {
+                client_certificate_path: Some(certificate),
+                client_id: Some(String::from("fake-client")),
+                client_secret: Some(String::from("fake-secret")),
+                tenant_id: Some(String::from("fake-tenant")),
+                ..EnvironmentSettings::default()
+            },
+            None,
+        )?;
+
+        assert_eq!(credential.source_name(), "client_certificate");
+        Ok(())
+    }
+
+    #[cfg(not(feature = "client_certificate"))]
+    #[test]
+    fn client_certificate_requires_feature() {
+        let result = EnvironmentCredential::from_settings(
+            &EnvironmentSettings {
+                client_certificate_path: Some(PathBuf::from("/fake/client.pem")),
+                client_id: Some(String::from("fake-client")),
+                client_secret: Some(String::from("fake-secret")),
+                tenant_id: Some(String::from("fake-tenant")),
+                ..EnvironmentSettings::default()
             },
             None,
+        );
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("`client_certificate` feature")
+        ));
+    }
+
+    #[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+    #[tokio::test]
+    async fn client_certificate_uses_authority_host() -> azure_core::Result<()> {
+        let policy = crate::test_util::MockPolicy::new(vec![("/oauth2/v2.0/token", TOKEN_BODY)]);
+        let dir = TempDir::new()?;
+        let certificate = dir.write(
+            "client.pem",
+            include_str!("certificate/testdata/client.pem"),
+        )?;
+        let credential = EnvironmentCredential::from_settings(
+            &EnvironmentSettings {
+                authority_host: Some(String::from("https://login.microsoftonline.us/")),
+                client_certificate_path: Some(certificate),
+                client_id: Some(String::from("fake-client")),
+                tenant_id: Some(String::from("fake-tenant")),
+                ..EnvironmentSettings::default()
+            },
+            Some(ClientSecretCredentialOptions {
+                client_options: policy.client_options(),
+            }),
         )?;
 
+        credential.get_token(&["fake-scope"], None).await?;
+
+        assert_eq!(
+            policy.requests(),
+            vec!["https://login.microsoftonline.us/fake-tenant/oauth2/v2.0/token"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_authority_host_is_a_credential_error() {
+        let result = EnvironmentCredential::from_settings(
+            &EnvironmentSettings {
+                authority_host: Some(String::from("http://login.contoso.local")),
+                client_id: Some(String::from("fake-client")),
+                client_secret: Some(String::from("fake-secret")),
+                tenant_id: Some(String::from("fake-tenant")),
+                ..EnvironmentSettings::default()
+            },
+            None,
+        );
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+    }
+
+    #[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+    #[test]
+    fn send_certificate_chain_accepts_one_or_true() {
+        for (value, expected) in [
+            ("1", true),
+            ("TRUE", true),
+            ("true", true),
+            ("0", false),
+            ("yes", false),
+        ] {
+            let environment = EnvironmentSettings {
+                send_certificate_chain: Some(String::from(value)),
+                ..EnvironmentSettings::default()
+            };
+            assert_eq!(environment.send_certificate_chain(), expected, "{value}");
+        }
+        assert!(!EnvironmentSettings::default().send_certificate_chain());
+    }
+
+    #[test]
+    fn environment_credential_uses_username_and_password() -> azure_core::Result<()> {
+        let environment = EnvironmentSettings {
+            client_id: Some(String::from("fake-client")),
+            password: Some(String::from("fake-password")),
+            tenant_id: Some(String::from("fake-tenant")),
+            username: Some(String::from("user@example.com")),
+            ..EnvironmentSettings::default()
+        };
+        let credential = EnvironmentCredential::from_settings(&environment, None)?;
+        assert_eq!(credential.source_name(), "username_password");
+
+        let credential = EnvironmentCredential::from_settings(
+            &EnvironmentSettings {
+                client_secret: Some(String::from("fake-secret")),
+                ..environment
+            },
+            None,
+        )?;
         assert_eq!(credential.source_name(), "client_secret");
         Ok(())
     }
@@ -282,4 +1458,65 @@ mod tests {
             Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
         ));
     }
+
+    #[test]
+    fn managed_identity_uses_client_id_from_environment() {
+        let options = managed_identity_credential_options(
+            &EnvironmentSettings {
+                client_id: Some(String::from("fake-client")),
+                ..EnvironmentSettings::default()
+            },
+            None,
+        );
+
+        assert!(matches!(
+            options.user_assigned_id,
+            Some(UserAssignedId::ClientId(ref id)) if id == "fake-client"
+        ));
+    }
+
+    #[test]
+    fn managed_identity_prefers_resource_id_then_object_id() {
+        let environment = EnvironmentSettings {
+            client_id: Some(String::from("fake-client")),
+            managed_identity_object_id: Some(String::from("fake-object")),
+            ..EnvironmentSettings::default()
+        };
+        let options = managed_identity_credential_options(&environment, None);
+        assert!(matches!(
+            options.user_assigned_id,
+            Some(UserAssignedId::ObjectId(ref id)) if id == "fake-object"
+        ));
+
+        let environment = EnvironmentSettings {
+            managed_identity_resource_id: Some(String::from("fake-resource")),
+            ..environment
+        };
+        let options = managed_identity_credential_options(&environment, None);
+        assert!(matches!(
+            options.user_assigned_id,
+            Some(UserAssignedId::ResourceId(ref id)) if id == "fake-resource"
+        ));
+    }
+
+    #[test]
+    fn managed_identity_explicit_options_take_precedence() {
+        let options = managed_identity_credential_options(
+            &EnvironmentSettings {
+                client_id: Some(String::from("fake-client")),
+                managed_identity_resource_id: Some(String::from("fake-resource")),
+                ..EnvironmentSettings::default()
+            },
+            Some(ManagedIdentityCredentialOptions::default()),
+        );
+
+        assert!(options.user_assigned_id.is_none());
+    }
+
+    #[test]
+    fn managed_identity_defaults_to_system_assigned() {
+        let options = managed_identity_credential_options(&EnvironmentSettings::default(), None);
+
+        assert!(options.user_assigned_id.is_none());
+    }
 }
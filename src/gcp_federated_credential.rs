@@ -0,0 +1,240 @@
+//! Authenticate a Google Cloud workload to Microsoft Entra ID without stored
+//! secrets.
+//!
+//! Workloads on Compute Engine, GKE, Cloud Run and Cloud Functions can request
+//! a signed identity token for their service account from the metadata
+//! server.  [`GcpFederatedCredential`] requests one with the
+//! `api://AzureADTokenExchange` audience and redeems it as a client assertion
+//! for an app registration with a federated identity credential whose issuer
+//! is `https://accounts.google.com` and whose subject is the service
+//! account's unique ID.
+//!
+//! The metadata server address defaults to `GCE_METADATA_HOST`, then to
+//! `metadata.google.internal`, and can be replaced so that tests can run
+//! against a local stand-in.
+
+use crate::{
+    authority_host::AuthorityHost,
+    client_assertion_credential::{
+        ClientAssertionCredential, ClientAssertionCredentialOptions, TOKEN_EXCHANGE_AUDIENCE,
+    },
+};
+use azure_core::{
+    credentials::{AccessToken, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::{ClientOptions, Context, Method, Pipeline, PipelineSendOptions, Request, Url},
+};
+use std::{env, sync::Arc};
+
+const GCE_METADATA_HOST: &str = "GCE_METADATA_HOST";
+const DEFAULT_METADATA_HOST: &str = "metadata.google.internal";
+const DEFAULT_SERVICE_ACCOUNT: &str = "default";
+
+/// Options for constructing a [`GcpFederatedCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct GcpFederatedCredentialOptions {
+    /// Options for the HTTP pipeline used to request identity and Entra ID
+    /// tokens.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+    /// The audience of the identity token.  Defaults to
+    /// `api://AzureADTokenExchange`.
+    pub audience: Option<String>,
+    /// The email of the service account to request a token for.  Defaults to
+    /// the workload's default service account.
+    pub service_account: Option<String>,
+    /// The metadata server.  Defaults to `http://{GCE_METADATA_HOST}/`, then
+    /// to `http://metadata.google.internal/`.
+    pub metadata_endpoint: Option<Url>,
+}
+
+#[derive(Debug)]
+/// Enables authentication to Azure Active Directory from Google Cloud using
+/// a service account identity token.
+pub struct GcpFederatedCredential {
+    credential: Arc<ClientAssertionCredential>,
+}
+
+impl GcpFederatedCredential {
+    /// Create a new `GcpFederatedCredential`.
+    pub fn new<T, C>(
+        tenant_id: T,
+        client_id: C,
+        options: Option<GcpFederatedCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+    {
+        let GcpFederatedCredentialOptions {
+            client_options,
+            authority_host,
+            audience,
+            service_account,
+            metadata_endpoint,
+        } = options.unwrap_or_default();
+        let metadata_endpoint = metadata_endpoint.map_or_else(default_metadata_endpoint, Ok)?;
+        let mut url = metadata_endpoint.join(&format!(
+            "computeMetadata/v1/instance/service-accounts/{}/identity",
+            service_account
+                .as_deref()
+                .unwrap_or(DEFAULT_SERVICE_ACCOUNT)
+        ))?;
+        url.query_pairs_mut()
+            .append_pair(
+                "audience",
+                audience.as_deref().unwrap_or(TOKEN_EXCHANGE_AUDIENCE),
+            )
+            .append_pair("format", "full");
+
+        let pipeline_options = client_options.clone();
+        let credential = ClientAssertionCredential::new(
+            tenant_id,
+            client_id,
+            move || request_identity_token(pipeline_options.clone(), url.clone()),
+            Some(ClientAssertionCredentialOptions {
+                client_options,
+                authority_host,
+            }),
+        )?;
+        Ok(Arc::new(Self { credential }))
+    }
+}
+
+fn default_metadata_endpoint() -> azure_core::Result<Url> {
+    let host = env::var(GCE_METADATA_HOST)
+        .ok()
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| DEFAULT_METADATA_HOST.to_owned());
+    Url::parse(&format!("http://{host}/")).map_err(|error| {
+        Error::with_error(
+            ErrorKind::Credential,
+            error,
+            format!("{GCE_METADATA_HOST} is not a valid host"),
+        )
+    })
+}
+
+async fn request_identity_token(
+    client_options: ClientOptions,
+    url: Url,
+) -> azure_core::Result<String> {
+    let pipeline = Pipeline::new(None, None, client_options, vec![], vec![], None);
+    let mut request = Request::new(url, Method::Get);
+    request.insert_header("metadata-flavor", "Google");
+
+    let response = pipeline
+        .send(
+            &Context::new(),
+            &mut request,
+            Some(PipelineSendOptions {
+                skip_checks: true,
+                ..PipelineSendOptions::default()
+            }),
+        )
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            format!(
+                "GcpFederatedCredential failed to get an identity token from the metadata server with status {status}"
+            ),
+        ));
+    }
+    let token = response.into_body().into_string()?;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            "GcpFederatedCredential received an empty identity token",
+        ));
+    }
+    Ok(token.to_owned())
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for GcpFederatedCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.credential.get_token(scopes, options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestServer, http_response};
+
+    fn credential(
+        metadata: &TestServer,
+        entra: &TestServer,
+    ) -> azure_core::Result<Arc<GcpFederatedCredential>> {
+        GcpFederatedCredential::new(
+            "fake-tenant",
+            "fake-client",
+            Some(GcpFederatedCredentialOptions {
+                authority_host: Some(entra.authority_host()?),
+                service_account: Some("workload@fake-project.iam.gserviceaccount.com".to_owned()),
+                metadata_endpoint: Some(Url::parse(&metadata.url)?),
+                ..GcpFederatedCredentialOptions::default()
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn exchanges_identity_token() -> azure_core::Result<()> {
+        let metadata = TestServer::start(Some(&http_response(
+            "200 OK",
+            "application/text",
+            "fake-gcp-token",
+        )))
+        .await?;
+        let entra = TestServer::entra().await?;
+
+        let token = credential(&metadata, &entra)?
+            .get_token(&["fake-scope"], None)
+            .await?;
+
+        assert_eq!(token.token.secret(), "fake-token");
+        let metadata_requests = metadata.requests();
+        let metadata_request = metadata_requests
+            .first()
+            .map(|request| request.to_ascii_lowercase())
+            .unwrap_or_default();
+        assert!(metadata_request.starts_with(
+            "get /computemetadata/v1/instance/service-accounts/workload@fake-project.iam.gserviceaccount.com/identity?audience=api%3a%2f%2fazureadtokenexchange&format=full "
+        ));
+        assert!(metadata_request.contains("metadata-flavor: google"));
+        let token_requests = entra.requests();
+        let token_request = token_requests
+            .first()
+            .map(String::as_str)
+            .unwrap_or_default();
+        assert!(token_request.contains("client_assertion=fake-gcp-token&"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_metadata_server_errors() -> azure_core::Result<()> {
+        let metadata =
+            TestServer::start(Some(&http_response("404 Not Found", "text/plain", ""))).await?;
+        let entra = TestServer::entra().await?;
+
+        let result = credential(&metadata, &entra)?
+            .get_token(&["fake-scope"], None)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        assert!(entra.requests().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+//! Authenticate a GitHub Actions workflow with its OIDC ID token.
+//!
+//! Workflows granted the `id-token: write` permission can request an ID token
+//! from the URL in `ACTIONS_ID_TOKEN_REQUEST_URL`, authorized by the bearer
+//! token in `ACTIONS_ID_TOKEN_REQUEST_TOKEN`.  [`GitHubActionsCredential`]
+//! requests one with the `api://AzureADTokenExchange` audience and redeems it
+//! as a client assertion for an app registration or user-assigned managed
+//! identity with a federated identity credential for the repository.
+//!
+//! [`DefaultAzureCredential`](crate::default_azure_credential::DefaultAzureCredential)
+//! adds this source after workload identity when both variables are set as
+//! the chain is built, using `AZURE_TENANT_ID` and `AZURE_CLIENT_ID` from its
+//! environment settings.
+
+use crate::{
+    authority_host::AuthorityHost,
+    client_assertion_credential::{
+        ClientAssertionCredential, ClientAssertionCredentialOptions, TOKEN_EXCHANGE_AUDIENCE,
+    },
+};
+use azure_core::{
+    credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::{ClientOptions, Context, Method, Pipeline, PipelineSendOptions, Request, Url, headers},
+    json::from_json,
+};
+use serde::Deserialize;
+use std::{env, sync::Arc};
+
+pub(crate) const ACTIONS_ID_TOKEN_REQUEST_TOKEN: &str = "ACTIONS_ID_TOKEN_REQUEST_TOKEN";
+pub(crate) const ACTIONS_ID_TOKEN_REQUEST_URL: &str = "ACTIONS_ID_TOKEN_REQUEST_URL";
+
+/// Whether `lookup` finds the variables a workflow needs to request ID tokens.
+pub(crate) fn can_request_id_tokens(lookup: impl Fn(&str) -> Option<String>) -> bool {
+    [ACTIONS_ID_TOKEN_REQUEST_URL, ACTIONS_ID_TOKEN_REQUEST_TOKEN]
+        .into_iter()
+        .all(|name| lookup(name).is_some_and(|value| !value.is_empty()))
+}
+
+/// Options for constructing a [`GitHubActionsCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct GitHubActionsCredentialOptions {
+    /// Options for the HTTP pipeline used to request ID tokens and tokens.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+}
+
+#[derive(Deserialize)]
+struct IdTokenResponse {
+    value: Secret,
+}
+
+#[derive(Debug)]
+/// Enables authentication to Azure Active Directory from a GitHub Actions
+/// workflow using its OIDC ID token.
+pub struct GitHubActionsCredential {
+    credential: Arc<ClientAssertionCredential>,
+}
+
+impl GitHubActionsCredential {
+    /// Create a new `GitHubActionsCredential` from
+    /// `ACTIONS_ID_TOKEN_REQUEST_URL` and `ACTIONS_ID_TOKEN_REQUEST_TOKEN`.
+    pub fn new<T, C>(
+        tenant_id: T,
+        client_id: C,
+        options: Option<GitHubActionsCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+    {
+        Self::from_lookup(tenant_id, client_id, options, |name| env::var(name).ok())
+    }
+
+    /// Create a new `GitHubActionsCredential` that requests ID tokens from
+    /// `request_url` with `request_token`, instead of reading them from the
+    /// environment.
+    pub fn with_id_token_request<T, C>(
+        tenant_id: T,
+        client_id: C,
+        request_url: &str,
+        request_token: Secret,
+        options: Option<GitHubActionsCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+    {
+        let GitHubActionsCredentialOptions {
+            client_options,
+            authority_host,
+        } = options.unwrap_or_default();
+        let mut request_url = Url::parse(request_url).map_err(|error| {
+            Error::with_error(
+                ErrorKind::Credential,
+                error,
+                format!("{ACTIONS_ID_TOKEN_REQUEST_URL} is not a valid URL"),
+            )
+        })?;
+        request_url
+            .query_pairs_mut()
+            .append_pair("audience", TOKEN_EXCHANGE_AUDIENCE);
+
+        let pipeline_options = client_options.clone();
+        let credential = ClientAssertionCredential::new(
+            tenant_id,
+            client_id,
+            move || {
+                request_id_token(
+                    pipeline_options.clone(),
+                    request_url.clone(),
+                    request_token.clone(),
+                )
+            },
+            Some(ClientAssertionCredentialOptions {
+                client_options,
+                authority_host,
+            }),
+        )?;
+        Ok(Arc::new(Self { credential }))
+    }
+
+    fn from_lookup<T, C>(
+        tenant_id: T,
+        client_id: C,
+        options: Option<GitHubActionsCredentialOptions>,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+    {
+        let variable = |name| {
+            lookup(name)
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| {
+                    Error::with_message(
+                        ErrorKind::Credential,
+                        format!(
+                            "{name} is not set. Grant the workflow the `id-token: write` permission"
+                        ),
+                    )
+                })
+        };
+        let request_url = variable(ACTIONS_ID_TOKEN_REQUEST_URL)?;
+        let request_token = variable(ACTIONS_ID_TOKEN_REQUEST_TOKEN)?;
+        Self::with_id_token_request(
+            tenant_id,
+            client_id,
+            &request_url,
+            Secret::new(request_token),
+            options,
+        )
+    }
+}
+
+async fn request_id_token(
+    client_options: ClientOptions,
+    url: Url,
+    request_token: Secret,
+) -> azure_core::Result<String> {
+    let pipeline = Pipeline::new(None, None, client_options, vec![], vec![], None);
+    let mut request = Request::new(url, Method::Get);
+    request.insert_header(
+        headers::AUTHORIZATION,
+        format!("Bearer {}", request_token.secret()),
+    );
+
+    let response = pipeline
+        .send(
+            &Context::new(),
+            &mut request,
+            Some(PipelineSendOptions {
+                skip_checks: true,
+                ..PipelineSendOptions::default()
+            }),
+        )
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            format!("GitHubActionsCredential failed to get an ID token with status {status}"),
+        ));
+    }
+    let response: IdTokenResponse = from_json(response.into_body())?;
+    Ok(response.value.secret().to_owned())
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for GitHubActionsCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.credential.get_token(scopes, options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TestServer, http_response};
+
+    const ID_TOKEN_BODY: &str = r#"{"value":"fake-id-token"}"#;
+
+    fn credential(
+        github: &TestServer,
+        entra: &TestServer,
+    ) -> azure_core::Result<Arc<GitHubActionsCredential>> {
+        GitHubActionsCredential::with_id_token_request(
+            "fake-tenant",
+            "fake-client",
+            &format!(
+                "{}_apis/distributedtask/idtoken?api-version=2.0",
+                github.url
+            ),
+            Secret::new("fake-request-token"),
+            Some(GitHubActionsCredentialOptions {
+                authority_host: Some(entra.authority_host()?),
+                ..GitHubActionsCredentialOptions::default()
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn exchanges_id_token() -> azure_core::Result<()> {
+        let github = TestServer::start(Some(&http_response(
+            "200 OK",
+            "application/json",
+            ID_TOKEN_BODY,
+        )))
+        .await?;
+        let entra = TestServer::entra().await?;
+
+        let token = credential(&github, &entra)?
+            .get_token(&["fake-scope"], None)
+            .await?;
+
+        assert_eq!(token.token.secret(), "fake-token");
+        let id_token_requests = github.requests();
+        let id_token_request = id_token_requests
+            .first()
+            .map(|request| request.to_ascii_lowercase())
+            .unwrap_or_default();
+        assert!(id_token_request.starts_with(
+            "get /_apis/distributedtask/idtoken?api-version=2.0&audience=api%3a%2f%2fazureadtokenexchange "
+        ));
+        assert!(id_token_request.contains("authorization: bearer fake-request-token"));
+        let token_requests = entra.requests();
+        let token_request = token_requests
+            .first()
+            .map(String::as_str)
+            .unwrap_or_default();
+        assert!(token_request.starts_with("POST /fake-tenant/oauth2/v2.0/token "));
+        assert!(token_request.contains("client_assertion=fake-id-token"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_id_token_errors() -> azure_core::Result<()> {
+        let github =
+            TestServer::start(Some(&http_response("403 Forbidden", "text/plain", ""))).await?;
+        let entra = TestServer::entra().await?;
+
+        let result = credential(&github, &entra)?
+            .get_token(&["fake-scope"], None)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        assert!(entra.requests().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn reports_missing_id_token_variables() {
+        let result =
+            GitHubActionsCredential::from_lookup("fake-tenant", "fake-client", None, |name| {
+                (name == ACTIONS_ID_TOKEN_REQUEST_URL)
+                    .then(|| "https://example.com/idtoken".to_owned())
+            });
+
+        assert!(matches!(
+            result,
+            Err(ref error) if error.to_string().contains("ACTIONS_ID_TOKEN_REQUEST_TOKEN is not set")
+        ));
+    }
+}
@@ -0,0 +1,371 @@
+//! Detect where the application is running from environment variables and
+//! well-known files.
+//!
+//! [`detect`] returns the [`HostingEnvironment`] along with the [`Evidence`]
+//! that identified it.  The result can order the sources of
+//! [`DefaultAzureCredential`](crate::default_azure_credential::DefaultAzureCredential)
+//! through
+//! [`DefaultAzureCredentialBuilder::with_hosting_environment`](crate::default_azure_credential::DefaultAzureCredentialBuilder::with_hosting_environment).
+//!
+//! Detection is a best-effort heuristic: it never sends requests, and an
+//! environment that matches none of the known signals is reported as
+//! [`HostingEnvironment::LocalDevelopment`].
+
+use crate::{
+    default_azure_credential::DefaultAzureCredentialSource,
+    environment_credential::AZURE_FEDERATED_TOKEN_FILE,
+    imds::{IDENTITY_ENDPOINT, MSI_ENDPOINT},
+};
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// The asset tag Azure sets on the chassis of its virtual machines.
+const AZURE_CHASSIS_ASSET_TAG: &str = "7783-7084-3265-9085-8269-3286-77";
+/// Exposes the chassis asset tag on Linux.
+const CHASSIS_ASSET_TAG_PATH: &str = "/sys/class/dmi/id/chassis_asset_tag";
+
+/// Where the application is running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HostingEnvironment {
+    /// A pod on Azure Kubernetes Service with workload identity enabled.
+    AksWorkloadIdentity,
+    /// Azure App Service, including Azure Functions.
+    AppService,
+    /// A machine connected to Azure Arc.
+    AzureArc,
+    /// Azure Cloud Shell.
+    CloudShell,
+    /// A GitHub Actions workflow.
+    GitHubActions,
+    /// An Azure Pipelines job.
+    AzurePipelines,
+    /// An Azure virtual machine or virtual machine scale set.
+    AzureVm,
+    /// None of the above, usually a developer's machine.
+    LocalDevelopment,
+}
+
+impl HostingEnvironment {
+    /// The `DefaultAzureCredential` sources suited to this environment, in
+    /// the order they should be attempted.
+    #[must_use]
+    pub fn sources(self) -> Vec<DefaultAzureCredentialSource> {
+        match self {
+            Self::AksWorkloadIdentity => vec![
+                DefaultAzureCredentialSource::WorkloadIdentity,
+                DefaultAzureCredentialSource::Environment,
+            ],
+            Self::AppService | Self::AzureArc | Self::AzureVm => vec![
+                DefaultAzureCredentialSource::ManagedIdentity,
+                DefaultAzureCredentialSource::Environment,
+            ],
+            Self::CloudShell => {
+                let mut sources = vec![DefaultAzureCredentialSource::ManagedIdentity];
+                #[cfg(not(target_arch = "wasm32"))]
+                sources.push(DefaultAzureCredentialSource::AzureCli);
+                sources
+            }
+            Self::GitHubActions => {
+                let mut sources = vec![
+                    DefaultAzureCredentialSource::Environment,
+                    DefaultAzureCredentialSource::WorkloadIdentity,
+                    DefaultAzureCredentialSource::GitHubActions,
+                ];
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    sources.push(DefaultAzureCredentialSource::AzureCli);
+                    sources.push(DefaultAzureCredentialSource::AzurePowerShell);
+                }
+                sources
+            }
+            Self::AzurePipelines => {
+                let mut sources = vec![
+                    DefaultAzureCredentialSource::Environment,
+                    DefaultAzureCredentialSource::WorkloadIdentity,
+                ];
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    sources.push(DefaultAzureCredentialSource::AzureCli);
+                    sources.push(DefaultAzureCredentialSource::AzurePowerShell);
+                }
+                sources
+            }
+            Self::LocalDevelopment => {
+                let mut sources = vec![DefaultAzureCredentialSource::Environment];
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    sources.push(DefaultAzureCredentialSource::AzureCli);
+                    sources.push(DefaultAzureCredentialSource::AzureDeveloperCli);
+                    sources.push(DefaultAzureCredentialSource::AzurePowerShell);
+                    sources.push(DefaultAzureCredentialSource::VisualStudioCode);
+                }
+                sources
+            }
+        }
+    }
+}
+
+impl fmt::Display for HostingEnvironment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::AksWorkloadIdentity => "AKS workload identity",
+            Self::AppService => "App Service",
+            Self::AzureArc => "Azure Arc",
+            Self::CloudShell => "Cloud Shell",
+            Self::GitHubActions => "GitHub Actions",
+            Self::AzurePipelines => "Azure Pipelines",
+            Self::AzureVm => "Azure VM",
+            Self::LocalDevelopment => "local development",
+        })
+    }
+}
+
+/// A signal that identified the hosting environment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Evidence {
+    /// The named environment variable is set.  Its value is not recorded.
+    EnvironmentVariable(String),
+    /// The file exists, or has the expected contents.
+    File(PathBuf),
+}
+
+impl fmt::Display for Evidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EnvironmentVariable(name) => write!(f, "environment variable {name}"),
+            Self::File(path) => write!(f, "file {}", path.display()),
+        }
+    }
+}
+
+/// The detected hosting environment and the evidence for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Detection {
+    /// The detected environment.
+    pub environment: HostingEnvironment,
+    /// The signals that identified the environment.  Empty for
+    /// [`HostingEnvironment::LocalDevelopment`].
+    pub evidence: Vec<Evidence>,
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.environment)?;
+        if self.evidence.is_empty() {
+            return Ok(());
+        }
+        let evidence = self
+            .evidence
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, " (detected from {evidence})")
+    }
+}
+
+/// Detect the hosting environment of the current process.
+#[must_use]
+pub fn detect() -> Detection {
+    detect_with(
+        |name| env::var_os(name).is_some_and(|value| !value.is_empty()),
+        Path::exists,
+        |path| fs::read_to_string(path).ok(),
+    )
+}
+
+/// Environments identified by environment variables, in the order they are
+/// checked, with the variables that must all be set.  CI systems are checked
+/// before the files below because hosted agents run on Azure VMs.
+const ENVIRONMENT_VARIABLE_RULES: [(HostingEnvironment, &[&str]); 6] = [
+    (
+        HostingEnvironment::AksWorkloadIdentity,
+        &[AZURE_FEDERATED_TOKEN_FILE, "KUBERNETES_SERVICE_HOST"],
+    ),
+    (
+        HostingEnvironment::AppService,
+        &["WEBSITE_SITE_NAME", IDENTITY_ENDPOINT],
+    ),
+    (
+        HostingEnvironment::AzureArc,
+        &[IDENTITY_ENDPOINT, "IMDS_ENDPOINT"],
+    ),
+    (HostingEnvironment::CloudShell, &["ACC_CLOUD", MSI_ENDPOINT]),
+    (HostingEnvironment::GitHubActions, &["GITHUB_ACTIONS"]),
+    (HostingEnvironment::AzurePipelines, &["TF_BUILD"]),
+];
+
+/// The Azure Arc agent, which identifies a machine connected to Azure Arc.
+fn arc_agent_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        env::var_os("ProgramFiles").map(|dir| {
+            PathBuf::from(dir)
+                .join("AzureConnectedMachineAgent")
+                .join("himds.exe")
+        })
+    } else {
+        Some(PathBuf::from("/opt/azcmagent/bin/himds"))
+    }
+}
+
+fn detect_with(
+    is_set: impl Fn(&str) -> bool,
+    exists: impl Fn(&Path) -> bool,
+    read_file: impl Fn(&Path) -> Option<String>,
+) -> Detection {
+    for (environment, names) in ENVIRONMENT_VARIABLE_RULES {
+        if names.iter().all(|name| is_set(name)) {
+            return Detection {
+                environment,
+                evidence: names
+                    .iter()
+                    .map(|name| Evidence::EnvironmentVariable((*name).to_owned()))
+                    .collect(),
+            };
+        }
+    }
+
+    if let Some(path) = arc_agent_path().filter(|path| exists(path)) {
+        return Detection {
+            environment: HostingEnvironment::AzureArc,
+            evidence: vec![Evidence::File(path)],
+        };
+    }
+
+    let path = Path::new(CHASSIS_ASSET_TAG_PATH);
+    if read_file(path).is_some_and(|tag| tag.trim() == AZURE_CHASSIS_ASSET_TAG) {
+        return Detection {
+            environment: HostingEnvironment::AzureVm,
+            evidence: vec![Evidence::File(path.to_path_buf())],
+        };
+    }
+
+    Detection {
+        environment: HostingEnvironment::LocalDevelopment,
+        evidence: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn detect_from(vars: &[(&str, &str)], files: &[(&str, &str)]) -> Detection {
+        let vars = vars.iter().copied().collect::<HashMap<_, _>>();
+        let files = files
+            .iter()
+            .map(|(path, contents)| (PathBuf::from(path), *contents))
+            .collect::<HashMap<_, _>>();
+        detect_with(
+            |name| vars.contains_key(name),
+            |path| files.contains_key(path),
+            |path| files.get(path).map(|contents| (*contents).to_owned()),
+        )
+    }
+
+    #[test]
+    fn nothing_detected_is_local_development() {
+        let detection = detect_from(&[], &[]);
+
+        assert_eq!(detection.environment, HostingEnvironment::LocalDevelopment);
+        assert!(detection.evidence.is_empty());
+        assert_eq!(detection.to_string(), "local development");
+    }
+
+    #[test]
+    fn detects_aks_workload_identity() {
+        let detection = detect_from(
+            &[
+                (AZURE_FEDERATED_TOKEN_FILE, "/var/run/secrets/token"),
+                ("KUBERNETES_SERVICE_HOST", "10.0.0.1"),
+            ],
+            &[],
+        );
+
+        assert_eq!(
+            detection.environment,
+            HostingEnvironment::AksWorkloadIdentity
+        );
+        assert_eq!(
+            detection.to_string(),
+            "AKS workload identity (detected from environment variable AZURE_FEDERATED_TOKEN_FILE, environment variable KUBERNETES_SERVICE_HOST)"
+        );
+    }
+
+    #[test]
+    fn managed_identity_endpoints_distinguish_environments() {
+        let app_service = detect_from(
+            &[
+                ("WEBSITE_SITE_NAME", "app"),
+                (IDENTITY_ENDPOINT, "http://localhost:8081/msi/token"),
+            ],
+            &[],
+        );
+        let arc = detect_from(
+            &[
+                (
+                    IDENTITY_ENDPOINT,
+                    "http://localhost:40342/metadata/identity",
+                ),
+                ("IMDS_ENDPOINT", "http://localhost:40342"),
+            ],
+            &[],
+        );
+        let cloud_shell = detect_from(
+            &[
+                ("ACC_CLOUD", "PROD"),
+                (MSI_ENDPOINT, "http://localhost:50342/oauth2/token"),
+            ],
+            &[],
+        );
+
+        assert_eq!(app_service.environment, HostingEnvironment::AppService);
+        assert_eq!(arc.environment, HostingEnvironment::AzureArc);
+        assert_eq!(cloud_shell.environment, HostingEnvironment::CloudShell);
+    }
+
+    #[test]
+    fn ci_is_preferred_over_the_vm_it_runs_on() {
+        let detection = detect_from(
+            &[("GITHUB_ACTIONS", "true")],
+            &[(CHASSIS_ASSET_TAG_PATH, AZURE_CHASSIS_ASSET_TAG)],
+        );
+
+        assert_eq!(detection.environment, HostingEnvironment::GitHubActions);
+        assert_eq!(
+            detection.evidence,
+            vec![Evidence::EnvironmentVariable(String::from(
+                "GITHUB_ACTIONS"
+            ))]
+        );
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn detects_azure_arc_from_agent() {
+        let detection = detect_from(&[], &[("/opt/azcmagent/bin/himds", "")]);
+
+        assert_eq!(detection.environment, HostingEnvironment::AzureArc);
+        assert_eq!(
+            detection.to_string(),
+            "Azure Arc (detected from file /opt/azcmagent/bin/himds)"
+        );
+    }
+
+    #[test]
+    fn detects_azure_vm_from_chassis_asset_tag() {
+        let detection = detect_from(
+            &[],
+            &[(CHASSIS_ASSET_TAG_PATH, "7783-7084-3265-9085-8269-3286-77\n")],
+        );
+        let other = detect_from(&[], &[(CHASSIS_ASSET_TAG_PATH, "No Asset Tag\n")]);
+
+        assert_eq!(detection.environment, HostingEnvironment::AzureVm);
+        assert_eq!(other.environment, HostingEnvironment::LocalDevelopment);
+    }
+}
@@ -0,0 +1,291 @@
+//! Reachability checks for the Azure Instance Metadata Service (IMDS) used by
+//! managed identity.
+
+use async_lock::OnceCell;
+use azure_core::{
+    credentials::{AccessToken, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::{
+        ClientOptions, Context, Method, Pipeline, PipelineSendOptions, Request, RetryOptions, Url,
+    },
+    sleep::sleep,
+    time::Duration,
+};
+use futures::future::{Either, select};
+use std::{env, pin::pin, sync::Arc};
+
+/// The IMDS token endpoint queried by `ManagedIdentityCredential`.
+pub const IMDS_ENDPOINT: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+/// Set when managed identity is provided by App Service, Service Fabric or
+/// Azure Arc rather than IMDS.
+pub const IDENTITY_ENDPOINT: &str = "IDENTITY_ENDPOINT";
+/// Set when managed identity is provided by Azure ML or Cloud Shell rather
+/// than IMDS.
+pub const MSI_ENDPOINT: &str = "MSI_ENDPOINT";
+
+/// How long to wait for IMDS before treating it as unreachable.
+pub const DEFAULT_PROBE_TIMEOUT: Duration = Duration::seconds(1);
+
+/// Whether `ManagedIdentityCredential` will use IMDS in this environment.
+pub fn uses_imds() -> bool {
+    env::var_os(IDENTITY_ENDPOINT).is_none() && env::var_os(MSI_ENDPOINT).is_none()
+}
+
+/// Whether anything answers HTTP requests at `endpoint` within `timeout`.
+///
+/// Any response counts, including the error IMDS returns for a request that
+/// lacks the `Metadata` header.  The probe uses `client_options` with retries
+/// disabled, so that it goes through the same transport and policies as the
+/// credential it guards.
+pub async fn is_reachable(
+    endpoint: &str,
+    timeout: Duration,
+    client_options: ClientOptions,
+) -> bool {
+    let Ok(endpoint) = Url::parse(endpoint) else {
+        return false;
+    };
+    let options = ClientOptions {
+        retry: RetryOptions::none(),
+        ..client_options
+    };
+    let pipeline = Pipeline::new(None, None, options, vec![], vec![], None);
+    let ctx = Context::new();
+    let mut request = Request::new(endpoint, Method::Get);
+    let send_options = PipelineSendOptions {
+        skip_checks: true,
+        ..PipelineSendOptions::default()
+    };
+
+    let response = pin!(pipeline.send(&ctx, &mut request, Some(send_options)));
+    match select(response, pin!(sleep(timeout))).await {
+        Either::Left((response, _)) => response.is_ok(),
+        Either::Right(_) => false,
+    }
+}
+
+/// Wraps a managed identity credential so that it fails fast when IMDS does
+/// not respond, instead of waiting for the HTTP timeout.
+///
+/// The probe runs on the first token request.  Once IMDS has responded, the
+/// credential is used without probing again.  A failed probe is not
+/// remembered, so that a slow first response does not disable managed
+/// identity for good; the next token request probes again.
+#[derive(Debug)]
+pub struct ProbedManagedIdentityCredential {
+    credential: Arc<dyn TokenCredential>,
+    endpoint: String,
+    timeout: Duration,
+    client_options: ClientOptions,
+    reachable: OnceCell<()>,
+}
+
+impl ProbedManagedIdentityCredential {
+    pub fn new(
+        credential: Arc<dyn TokenCredential>,
+        endpoint: String,
+        timeout: Duration,
+        client_options: ClientOptions,
+    ) -> Self {
+        Self {
+            credential,
+            endpoint,
+            timeout,
+            client_options,
+            reachable: OnceCell::new(),
+        }
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for ProbedManagedIdentityCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let reachable = self
+            .reachable
+            .get_or_try_init(|| async {
+                if is_reachable(&self.endpoint, self.timeout, self.client_options.clone()).await {
+                    Ok(())
+                } else {
+                    Err(())
+                }
+            })
+            .await;
+        if reachable.is_err() {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                format!(
+                    "ManagedIdentityCredential is unavailable: IMDS did not respond at {} within {} ms",
+                    self.endpoint,
+                    self.timeout.whole_milliseconds()
+                ),
+            ));
+        }
+        self.credential.get_token(scopes, options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockPolicy, TestServer, http_response};
+    use azure_core::{
+        credentials::Secret,
+        http::{
+            AsyncRawResponse, StatusCode,
+            headers::Headers,
+            policies::{Policy, PolicyResult},
+        },
+        time::OffsetDateTime,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const SCOPE: &str = "https://management.azure.com/.default";
+
+    #[derive(Debug)]
+    struct StaticCredential;
+
+    #[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+    impl TokenCredential for StaticCredential {
+        async fn get_token(
+            &self,
+            _scopes: &[&str],
+            _options: Option<TokenRequestOptions<'_>>,
+        ) -> azure_core::Result<AccessToken> {
+            Ok(AccessToken {
+                token: Secret::new("managed-identity-token"),
+                expires_on: OffsetDateTime::now_utc() + Duration::hours(1),
+            })
+        }
+    }
+
+    /// A transport that fails the first request and answers the rest.
+    #[derive(Debug, Default)]
+    struct UnreachableOnce(AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl Policy for UnreachableOnce {
+        async fn send(
+            &self,
+            _ctx: &Context,
+            _request: &mut Request,
+            _next: &[Arc<dyn Policy>],
+        ) -> PolicyResult {
+            if self.0.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(Error::with_message(ErrorKind::Io, "connection refused"));
+            }
+            Ok(AsyncRawResponse::from_bytes(
+                StatusCode::BadRequest,
+                Headers::new(),
+                Vec::new(),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn any_response_is_reachable() -> azure_core::Result<()> {
+        let server =
+            TestServer::start(Some(&http_response("400 Bad Request", "text/plain", ""))).await?;
+
+        assert!(is_reachable(&server.url, DEFAULT_PROBE_TIMEOUT, ClientOptions::default()).await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unanswered_request_times_out() -> azure_core::Result<()> {
+        let server = TestServer::start(None).await?;
+
+        assert!(
+            !is_reachable(
+                &server.url,
+                Duration::milliseconds(100),
+                ClientOptions::default()
+            )
+            .await
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn probe_uses_client_options() {
+        let policy = MockPolicy::new(vec![]);
+
+        assert!(
+            is_reachable(
+                IMDS_ENDPOINT,
+                DEFAULT_PROBE_TIMEOUT,
+                policy.client_options()
+            )
+            .await
+        );
+        assert_eq!(policy.requests(), vec![IMDS_ENDPOINT]);
+    }
+
+    #[tokio::test]
+    async fn probe_result_is_cached() -> azure_core::Result<()> {
+        let server =
+            TestServer::start(Some(&http_response("400 Bad Request", "text/plain", ""))).await?;
+        let credential = ProbedManagedIdentityCredential::new(
+            Arc::new(StaticCredential),
+            server.url.clone(),
+            DEFAULT_PROBE_TIMEOUT,
+            ClientOptions::default(),
+        );
+
+        credential.get_token(&[SCOPE], None).await?;
+        let token = credential.get_token(&[SCOPE], None).await?;
+
+        assert_eq!(token.token.secret(), "managed-identity-token");
+        assert_eq!(server.requests().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unreachable_imds_is_unavailable() -> azure_core::Result<()> {
+        let server = TestServer::start(None).await?;
+        let credential = ProbedManagedIdentityCredential::new(
+            Arc::new(StaticCredential),
+            server.url.clone(),
+            Duration::milliseconds(100),
+            ClientOptions::default(),
+        );
+
+        let result = credential.get_token(&[SCOPE], None).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_probe_is_retried() -> azure_core::Result<()> {
+        let transport = Arc::new(UnreachableOnce::default());
+        let credential = ProbedManagedIdentityCredential::new(
+            Arc::new(StaticCredential),
+            IMDS_ENDPOINT.to_owned(),
+            DEFAULT_PROBE_TIMEOUT,
+            ClientOptions {
+                per_call_policies: vec![transport.clone()],
+                ..ClientOptions::default()
+            },
+        );
+
+        let first = credential.get_token(&[SCOPE], None).await;
+        let second = credential.get_token(&[SCOPE], None).await?;
+        credential.get_token(&[SCOPE], None).await?;
+
+        assert!(first.is_err());
+        assert_eq!(second.token.secret(), "managed-identity-token");
+        // Only the failed probe and the first successful one were sent.
+        assert_eq!(transport.0.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+}
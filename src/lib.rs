@@ -10,14 +10,30 @@
 //!
 //! ## Modules
 //!
+//! - `authority_host`: Selects the Microsoft Entra authority host for the public, China, US Government or a custom cloud, honoring `AZURE_AUTHORITY_HOST`.
+//! - `aws_federated_credential`: Implements authentication from AWS without stored secrets, redeeming a web identity token file or a presigned STS `GetCallerIdentity` request as a federated client assertion. STS requests are signed with an access key from the environment, the container credentials endpoint, the EC2 instance profile or a callback. Not available on `wasm32`.
+//! - `azure_pipelines_credential`: Implements Azure Pipelines authentication through a workload identity federation service connection, exchanging the job's OIDC token from `SYSTEM_OIDCREQUESTURI` for a Microsoft Entra ID token.
+//! - `azure_powershell_credential`: Implements Azure PowerShell based authentication using `Get-AzAccessToken` from the `Az.Accounts` module.
 //! - `azureauth_cli_credentials`: Implements [AzureAuth CLI](https://github.com/AzureAD/microsoft-authentication-cli) based authentication.  Originally from `azure_identity` 0.20.0.
 //! - `cache`: Re-implements the azure-identity caching provider
 //! - `chained_token_credential`: Implements credential chaining to try multiple authentication methods.  This method has been added to an unreleased version of the upstream `azure_identity` crate.  This will be removed once the updated upstream crate is released.
-//! - `default_azure_credential`: Recreates [Go `DefaultAzureCredential`](https://learn.microsoft.com/en-us/azure/developer/go/sdk/authentication/credential-chains#defaultazurecredential-overview) using the currently supported Rust credential types. On non-`wasm32` targets, the default order is environment, workload identity, managed identity, Azure CLI, and Azure Developer CLI. On `wasm32`, the default order is environment, workload identity, and managed identity. `AzurePowerShellCredential` is not currently implemented in this crate.
+//! - `client_assertion_credential`: Implements service principal authentication with a client assertion returned by an async callback, such as a JWT from an in-house signer or another OIDC issuer.
+//! - `client_certificate_credential`: Implements service principal authentication with a client certificate loaded from a PEM or PKCS#12 file using OpenSSL. Requires the `client_certificate` feature and is not available on `wasm32`.
+//! - `client_secrets_credential`: Implements service principal authentication with an ordered list of client secrets, falling back to the next secret on `invalid_client` errors during a rotation window.
+//! - `default_azure_credential`: Recreates [Go `DefaultAzureCredential`](https://learn.microsoft.com/en-us/azure/developer/go/sdk/authentication/credential-chains#defaultazurecredential-overview) using the currently supported Rust credential types. On non-`wasm32` targets, the default order is environment, workload identity, managed identity, Azure CLI, Azure Developer CLI, Azure PowerShell, and Visual Studio Code. On `wasm32`, the default order is environment, workload identity, and managed identity. A GitHub Actions source follows workload identity when the job can request ID tokens. Setting `AZURE_TOKEN_CREDENTIALS` to `dev`, `prod`, or a single credential type name restricts the chain. The managed identity source first probes IMDS with a short, configurable timeout so that it fails fast off Azure. `AzureauthCliCredential` and `DeviceCodeCredential` can be opted into the chain at a chosen position.
 //! - `device_code`: Provides device code flow authentication support for Azure services.  Originally from `azure_identity` 0.20.0.
 //! - `devicecode_credentials`: Implements a credential that can authenticate using device code flow. Uses the `device_code` module's functionality.
+//! - `diagnostics`: Explains which `DefaultAzureCredential` sources can authenticate, with remediation hints.
 //! - `environment_credential`: Recreates an `EnvironmentCredential`-style helper for service principal authentication from environment variables.
+//! - `gcp_federated_credential`: Implements authentication from Google Cloud without stored secrets, redeeming a metadata server identity token as a federated client assertion.
+//! - `github_actions_credential`: Implements GitHub Actions OIDC authentication, exchanging the workflow's ID token for a Microsoft Entra ID token. `DefaultAzureCredential` adds it after workload identity only when `ACTIONS_ID_TOKEN_REQUEST_URL` and `ACTIONS_ID_TOKEN_REQUEST_TOKEN` are set.
+//! - `hosting_environment`: Detects where the application is running, such as AKS, App Service, an Azure VM or a CI system, and orders the `DefaultAzureCredential` sources to suit it.
 //! - `refresh_token`: Handles refresh token operations for maintaining authentication sessions.  Originally from `azure_identity` 0.20.0.
+//! - `shared_token_cache_credential`: Implements authentication using accounts from the MSAL token cache shared by Microsoft developer tools.
+//! - `spiffe_credential`: Implements authentication with a SPIFFE JWT-SVID fetched from the Workload API socket in `SPIFFE_ENDPOINT_SOCKET`, redeemed as a federated client assertion. Requires the `spiffe` feature and a Unix target.
+//! - `tenant`: Requests tokens for a specific tenant and restricts the tenants a credential may authenticate in.
+//! - `username_password_credential`: Implements username and password (resource owner password credentials) authentication for legacy automation, reporting MFA-required and consent-required errors distinctly.
+//! - `visual_studio_code_credential`: Implements authentication using the account signed in to the Visual Studio Code Azure extensions.
 //!
 
 #![forbid(unsafe_code)]
@@ -29,11 +45,38 @@
     clippy::unwrap_used
 )]
 
+pub mod authority_host;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod aws_federated_credential;
+pub mod azure_pipelines_credential;
+pub mod azure_powershell_credential;
 pub mod azureauth_cli_credentials;
 pub mod cache;
+#[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+mod certificate;
 pub mod chained_token_credential;
+pub mod client_assertion_credential;
+#[cfg(all(feature = "client_certificate", not(target_arch = "wasm32")))]
+pub mod client_certificate_credential;
+pub mod client_secrets_credential;
 pub mod default_azure_credential;
 pub mod device_code;
 pub mod devicecode_credentials;
+pub mod diagnostics;
+mod dotenv;
 pub mod environment_credential;
+pub mod gcp_federated_credential;
+pub mod github_actions_credential;
+pub mod hosting_environment;
+mod imds;
+mod msal_cache;
 pub mod refresh_token;
+pub mod shared_token_cache_credential;
+#[cfg(all(feature = "spiffe", unix))]
+pub mod spiffe_credential;
+pub mod tenant;
+#[cfg(test)]
+mod test_util;
+mod token_endpoint;
+pub mod username_password_credential;
+pub mod visual_studio_code_credential;
@@ -0,0 +1,199 @@
+//! Reads the JSON token cache format persisted by MSAL-based tools.
+
+use crate::{authority_host::AuthorityHost, cache::should_refresh};
+use azure_core::{
+    credentials::{AccessToken, Secret},
+    error::{Error, ErrorKind},
+    http::Url,
+    json::from_json,
+};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+use time::OffsetDateTime;
+
+/// The family ID shared by Microsoft first-party applications whose refresh
+/// tokens can be redeemed by any member of the family.
+const FAMILY_ID: &str = "1";
+
+/// The environments MSAL records for the public cloud, which are aliases of
+/// one another.
+const PUBLIC_CLOUD_ENVIRONMENTS: [&str; 4] = [
+    "login.microsoftonline.com",
+    "login.windows.net",
+    "login.microsoft.com",
+    "sts.windows.net",
+];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MsalCache {
+    #[serde(rename = "Account", default)]
+    accounts: HashMap<String, MsalAccount>,
+    #[serde(rename = "AccessToken", default)]
+    access_tokens: HashMap<String, MsalAccessToken>,
+    #[serde(rename = "RefreshToken", default)]
+    refresh_tokens: HashMap<String, MsalRefreshToken>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MsalAccount {
+    home_account_id: String,
+    environment: String,
+    realm: String,
+    username: String,
+}
+
+impl MsalAccount {
+    pub fn home_account_id(&self) -> &str {
+        &self.home_account_id
+    }
+
+    /// The tenant the account was signed in to.
+    pub fn realm(&self) -> &str {
+        &self.realm
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MsalAccessToken {
+    home_account_id: String,
+    environment: String,
+    client_id: String,
+    secret: Secret,
+    realm: String,
+    target: String,
+    expires_on: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MsalRefreshToken {
+    home_account_id: String,
+    environment: String,
+    client_id: String,
+    secret: Secret,
+    #[serde(default)]
+    family_id: Option<String>,
+}
+
+impl MsalRefreshToken {
+    pub fn secret(&self) -> &Secret {
+        &self.secret
+    }
+}
+
+impl MsalCache {
+    pub fn from_file(path: &Path) -> azure_core::Result<Self> {
+        let contents = fs::read(path).map_err(|error| {
+            Error::with_error(
+                ErrorKind::Io,
+                error,
+                format!("unable to read MSAL token cache {}", path.display()),
+            )
+        })?;
+        from_json(contents)
+    }
+
+    /// The accounts of `authority_host` in the cache, with one entry per home
+    /// account ID.
+    pub fn accounts(&self, authority_host: &AuthorityHost) -> Vec<&MsalAccount> {
+        let environment = Environment::new(authority_host);
+        let mut accounts = self
+            .accounts
+            .values()
+            .filter(|account| environment.matches(&account.environment))
+            .collect::<Vec<_>>();
+        accounts.sort_by_key(|account| account.home_account_id.to_ascii_lowercase());
+        accounts.dedup_by(|a, b| a.home_account_id.eq_ignore_ascii_case(&b.home_account_id));
+        accounts
+    }
+
+    /// Find an access token for the account that `authority_host` issued, is
+    /// valid for all of `scopes` and is not about to expire.
+    pub fn access_token(
+        &self,
+        authority_host: &AuthorityHost,
+        home_account_id: &str,
+        client_id: &str,
+        realm: &str,
+        scopes: &[&str],
+    ) -> Option<AccessToken> {
+        let environment = Environment::new(authority_host);
+        self.access_tokens
+            .values()
+            .filter(|token| {
+                environment.matches(&token.environment)
+                    && token.home_account_id.eq_ignore_ascii_case(home_account_id)
+                    && token.client_id.eq_ignore_ascii_case(client_id)
+                    && token.realm.eq_ignore_ascii_case(realm)
+                    && scopes.iter().all(|scope| {
+                        token
+                            .target
+                            .split_whitespace()
+                            .any(|target| target.eq_ignore_ascii_case(scope))
+                    })
+            })
+            .filter_map(|token| {
+                let expires_on =
+                    token.expires_on.parse().ok().and_then(|timestamp| {
+                        OffsetDateTime::from_unix_timestamp(timestamp).ok()
+                    })?;
+                Some(AccessToken {
+                    token: token.secret.clone(),
+                    expires_on,
+                })
+            })
+            .find(|token| !should_refresh(token))
+    }
+
+    /// Find a refresh token from `authority_host` for the account that
+    /// `client_id` can redeem, preferring one issued to the client over a
+    /// family refresh token.
+    pub fn refresh_token(
+        &self,
+        authority_host: &AuthorityHost,
+        home_account_id: &str,
+        client_id: &str,
+    ) -> Option<&MsalRefreshToken> {
+        let environment = Environment::new(authority_host);
+        let tokens = self.refresh_tokens.values().filter(|token| {
+            environment.matches(&token.environment)
+                && token.home_account_id.eq_ignore_ascii_case(home_account_id)
+        });
+        let mut family = None;
+        for token in tokens {
+            if token.client_id.eq_ignore_ascii_case(client_id) {
+                return Some(token);
+            }
+            if family.is_none() && token.family_id.as_deref() == Some(FAMILY_ID) {
+                family = Some(token);
+            }
+        }
+        family
+    }
+}
+
+/// The MSAL `environment` of an authority host, which is its host name.
+struct Environment(String);
+
+impl Environment {
+    fn new(authority_host: &AuthorityHost) -> Self {
+        Self(
+            Url::parse(authority_host.as_str())
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Whether an entry recorded for `environment` was issued by this
+    /// authority host.
+    fn matches(&self, environment: &str) -> bool {
+        let environment = environment.to_ascii_lowercase();
+        environment == self.0
+            || (PUBLIC_CLOUD_ENVIRONMENTS.contains(&environment.as_str())
+                && PUBLIC_CLOUD_ENVIRONMENTS.contains(&self.0.as_str()))
+    }
+}
@@ -3,11 +3,12 @@
 
 //! Refresh tokens.
 
+use crate::authority_host::AuthorityHost;
 use azure_core::{
     credentials::Secret,
     error::{Error, ErrorKind},
     http::{
-        ClientOptions, Context, Method, Pipeline, Request, Url,
+        ClientOptions, Context, Method, Pipeline, Request,
         headers::{self, content_type},
     },
 };
@@ -15,6 +16,18 @@ use serde::Deserialize;
 use std::fmt;
 use url::form_urlencoded;
 
+/// Options for [`exchange_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeOptions {
+    /// Scopes to request for the new access token.  When empty, the scopes
+    /// of the original grant are used.
+    pub scopes: Vec<String>,
+    /// Options for the HTTP pipeline used to redeem the refresh token.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+}
+
 /// Exchange a refresh token for a new access token and refresh token.
 #[allow(dead_code)]
 pub async fn exchange(
@@ -23,7 +36,26 @@ pub async fn exchange(
     client_secret: Option<&str>,
     refresh_token: &Secret,
 ) -> azure_core::Result<RefreshTokenResponse> {
-    let pipeline = Pipeline::new(None, None, ClientOptions::default(), vec![], vec![], None);
+    exchange_with_options(
+        tenant_id,
+        client_id,
+        client_secret,
+        refresh_token,
+        ExchangeOptions::default(),
+    )
+    .await
+}
+
+/// Exchange a refresh token for a new access token and refresh token, using
+/// the provided options.
+pub async fn exchange_with_options(
+    tenant_id: &str,
+    client_id: &str,
+    client_secret: Option<&str>,
+    refresh_token: &Secret,
+    options: ExchangeOptions,
+) -> azure_core::Result<RefreshTokenResponse> {
+    let pipeline = Pipeline::new(None, None, options.client_options, vec![], vec![], None);
     let ctx = Context::new();
 
     let encoded = {
@@ -36,12 +68,13 @@ pub async fn exchange(
         if let Some(client_secret) = client_secret {
             encoded = encoded.append_pair("client_secret", client_secret);
         }
+        if !options.scopes.is_empty() {
+            encoded = encoded.append_pair("scope", &options.scopes.join(" "));
+        }
         encoded.finish()
     };
 
-    let url = Url::parse(&format!(
-        "https://login.microsoftonline.com/{tenant_id}/oauth2/v2.0/token"
-    ))?;
+    let url = AuthorityHost::or_from_env(options.authority_host)?.endpoint(tenant_id, "token")?;
 
     let mut req = Request::new(url, Method::Post);
     req.insert_header(
@@ -156,6 +189,7 @@ impl fmt::Display for RefreshTokenError {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::MockPolicy;
 
     fn require_send<T: Send>(_t: T) {}
 
@@ -163,4 +197,70 @@ mod tests {
     fn ensure_that_exchange_is_send() {
         require_send(exchange("UNUSED", "UNUSED", None, &Secret::new("UNUSED")));
     }
+
+    #[test]
+    fn ensure_that_exchange_with_options_is_send() {
+        require_send(exchange_with_options(
+            "UNUSED",
+            "UNUSED",
+            None,
+            &Secret::new("UNUSED"),
+            ExchangeOptions::default(),
+        ));
+    }
+
+    #[tokio::test]
+    async fn exchange_uses_client_options() -> azure_core::Result<()> {
+        let policy = MockPolicy::new(vec![(
+            "/token",
+            r#"{"token_type":"Bearer","scope":"openid profile","expires_in":3600,"ext_expires_in":3600,"access_token":"access","refresh_token":"rotated"}"#,
+        )]);
+
+        let response = exchange_with_options(
+            "fake-tenant",
+            "fake-client",
+            None,
+            &Secret::new("refresh"),
+            ExchangeOptions {
+                client_options: policy.client_options(),
+                ..ExchangeOptions::default()
+            },
+        )
+        .await?;
+
+        assert_eq!(response.access_token().secret(), "access");
+        assert_eq!(response.refresh_token().secret(), "rotated");
+        assert_eq!(
+            policy.requests(),
+            vec!["https://login.microsoftonline.com/fake-tenant/oauth2/v2.0/token"]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn exchange_uses_authority_host() -> azure_core::Result<()> {
+        let policy = MockPolicy::new(vec![(
+            "/token",
+            r#"{"token_type":"Bearer","scope":"openid","expires_in":3600,"ext_expires_in":3600,"access_token":"access","refresh_token":"rotated"}"#,
+        )]);
+
+        exchange_with_options(
+            "fake-tenant",
+            "fake-client",
+            None,
+            &Secret::new("refresh"),
+            ExchangeOptions {
+                client_options: policy.client_options(),
+                authority_host: Some(AuthorityHost::USGovernment),
+                ..ExchangeOptions::default()
+            },
+        )
+        .await?;
+
+        assert_eq!(
+            policy.requests(),
+            vec!["https://login.microsoftonline.us/fake-tenant/oauth2/v2.0/token"]
+        );
+        Ok(())
+    }
 }
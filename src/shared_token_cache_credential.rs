@@ -0,0 +1,619 @@
+//! Authenticate using the MSAL token cache shared by Microsoft developer tools.
+//!
+//! Tools such as Visual Studio and older releases of the Azure CLI persist
+//! their MSAL token cache to `~/.IdentityService/msal.cache`.  This credential
+//! reads that cache, selects an account, and returns a cached access token when
+//! one is still valid.  Otherwise it redeems the account's refresh token
+//! through [`exchange_with_options`].  Only accounts and tokens recorded for
+//! the configured authority host are used.
+//!
+//! Only unencrypted caches can be read.  The cache is never written to; new
+//! refresh tokens are kept in memory for the lifetime of the credential.
+
+use crate::{
+    authority_host::AuthorityHost,
+    cache::TokenCache,
+    devicecode_credentials::convert_expires_in,
+    msal_cache::{MsalAccount, MsalCache},
+    refresh_token::{ExchangeOptions, exchange_with_options},
+    visual_studio_code_credential::home_dir,
+};
+use async_lock::Mutex;
+use azure_core::{
+    credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::ClientOptions,
+};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+/// The client ID of the Azure CLI, which is used to redeem refresh tokens by default.
+const AZURE_CLI_CLIENT_ID: &str = "04b07795-8ddb-461a-bbee-02f9e1bf7b46";
+const CACHE_DIR: &str = ".IdentityService";
+const CACHE_FILE: &str = "msal.cache";
+
+/// Options for constructing a [`SharedTokenCacheCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct SharedTokenCacheCredentialOptions {
+    /// Select the cached account with this username.
+    pub username: Option<String>,
+    /// Select the cached account with this home account ID.
+    pub home_account_id: Option<String>,
+    /// Identifies the tenant the credential should authenticate in.
+    ///
+    /// Defaults to the tenant the selected account signed in to.
+    pub tenant_id: Option<String>,
+    /// The client ID used to look up and redeem tokens.
+    ///
+    /// Defaults to the Azure CLI client ID.  Family refresh tokens issued to
+    /// other Microsoft first-party clients are also accepted.
+    pub client_id: Option<String>,
+    /// Path of the MSAL token cache.
+    ///
+    /// Defaults to `~/.IdentityService/msal.cache`.
+    pub cache_path: Option<PathBuf>,
+    /// Options for the HTTP pipeline used to redeem refresh tokens.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+}
+
+#[derive(Debug)]
+/// Enables authentication to Azure Active Directory using an account from the
+/// shared MSAL token cache.
+pub struct SharedTokenCacheCredential {
+    username: Option<String>,
+    home_account_id: Option<String>,
+    tenant_id: Option<String>,
+    client_id: String,
+    cache_path: Option<PathBuf>,
+    client_options: ClientOptions,
+    authority_host: AuthorityHost,
+    cache: TokenCache,
+    refresh_tokens: Mutex<HashMap<String, Secret>>,
+}
+
+impl SharedTokenCacheCredential {
+    /// Create a new `SharedTokenCacheCredential`.
+    pub fn new(
+        options: Option<SharedTokenCacheCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>> {
+        let SharedTokenCacheCredentialOptions {
+            username,
+            home_account_id,
+            tenant_id,
+            client_id,
+            cache_path,
+            client_options,
+            authority_host,
+        } = options.unwrap_or_default();
+
+        Ok(Arc::new(Self {
+            username,
+            home_account_id,
+            tenant_id,
+            client_id: client_id.unwrap_or_else(|| AZURE_CLI_CLIENT_ID.to_owned()),
+            cache_path: cache_path.or_else(default_cache_path),
+            client_options,
+            authority_host: AuthorityHost::or_from_env(authority_host)?,
+            cache: TokenCache::new(),
+            refresh_tokens: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    fn load_cache(&self) -> azure_core::Result<MsalCache> {
+        let path = self
+            .cache_path
+            .as_deref()
+            .filter(|path| path.exists())
+            .ok_or_else(|| {
+                Error::with_message(
+                    ErrorKind::Credential,
+                    "the shared token cache was not found",
+                )
+            })?;
+        MsalCache::from_file(path)
+            .map_err(|error| Error::with_error(ErrorKind::Credential, error, "shared token cache"))
+    }
+
+    async fn get_access_token(
+        &self,
+        scopes: &[&str],
+        _options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let cache = self.load_cache()?;
+        let account = select_account(
+            &cache,
+            &self.authority_host,
+            self.username.as_deref(),
+            self.home_account_id.as_deref(),
+        )?;
+        let tenant_id = self.tenant_id.as_deref().unwrap_or_else(|| account.realm());
+
+        if let Some(token) = cache.access_token(
+            &self.authority_host,
+            account.home_account_id(),
+            &self.client_id,
+            tenant_id,
+            scopes,
+        ) {
+            return Ok(token);
+        }
+
+        let mut refresh_tokens = self.refresh_tokens.lock().await;
+        let refresh_token = match refresh_tokens.get(account.home_account_id()) {
+            Some(refresh_token) => refresh_token.clone(),
+            None => cache
+                .refresh_token(
+                    &self.authority_host,
+                    account.home_account_id(),
+                    &self.client_id,
+                )
+                .ok_or_else(|| {
+                    Error::with_message(
+                        ErrorKind::Credential,
+                        format!(
+                            "no refresh token for {} was found in the shared token cache",
+                            account.username()
+                        ),
+                    )
+                })?
+                .secret()
+                .clone(),
+        };
+
+        let response = exchange_with_options(
+            tenant_id,
+            &self.client_id,
+            None,
+            &refresh_token,
+            ExchangeOptions {
+                scopes: scopes.iter().map(ToString::to_string).collect(),
+                client_options: self.client_options.clone(),
+                authority_host: Some(self.authority_host.clone()),
+            },
+        )
+        .await?;
+        refresh_tokens.insert(
+            account.home_account_id().to_owned(),
+            response.refresh_token().clone(),
+        );
+
+        Ok(AccessToken {
+            token: response.access_token().clone(),
+            expires_on: convert_expires_in(response.expires_in()),
+        })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for SharedTokenCacheCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.cache
+            .get_token(scopes, options, |s, o| self.get_access_token(s, o))
+            .await
+    }
+}
+
+fn default_cache_path() -> Option<PathBuf> {
+    #[cfg(windows)]
+    let base = std::env::var_os("LOCALAPPDATA").map(PathBuf::from);
+    #[cfg(not(windows))]
+    let base = home_dir();
+    base.map(|base| base.join(CACHE_DIR).join(CACHE_FILE))
+}
+
+fn select_account<'a>(
+    cache: &'a MsalCache,
+    authority_host: &AuthorityHost,
+    username: Option<&str>,
+    home_account_id: Option<&str>,
+) -> azure_core::Result<&'a MsalAccount> {
+    let accounts = cache.accounts(authority_host);
+    if accounts.is_empty() {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            format!(
+                "no accounts for {} were found in the shared token cache",
+                authority_host.as_str()
+            ),
+        ));
+    }
+
+    let matching = accounts
+        .into_iter()
+        .filter(|account| {
+            username.is_none_or(|username| account.username().eq_ignore_ascii_case(username))
+                && home_account_id.is_none_or(|home_account_id| {
+                    account
+                        .home_account_id()
+                        .eq_ignore_ascii_case(home_account_id)
+                })
+        })
+        .collect::<Vec<_>>();
+
+    match matching.as_slice() {
+        [account] => Ok(account),
+        [] => Err(Error::with_message(
+            ErrorKind::Credential,
+            "no account matching the specified username or home account ID was found in the shared token cache",
+        )),
+        accounts => Err(Error::with_message(
+            ErrorKind::Credential,
+            format!(
+                "multiple accounts were found in the shared token cache. Specify a username or home account ID to select one: {}",
+                accounts
+                    .iter()
+                    .map(|account| account.username())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockPolicy, TempDir};
+    use time::OffsetDateTime;
+
+    const SCOPE: &str = "https://management.azure.com/.default";
+
+    fn account(home_account_id: &str, username: &str) -> String {
+        format!(
+            r#""{home_account_id}-login.microsoftonline.com-fake-tenant": {{
+                "home_account_id": "{home_account_id}",
+                "environment": "login.microsoftonline.com",
+                "realm": "fake-tenant",
+                "local_account_id": "fake-object",
+                "username": "{username}",
+                "authority_type": "MSSTS"
+            }}"#
+        )
+    }
+
+    fn access_token(home_account_id: &str, expires_on: i64) -> String {
+        format!(
+            r#""{home_account_id}-login.microsoftonline.com-accesstoken-{AZURE_CLI_CLIENT_ID}-fake-tenant-{SCOPE}": {{
+                "home_account_id": "{home_account_id}",
+                "environment": "login.microsoftonline.com",
+                "credential_type": "AccessToken",
+                "client_id": "{AZURE_CLI_CLIENT_ID}",
+                "secret": "access-token-{home_account_id}",
+                "realm": "fake-tenant",
+                "target": "https://management.azure.com/user_impersonation {SCOPE}",
+                "cached_at": "1700000000",
+                "expires_on": "{expires_on}",
+                "extended_expires_on": "{expires_on}"
+            }}"#
+        )
+    }
+
+    fn refresh_token(home_account_id: &str) -> String {
+        format!(
+            r#""{home_account_id}-login.microsoftonline.com-refreshtoken-1--": {{
+                "home_account_id": "{home_account_id}",
+                "environment": "login.microsoftonline.com",
+                "credential_type": "RefreshToken",
+                "client_id": "872cd9fa-d31f-45e0-9eab-6e460a02d1f1",
+                "secret": "refresh-token-{home_account_id}",
+                "family_id": "1"
+            }}"#
+        )
+    }
+
+    fn cache_file(
+        accounts: &[String],
+        access_tokens: &[String],
+        refresh_tokens: &[String],
+    ) -> String {
+        format!(
+            r#"{{
+                "Account": {{ {} }},
+                "AccessToken": {{ {} }},
+                "RefreshToken": {{ {} }},
+                "IdToken": {{}},
+                "AppMetadata": {{}}
+            }}"#,
+            accounts.join(","),
+            access_tokens.join(","),
+            refresh_tokens.join(",")
+        )
+    }
+
+    fn credential(
+        dir: &TempDir,
+        contents: &str,
+        options: SharedTokenCacheCredentialOptions,
+    ) -> azure_core::Result<Arc<SharedTokenCacheCredential>> {
+        let cache_path = dir.write(CACHE_FILE, contents)?;
+        SharedTokenCacheCredential::new(Some(SharedTokenCacheCredentialOptions {
+            cache_path: Some(cache_path),
+            authority_host: options
+                .authority_host
+                .clone()
+                .or(Some(AuthorityHost::Public)),
+            ..options
+        }))
+    }
+
+    fn valid_until() -> i64 {
+        (OffsetDateTime::now_utc() + time::Duration::hours(1)).unix_timestamp()
+    }
+
+    #[tokio::test]
+    async fn returns_unexpired_access_token() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let credential = credential(
+            &dir,
+            &cache_file(
+                &[account("uid.utid", "user@contoso.com")],
+                &[access_token("uid.utid", valid_until())],
+                &[refresh_token("uid.utid")],
+            ),
+            SharedTokenCacheCredentialOptions::default(),
+        )?;
+
+        let token = credential.get_token(&[SCOPE], None).await?;
+
+        assert_eq!(token.token.secret(), "access-token-uid.utid");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn selects_account_by_username() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let credential = credential(
+            &dir,
+            &cache_file(
+                &[
+                    account("first.utid", "first@contoso.com"),
+                    account("second.utid", "second@contoso.com"),
+                ],
+                &[
+                    access_token("first.utid", valid_until()),
+                    access_token("second.utid", valid_until()),
+                ],
+                &[],
+            ),
+            SharedTokenCacheCredentialOptions {
+                username: Some(String::from("SECOND@contoso.com")),
+                ..SharedTokenCacheCredentialOptions::default()
+            },
+        )?;
+
+        let token = credential.get_token(&[SCOPE], None).await?;
+
+        assert_eq!(token.token.secret(), "access-token-second.utid");
+        Ok(())
+    }
+
+    #[test]
+    fn selects_account_by_home_account_id() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.write(
+            CACHE_FILE,
+            &cache_file(
+                &[
+                    account("first.utid", "user@contoso.com"),
+                    account("second.utid", "user@contoso.com"),
+                ],
+                &[],
+                &[],
+            ),
+        )?;
+        let cache = MsalCache::from_file(&path)?;
+
+        let account = select_account(&cache, &AuthorityHost::Public, None, Some("second.utid"))?;
+
+        assert_eq!(account.home_account_id(), "second.utid");
+        Ok(())
+    }
+
+    #[test]
+    fn multiple_accounts_require_selection() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.write(
+            CACHE_FILE,
+            &cache_file(
+                &[
+                    account("first.utid", "first@contoso.com"),
+                    account("second.utid", "second@contoso.com"),
+                ],
+                &[],
+                &[],
+            ),
+        )?;
+        let cache = MsalCache::from_file(&path)?;
+
+        let result = select_account(&cache, &AuthorityHost::Public, None, None);
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("first@contoso.com")
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn expired_access_token_falls_back_to_family_refresh_token() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.write(
+            CACHE_FILE,
+            &cache_file(
+                &[account("uid.utid", "user@contoso.com")],
+                &[access_token("uid.utid", 1_700_000_000)],
+                &[refresh_token("uid.utid")],
+            ),
+        )?;
+        let cache = MsalCache::from_file(&path)?;
+
+        assert!(
+            cache
+                .access_token(
+                    &AuthorityHost::Public,
+                    "uid.utid",
+                    AZURE_CLI_CLIENT_ID,
+                    "fake-tenant",
+                    &[SCOPE]
+                )
+                .is_none()
+        );
+        let refresh_token =
+            cache.refresh_token(&AuthorityHost::Public, "uid.utid", AZURE_CLI_CLIENT_ID);
+        assert_eq!(
+            refresh_token.map(|token| token.secret().secret()),
+            Some("refresh-token-uid.utid")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn redeems_and_rotates_family_refresh_token() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let policy = MockPolicy::new(vec![(
+            "/fake-tenant/oauth2/v2.0/token",
+            r#"{"token_type":"Bearer","scope":"https://management.azure.com/.default","expires_in":3600,"ext_expires_in":3600,"access_token":"redeemed","refresh_token":"rotated"}"#,
+        )]);
+        let credential = credential(
+            &dir,
+            &cache_file(
+                &[account("uid.utid", "user@contoso.com")],
+                &[access_token("uid.utid", 1_700_000_000)],
+                &[refresh_token("uid.utid")],
+            ),
+            SharedTokenCacheCredentialOptions {
+                client_options: policy.client_options(),
+                ..SharedTokenCacheCredentialOptions::default()
+            },
+        )?;
+
+        let token = credential.get_token(&[SCOPE], None).await?;
+        credential
+            .get_token(&["https://storage.azure.com/.default"], None)
+            .await?;
+
+        assert_eq!(token.token.secret(), "redeemed");
+        assert_eq!(
+            policy.requests(),
+            vec![
+                "https://login.microsoftonline.com/fake-tenant/oauth2/v2.0/token",
+                "https://login.microsoftonline.com/fake-tenant/oauth2/v2.0/token",
+            ]
+        );
+        let bodies = policy.bodies();
+        assert!(bodies.first().is_some_and(|body| {
+            body.contains("grant_type=refresh_token")
+                && body.contains(&format!("client_id={AZURE_CLI_CLIENT_ID}"))
+                && body.contains("refresh_token=refresh-token-uid.utid")
+        }));
+        // The second exchange redeems the refresh token the first one returned.
+        assert!(
+            bodies
+                .get(1)
+                .is_some_and(|body| body.contains("refresh_token=rotated"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ignores_entries_of_other_clouds() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let contents = cache_file(
+            &[account("uid.utid", "user@contoso.com")],
+            &[access_token("uid.utid", valid_until())],
+            &[refresh_token("uid.utid")],
+        )
+        .replace("login.microsoftonline.com", "login.chinacloudapi.cn");
+        let public = credential(
+            &dir,
+            &contents,
+            SharedTokenCacheCredentialOptions::default(),
+        )?;
+        let china = credential(
+            &dir,
+            &contents,
+            SharedTokenCacheCredentialOptions {
+                authority_host: Some(AuthorityHost::China),
+                ..SharedTokenCacheCredentialOptions::default()
+            },
+        )?;
+
+        let result = public.get_token(&[SCOPE], None).await;
+        let token = china.get_token(&[SCOPE], None).await?;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("no accounts for https://login.microsoftonline.com")
+        ));
+        assert_eq!(token.token.secret(), "access-token-uid.utid");
+        Ok(())
+    }
+
+    #[test]
+    fn public_cloud_aliases_match() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.write(
+            CACHE_FILE,
+            &cache_file(&[account("uid.utid", "user@contoso.com")], &[], &[])
+                .replace("login.microsoftonline.com", "login.windows.net"),
+        )?;
+        let cache = MsalCache::from_file(&path)?;
+
+        assert_eq!(cache.accounts(&AuthorityHost::Public).len(), 1);
+        assert!(cache.accounts(&AuthorityHost::USGovernment).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn access_token_requires_all_scopes() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.write(
+            CACHE_FILE,
+            &cache_file(
+                &[account("uid.utid", "user@contoso.com")],
+                &[access_token("uid.utid", valid_until())],
+                &[],
+            ),
+        )?;
+        let cache = MsalCache::from_file(&path)?;
+
+        assert!(
+            cache
+                .access_token(
+                    &AuthorityHost::Public,
+                    "uid.utid",
+                    AZURE_CLI_CLIENT_ID,
+                    "fake-tenant",
+                    &[SCOPE, "https://graph.microsoft.com/.default"],
+                )
+                .is_none()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn missing_cache_is_unavailable() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let credential =
+            SharedTokenCacheCredential::new(Some(SharedTokenCacheCredentialOptions {
+                cache_path: Some(dir.path.join(CACHE_FILE)),
+                ..SharedTokenCacheCredentialOptions::default()
+            }))?;
+
+        let result = credential.get_token(&[SCOPE], None).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,551 @@
+//! Authenticate a workload with a SPIFFE JWT-SVID.
+//!
+//! Service meshes and SPIRE agents serve the SPIFFE Workload API over gRPC on
+//! a Unix domain socket, named by `SPIFFE_ENDPOINT_SOCKET`.  [`SpiffeCredential`]
+//! fetches a JWT-SVID for the `api://AzureADTokenExchange` audience from it and
+//! redeems the SVID as a client assertion for an app registration with a
+//! federated identity credential that trusts the SPIFFE trust domain's OIDC
+//! issuer.  An SVID is reused until shortly before it expires, then a new one
+//! is fetched.
+//!
+//! Requires the `spiffe` feature, a Unix target and a Tokio runtime.
+
+use crate::{
+    authority_host::AuthorityHost,
+    client_assertion_credential::{
+        ClientAssertionCredential, ClientAssertionCredentialOptions, TOKEN_EXCHANGE_AUDIENCE,
+    },
+};
+use azure_core::{
+    credentials::{AccessToken, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::ClientOptions,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::future::{self, Either};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::net::UnixStream;
+
+const SPIFFE_ENDPOINT_SOCKET: &str = "SPIFFE_ENDPOINT_SOCKET";
+
+/// The gRPC method that issues JWT-SVIDs.
+const FETCH_JWT_SVID: &str = "http://localhost/SpiffeWorkloadAPI/FetchJWTSVID";
+/// The metadata the Workload API requires to tell workloads from other
+/// callers.
+const WORKLOAD_HEADER: &str = "workload.spiffe.io";
+
+/// Options for constructing a [`SpiffeCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct SpiffeCredentialOptions {
+    /// Options for the HTTP pipeline used to request tokens.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+    /// The Workload API socket, as a `unix:` URI or a path.  Defaults to
+    /// `SPIFFE_ENDPOINT_SOCKET`.
+    pub endpoint_socket: Option<String>,
+    /// The audience of the JWT-SVID.  Defaults to
+    /// `api://AzureADTokenExchange`.
+    pub audience: Option<String>,
+    /// The SPIFFE ID to request an SVID for, when the workload has several.
+    /// Defaults to the first SVID returned.
+    pub spiffe_id: Option<String>,
+}
+
+#[derive(Debug)]
+/// Enables authentication to Azure Active Directory using a JWT-SVID from the
+/// SPIFFE Workload API.
+pub struct SpiffeCredential {
+    credential: Arc<ClientAssertionCredential>,
+}
+
+impl SpiffeCredential {
+    /// Create a new `SpiffeCredential`.
+    pub fn new<T, C>(
+        tenant_id: T,
+        client_id: C,
+        options: Option<SpiffeCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+    {
+        let SpiffeCredentialOptions {
+            client_options,
+            authority_host,
+            endpoint_socket,
+            audience,
+            spiffe_id,
+        } = options.unwrap_or_default();
+        let endpoint_socket = endpoint_socket
+            .or_else(|| env::var(SPIFFE_ENDPOINT_SOCKET).ok())
+            .filter(|socket| !socket.is_empty())
+            .ok_or_else(|| {
+                Error::with_message(
+                    ErrorKind::Credential,
+                    format!("SpiffeCredential requires the Workload API socket. Set {SPIFFE_ENDPOINT_SOCKET}"),
+                )
+            })?;
+        let socket = Arc::new(socket_path(&endpoint_socket)?);
+        let request = Bytes::from(encode_request(
+            audience.as_deref().unwrap_or(TOKEN_EXCHANGE_AUDIENCE),
+            spiffe_id.as_deref(),
+        ));
+        let spiffe_id = Arc::new(spiffe_id);
+
+        let credential = ClientAssertionCredential::new(
+            tenant_id,
+            client_id,
+            move || {
+                let socket = socket.clone();
+                let request = request.clone();
+                let spiffe_id = spiffe_id.clone();
+                async move { fetch_jwt_svid(&socket, request, spiffe_id.as_deref()).await }
+            },
+            Some(ClientAssertionCredentialOptions {
+                client_options,
+                authority_host,
+            }),
+        )?;
+        Ok(Arc::new(Self { credential }))
+    }
+}
+
+/// The path of the socket named by a `unix:` URI or a plain path.
+fn socket_path(endpoint: &str) -> azure_core::Result<PathBuf> {
+    if let Some(path) = endpoint
+        .strip_prefix("unix://")
+        .or_else(|| endpoint.strip_prefix("unix:"))
+    {
+        return Ok(PathBuf::from(path));
+    }
+    if endpoint.contains("://") {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            format!(
+                "unsupported Workload API address {endpoint}. Only unix: sockets are supported"
+            ),
+        ));
+    }
+    Ok(PathBuf::from(endpoint))
+}
+
+fn workload_api_error(error: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::with_error(
+        ErrorKind::Credential,
+        error,
+        "SpiffeCredential failed to call the Workload API",
+    )
+}
+
+async fn fetch_jwt_svid(
+    socket: &Path,
+    request: Bytes,
+    spiffe_id: Option<&str>,
+) -> azure_core::Result<String> {
+    let stream = UnixStream::connect(socket).await.map_err(|error| {
+        Error::with_error(
+            ErrorKind::Credential,
+            error,
+            format!(
+                "SpiffeCredential failed to connect to the Workload API at {}",
+                socket.display()
+            ),
+        )
+    })?;
+    let (client, connection) = h2::client::handshake(stream)
+        .await
+        .map_err(workload_api_error)?;
+
+    // The connection makes progress only while it is polled, so drive it
+    // alongside the call until the response is complete.
+    let call = Box::pin(call(client, request));
+    let response = match future::select(call, connection).await {
+        Either::Left((response, _)) => response,
+        Either::Right((Ok(()), call)) => call.await,
+        Either::Right((Err(error), _)) => Err(workload_api_error(error)),
+    }?;
+
+    let svids = decode_response(&response).ok_or_else(|| {
+        Error::with_message(
+            ErrorKind::Credential,
+            "SpiffeCredential received an invalid Workload API response",
+        )
+    })?;
+    svids
+        .into_iter()
+        .find(|(id, _)| spiffe_id.is_none_or(|spiffe_id| id == spiffe_id))
+        .map(|(_, svid)| svid)
+        .ok_or_else(|| {
+            Error::with_message(
+                ErrorKind::Credential,
+                format!(
+                    "the Workload API returned no JWT-SVID{}",
+                    spiffe_id.map_or_else(String::new, |id| format!(" for {id}"))
+                ),
+            )
+        })
+}
+
+/// Make a unary gRPC call, returning the response message.
+async fn call(client: h2::client::SendRequest<Bytes>, message: Bytes) -> azure_core::Result<Bytes> {
+    let request = http::Request::post(FETCH_JWT_SVID)
+        .header(http::header::CONTENT_TYPE, "application/grpc")
+        .header(http::header::TE, "trailers")
+        .header(WORKLOAD_HEADER, "true")
+        .body(())
+        .map_err(workload_api_error)?;
+    let mut client = client.ready().await.map_err(workload_api_error)?;
+    let (response, mut send) = client
+        .send_request(request, false)
+        .map_err(workload_api_error)?;
+    let mut frame = BytesMut::with_capacity(message.len() + 5);
+    frame.put_u8(0);
+    frame.put_u32(u32::try_from(message.len()).map_err(workload_api_error)?);
+    frame.put(message);
+    send.send_data(frame.freeze(), true)
+        .map_err(workload_api_error)?;
+
+    let response = response.await.map_err(workload_api_error)?;
+    let (parts, mut body) = response.into_parts();
+    if parts.status != http::StatusCode::OK {
+        return Err(Error::with_message(
+            ErrorKind::Credential,
+            format!(
+                "SpiffeCredential failed to call the Workload API with status {}",
+                parts.status
+            ),
+        ));
+    }
+    let mut data = BytesMut::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(workload_api_error)?;
+        body.flow_control()
+            .release_capacity(chunk.len())
+            .map_err(workload_api_error)?;
+        data.extend_from_slice(&chunk);
+    }
+    // A call that fails before sending a message reports its status in the
+    // headers rather than in the trailers.
+    let trailers = body.trailers().await.map_err(workload_api_error)?;
+    check_grpc_status(trailers.as_ref().unwrap_or(&parts.headers))?;
+
+    decode_message(&data)
+        .map(Bytes::copy_from_slice)
+        .ok_or_else(|| {
+            Error::with_message(
+                ErrorKind::Credential,
+                "SpiffeCredential received a malformed Workload API response. Expected a single uncompressed message",
+            )
+        })
+}
+
+/// The message of a unary gRPC response body, which is a compression flag and
+/// a big-endian length followed by exactly that many bytes.  Compressed and
+/// multiple messages are rejected; no compression is requested.
+fn decode_message(data: &[u8]) -> Option<&[u8]> {
+    let (&[compressed, a, b, c, d], message) = data.split_first_chunk::<5>()?;
+    let length = usize::try_from(u32::from_be_bytes([a, b, c, d])).ok()?;
+    (compressed == 0 && message.len() == length).then_some(message)
+}
+
+fn check_grpc_status(metadata: &http::HeaderMap) -> azure_core::Result<()> {
+    let status = metadata
+        .get("grpc-status")
+        .and_then(|status| status.to_str().ok());
+    if status == Some("0") {
+        return Ok(());
+    }
+    let message = metadata
+        .get("grpc-message")
+        .and_then(|message| message.to_str().ok())
+        .unwrap_or_default();
+    Err(Error::with_message(
+        ErrorKind::Credential,
+        format!(
+            "SpiffeCredential failed to get a JWT-SVID. gRPC status {}: {message}",
+            status.unwrap_or("missing")
+        ),
+    ))
+}
+
+/// Encode a `JWTSVIDRequest` message.
+fn encode_request(audience: &str, spiffe_id: Option<&str>) -> Vec<u8> {
+    let mut message = Vec::new();
+    encode_string(&mut message, 1, audience);
+    if let Some(spiffe_id) = spiffe_id {
+        encode_string(&mut message, 2, spiffe_id);
+    }
+    message
+}
+
+fn encode_string(message: &mut Vec<u8>, field: u64, value: &str) {
+    encode_varint(message, (field << 3) | 2);
+    encode_varint(message, value.len() as u64);
+    message.extend_from_slice(value.as_bytes());
+}
+
+fn encode_varint(message: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        message.push(u8::try_from(value & 0x7f).unwrap_or_default() | 0x80);
+        value >>= 7;
+    }
+    message.push(u8::try_from(value).unwrap_or_default());
+}
+
+/// Decode the SPIFFE IDs and SVIDs of a `JWTSVIDResponse` message.
+fn decode_response(message: &[u8]) -> Option<Vec<(String, String)>> {
+    decode_fields(message)?
+        .into_iter()
+        .filter(|(field, _)| *field == 1)
+        .map(|(_, svid)| {
+            let mut spiffe_id = String::new();
+            let mut token = None;
+            for (field, value) in decode_fields(svid)? {
+                match field {
+                    1 => spiffe_id = String::from_utf8(value.to_vec()).ok()?,
+                    2 => token = Some(String::from_utf8(value.to_vec()).ok()?),
+                    _ => {}
+                }
+            }
+            Some((spiffe_id, token?))
+        })
+        .collect()
+}
+
+/// The length-delimited fields of a message, skipping fields of other wire
+/// types.
+fn decode_fields(mut message: &[u8]) -> Option<Vec<(u64, &[u8])>> {
+    let mut fields = Vec::new();
+    while !message.is_empty() {
+        let key = decode_varint(&mut message)?;
+        let skip = match key & 7 {
+            0 => {
+                decode_varint(&mut message)?;
+                0
+            }
+            1 => 8,
+            2 => {
+                let len = usize::try_from(decode_varint(&mut message)?).ok()?;
+                let (value, rest) = message.split_at_checked(len)?;
+                fields.push((key >> 3, value));
+                message = rest;
+                0
+            }
+            5 => 4,
+            _ => return None,
+        };
+        message = message.get(skip..)?;
+    }
+    Some(fields)
+}
+
+fn decode_varint(message: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = message.split_first()?;
+        *message = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for SpiffeCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.credential.get_token(scopes, options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{TempDir, TestServer};
+    use azure_core::{base64, time::Duration, time::OffsetDateTime};
+    use std::sync::Mutex;
+    use tokio::net::UnixListener;
+
+    fn jwt_svid() -> String {
+        format!(
+            "{}.{}.fake-signature",
+            base64::encode_url_safe(r#"{"alg":"ES256","typ":"JWT"}"#),
+            base64::encode_url_safe(format!(
+                r#"{{"sub":"spiffe://example.org/workload","exp":{}}}"#,
+                (OffsetDateTime::now_utc() + Duration::hours(1)).unix_timestamp()
+            )),
+        )
+    }
+
+    fn encode_response(svids: &[(&str, &str)]) -> Vec<u8> {
+        let mut message = Vec::new();
+        for (spiffe_id, svid) in svids {
+            let mut entry = Vec::new();
+            encode_string(&mut entry, 1, spiffe_id);
+            encode_string(&mut entry, 2, svid);
+            encode_varint(&mut message, (1 << 3) | 2);
+            encode_varint(&mut message, entry.len() as u64);
+            message.extend_from_slice(&entry);
+        }
+        message
+    }
+
+    /// A fake Workload API that answers every `FetchJWTSVID` call with
+    /// `response` and records the request messages.
+    fn workload_api(
+        socket: &Path,
+        response: Vec<u8>,
+    ) -> azure_core::Result<Arc<Mutex<Vec<Vec<u8>>>>> {
+        let listener = UnixListener::bind(socket)?;
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let Ok(mut connection) = h2::server::handshake(stream).await else {
+                    continue;
+                };
+                while let Some(Ok((request, mut respond))) = connection.accept().await {
+                    let (parts, mut body) = request.into_parts();
+                    let mut message = Vec::new();
+                    while let Some(Ok(chunk)) = body.data().await {
+                        message.extend_from_slice(&chunk);
+                    }
+                    if parts.uri.path() == "/SpiffeWorkloadAPI/FetchJWTSVID"
+                        && parts.headers.get(WORKLOAD_HEADER).is_some()
+                        && let Ok(mut received) = received.lock()
+                    {
+                        received.push(message.get(5..).unwrap_or_default().to_vec());
+                    }
+
+                    let mut frame = vec![0];
+                    frame.extend_from_slice(
+                        &u32::try_from(response.len())
+                            .unwrap_or_default()
+                            .to_be_bytes(),
+                    );
+                    frame.extend_from_slice(&response);
+                    let mut trailers = http::HeaderMap::new();
+                    trailers.insert("grpc-status", http::HeaderValue::from_static("0"));
+                    let Ok(mut send) = respond.send_response(http::Response::new(()), false) else {
+                        continue;
+                    };
+                    let _ = send.send_data(Bytes::from(frame), false);
+                    let _ = send.send_trailers(trailers);
+                }
+            }
+        });
+        Ok(requests)
+    }
+
+    #[tokio::test]
+    async fn exchanges_and_reuses_jwt_svid() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let socket = dir.path.join("agent.sock");
+        let svid = jwt_svid();
+        let requests = workload_api(
+            &socket,
+            encode_response(&[
+                ("spiffe://example.org/other", "other-svid"),
+                ("spiffe://example.org/workload", &svid),
+            ]),
+        )?;
+        let entra = TestServer::entra().await?;
+        let credential = SpiffeCredential::new(
+            "fake-tenant",
+            "fake-client",
+            Some(SpiffeCredentialOptions {
+                authority_host: Some(entra.authority_host()?),
+                endpoint_socket: Some(format!("unix://{}", socket.display())),
+                spiffe_id: Some("spiffe://example.org/workload".to_owned()),
+                ..SpiffeCredentialOptions::default()
+            }),
+        )?;
+
+        let token = credential.get_token(&["first-scope"], None).await?;
+        credential.get_token(&["second-scope"], None).await?;
+
+        assert_eq!(token.token.secret(), "fake-token");
+        let requests = requests
+            .lock()
+            .map(|requests| requests.clone())
+            .unwrap_or_default();
+        assert_eq!(
+            requests,
+            vec![encode_request(
+                "api://AzureADTokenExchange",
+                Some("spiffe://example.org/workload")
+            )]
+        );
+        let token_requests = entra.requests();
+        assert_eq!(token_requests.len(), 2);
+        assert!(
+            token_requests
+                .iter()
+                .all(|request| request.contains(&format!("client_assertion={svid}&")))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reports_unreachable_workload_api() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        let credential = SpiffeCredential::new(
+            "fake-tenant",
+            "fake-client",
+            Some(SpiffeCredentialOptions {
+                endpoint_socket: Some(dir.path.join("missing.sock").display().to_string()),
+                ..SpiffeCredentialOptions::default()
+            }),
+        )?;
+
+        let result = credential.get_token(&["fake-scope"], None).await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn parses_socket_addresses() {
+        assert_eq!(
+            socket_path("unix:///run/spire/agent.sock").ok(),
+            Some(PathBuf::from("/run/spire/agent.sock"))
+        );
+        assert_eq!(
+            socket_path("unix:/run/spire/agent.sock").ok(),
+            Some(PathBuf::from("/run/spire/agent.sock"))
+        );
+        assert!(socket_path("tcp://127.0.0.1:8081").is_err());
+    }
+
+    #[test]
+    fn decodes_only_a_single_uncompressed_message() {
+        let frame = |compressed: u8, length: u32, message: &[u8]| {
+            let mut frame = vec![compressed];
+            frame.extend_from_slice(&length.to_be_bytes());
+            frame.extend_from_slice(message);
+            frame
+        };
+
+        assert_eq!(decode_message(&frame(0, 3, b"abc")), Some(&b"abc"[..]));
+        assert_eq!(decode_message(&frame(0, 0, b"")), Some(&b""[..]));
+        // Truncated, followed by another message, compressed, or no prefix.
+        assert_eq!(decode_message(&frame(0, 4, b"abc")), None);
+        assert_eq!(decode_message(&frame(0, 1, b"abc")), None);
+        assert_eq!(decode_message(&frame(1, 3, b"abc")), None);
+        assert_eq!(decode_message(&[0, 0, 0]), None);
+    }
+}
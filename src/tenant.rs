@@ -0,0 +1,360 @@
+//! Select the tenant a token is requested for, and restrict which tenants a
+//! credential may authenticate in.
+//!
+//! `TokenRequestOptions` has no tenant field, so a [`RequestedTenant`] is
+//! carried on the request context:
+//!
+//! ```
+//! use azure_core::credentials::TokenRequestOptions;
+//! use azure_identity_helpers::tenant::RequestedTenant;
+//!
+//! let mut options = TokenRequestOptions::default();
+//! options
+//!     .method_options
+//!     .context
+//!     .insert(RequestedTenant::new("00000000-0000-0000-0000-000000000000"));
+//! ```
+//!
+//! Credentials that enforce a tenant policy reject a requested tenant other
+//! than their own unless it is additionally allowed, either through their
+//! builder or the `AZURE_ADDITIONALLY_ALLOWED_TENANTS` environment variable.
+
+use azure_core::{
+    credentials::{AccessToken, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+pub(crate) const AZURE_ADDITIONALLY_ALLOWED_TENANTS: &str = "AZURE_ADDITIONALLY_ALLOWED_TENANTS";
+
+/// Allows a credential to request tokens for any tenant.
+pub const ALL_TENANTS: &str = "*";
+
+/// The tenant a token is requested for, set as a value on the request context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestedTenant(String);
+
+impl RequestedTenant {
+    /// Request a token for `tenant_id`.
+    pub fn new<T>(tenant_id: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Self(tenant_id.into())
+    }
+
+    /// The ID of the requested tenant.
+    #[must_use]
+    pub fn tenant_id(&self) -> &str {
+        &self.0
+    }
+
+    /// The tenant requested by `options`, if any.
+    #[must_use]
+    pub fn from_options<'a>(options: Option<&'a TokenRequestOptions<'_>>) -> Option<&'a str> {
+        options?
+            .method_options
+            .context
+            .value::<Self>()
+            .map(Self::tenant_id)
+    }
+}
+
+/// Split an `AZURE_ADDITIONALLY_ALLOWED_TENANTS` value on `;`.
+pub(crate) fn parse_allowed_tenants(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|tenant| !tenant.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+/// The tenants a credential may request tokens for.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct TenantPolicy {
+    tenant_id: Option<String>,
+    additionally_allowed_tenants: Vec<String>,
+}
+
+impl TenantPolicy {
+    pub(crate) fn new(
+        tenant_id: Option<String>,
+        additionally_allowed_tenants: Vec<String>,
+    ) -> Self {
+        Self {
+            tenant_id,
+            additionally_allowed_tenants,
+        }
+    }
+
+    /// Reject a request for a tenant other than the credential's own unless it
+    /// is additionally allowed.
+    pub(crate) fn check(
+        &self,
+        options: Option<&TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<()> {
+        let Some(requested) = RequestedTenant::from_options(options) else {
+            return Ok(());
+        };
+        let allowed = self
+            .tenant_id
+            .as_deref()
+            .is_some_and(|tenant_id| tenant_id.eq_ignore_ascii_case(requested))
+            || self
+                .additionally_allowed_tenants
+                .iter()
+                .any(|tenant| tenant == ALL_TENANTS || tenant.eq_ignore_ascii_case(requested));
+        if allowed {
+            return Ok(());
+        }
+
+        Err(Error::with_message(
+            ErrorKind::Credential,
+            format!(
+                "the requested tenant '{requested}' is not allowed. Add it to the additionally allowed tenants or {AZURE_ADDITIONALLY_ALLOWED_TENANTS}, or use '{ALL_TENANTS}' to allow any tenant"
+            ),
+        ))
+    }
+}
+
+/// Creates a credential that authenticates in the given tenant, or in its
+/// configured tenant for `None`.
+pub(crate) type TenantCredentialFactory =
+    Box<dyn Fn(Option<&str>) -> azure_core::Result<Arc<dyn TokenCredential>> + Send + Sync>;
+
+/// Authenticates in the tenant a token is requested for.
+///
+/// Requests without a [`RequestedTenant`], or for the configured tenant, use
+/// the credential for the configured tenant.  A credential is created for
+/// each other tenant on its first request.  Sources without a factory can
+/// only authenticate in the tenant of their identity and reject requests for
+/// any other tenant.
+pub(crate) struct TenantCredential {
+    name: &'static str,
+    tenant_id: Option<String>,
+    credential: Arc<dyn TokenCredential>,
+    factory: Option<TenantCredentialFactory>,
+    tenants: Mutex<HashMap<String, Arc<dyn TokenCredential>>>,
+}
+
+impl TenantCredential {
+    /// A source named `name` that authenticates in `tenant_id` unless another
+    /// tenant is requested.
+    pub(crate) fn new(
+        name: &'static str,
+        tenant_id: Option<String>,
+        factory: TenantCredentialFactory,
+    ) -> azure_core::Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            name,
+            tenant_id,
+            credential: factory(None)?,
+            factory: Some(factory),
+            tenants: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// A source named `name` that can only authenticate in `tenant_id`.
+    pub(crate) fn fixed(
+        name: &'static str,
+        tenant_id: Option<String>,
+        credential: Arc<dyn TokenCredential>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            tenant_id,
+            credential,
+            factory: None,
+            tenants: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The credential for `requested`.
+    fn credential(&self, requested: &str) -> azure_core::Result<Arc<dyn TokenCredential>> {
+        let Some(factory) = &self.factory else {
+            return Err(Error::with_message(
+                ErrorKind::Credential,
+                format!(
+                    "{} cannot request tokens for tenant '{requested}'; it only authenticates in the tenant of its identity",
+                    self.name
+                ),
+            ));
+        };
+        let mut tenants = self.tenants.lock().unwrap_or_else(PoisonError::into_inner);
+        let key = requested.to_ascii_lowercase();
+        if let Some(credential) = tenants.get(&key) {
+            return Ok(credential.clone());
+        }
+        let credential = factory(Some(requested))?;
+        tenants.insert(key, credential.clone());
+        Ok(credential)
+    }
+}
+
+impl fmt::Debug for TenantCredential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TenantCredential")
+            .field("name", &self.name)
+            .field("tenant_id", &self.tenant_id)
+            .field("credential", &self.credential)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for TenantCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let credential = match RequestedTenant::from_options(options.as_ref()) {
+            Some(requested)
+                if !self
+                    .tenant_id
+                    .as_deref()
+                    .is_some_and(|tenant_id| tenant_id.eq_ignore_ascii_case(requested)) =>
+            {
+                self.credential(requested)?
+            }
+            _ => self.credential.clone(),
+        };
+        credential.get_token(scopes, options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use azure_core::{
+        credentials::Secret,
+        time::{Duration, OffsetDateTime},
+    };
+
+    fn request(tenant_id: &str) -> TokenRequestOptions<'static> {
+        let mut options = TokenRequestOptions::default();
+        options
+            .method_options
+            .context
+            .insert(RequestedTenant::new(tenant_id));
+        options
+    }
+
+    #[test]
+    fn parses_allowed_tenants() {
+        assert_eq!(
+            parse_allowed_tenants(" first ;second;; "),
+            vec!["first", "second"]
+        );
+    }
+
+    #[test]
+    fn requests_without_tenant_are_allowed() -> azure_core::Result<()> {
+        let policy = TenantPolicy::new(Some(String::from("home")), Vec::new());
+
+        policy.check(None)?;
+        policy.check(Some(&TokenRequestOptions::default()))?;
+        Ok(())
+    }
+
+    #[test]
+    fn own_and_additional_tenants_are_allowed() -> azure_core::Result<()> {
+        let policy = TenantPolicy::new(Some(String::from("home")), vec![String::from("other")]);
+
+        policy.check(Some(&request("HOME")))?;
+        policy.check(Some(&request("other")))?;
+        assert!(matches!(
+            policy.check(Some(&request("unknown"))),
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn wildcard_allows_any_tenant() -> azure_core::Result<()> {
+        let policy = TenantPolicy::new(None, vec![String::from(ALL_TENANTS)]);
+
+        policy.check(Some(&request("unknown")))?;
+        Ok(())
+    }
+
+    #[test]
+    fn tenant_is_rejected_without_configuration() {
+        let policy = TenantPolicy::default();
+
+        assert!(policy.check(Some(&request("unknown"))).is_err());
+    }
+
+    #[derive(Debug)]
+    struct TenantToken(String);
+
+    #[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+    #[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+    impl TokenCredential for TenantToken {
+        async fn get_token(
+            &self,
+            _scopes: &[&str],
+            _options: Option<TokenRequestOptions<'_>>,
+        ) -> azure_core::Result<AccessToken> {
+            Ok(AccessToken {
+                token: Secret::new(self.0.clone()),
+                expires_on: OffsetDateTime::now_utc() + Duration::hours(1),
+            })
+        }
+    }
+
+    fn tenant_token(tenant_id: Option<&str>) -> Arc<dyn TokenCredential> {
+        Arc::new(TenantToken(tenant_id.unwrap_or("home").to_owned()))
+    }
+
+    #[tokio::test]
+    async fn requested_tenant_gets_its_own_credential() -> azure_core::Result<()> {
+        let credential = TenantCredential::new(
+            "FakeCredential",
+            Some(String::from("home")),
+            Box::new(|tenant_id| Ok(tenant_token(tenant_id))),
+        )?;
+
+        let home = credential.get_token(&["scope"], None).await?;
+        let explicit_home = credential
+            .get_token(&["scope"], Some(request("HOME")))
+            .await?;
+        let other = credential
+            .get_token(&["scope"], Some(request("other")))
+            .await?;
+
+        assert_eq!(home.token.secret(), "home");
+        assert_eq!(explicit_home.token.secret(), "home");
+        assert_eq!(other.token.secret(), "other");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fixed_tenant_rejects_other_tenants() -> azure_core::Result<()> {
+        let credential = TenantCredential::fixed(
+            "FakeCredential",
+            Some(String::from("home")),
+            tenant_token(None),
+        );
+
+        credential
+            .get_token(&["scope"], Some(request("home")))
+            .await?;
+        let result = credential
+            .get_token(&["scope"], Some(request("other")))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+                && error.to_string().contains("FakeCredential cannot request tokens for tenant 'other'")
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+//! Helpers shared by the unit tests in this crate.
+
+use crate::authority_host::AuthorityHost;
+use async_trait::async_trait;
+use azure_core::{
+    error::{Error, ErrorKind},
+    http::{
+        AsyncRawResponse, Body, ClientOptions, Context, Request, StatusCode, Url,
+        headers::Headers,
+        policies::{Policy, PolicyResult},
+    },
+};
+use std::{
+    env, fs,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// The body of a successful Microsoft Entra ID token response.
+pub const TOKEN_BODY: &str =
+    r#"{"token_type":"Bearer","expires_in":3600,"access_token":"fake-token"}"#;
+
+/// A raw HTTP/1.1 response with the given status line, such as `200 OK`,
+/// content type and body.
+pub fn http_response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// A raw successful Microsoft Entra ID token response.
+pub fn token_response() -> String {
+    http_response("200 OK", "application/json", TOKEN_BODY)
+}
+
+static TEMP_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A directory of fixture files that is removed when dropped.
+pub struct TempDir {
+    pub path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> azure_core::Result<Self> {
+        let suffix = TEMP_DIR_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let path = env::temp_dir().join(format!(
+            "azure-identity-helpers-{}-{suffix}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&path).map_err(io_error)?;
+        Ok(Self { path })
+    }
+
+    pub fn write(&self, name: &str, contents: &str) -> azure_core::Result<PathBuf> {
+        let path = self.path.join(name);
+        fs::write(&path, contents).map_err(io_error)?;
+        Ok(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A local stand-in HTTP server that answers every request with the same
+/// raw response, or never answers when no response is given.
+pub struct TestServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl TestServer {
+    pub async fn start(response: Option<&str>) -> azure_core::Result<Self> {
+        let response: Option<Arc<str>> = response.map(Arc::from);
+        let listener = TcpListener::bind("127.0.0.1:0").await.map_err(io_error)?;
+        let url = format!("http://{}/", listener.local_addr().map_err(io_error)?);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let request = read_request(&mut stream).await;
+                if let Ok(mut received) = received.lock() {
+                    received.push(request);
+                }
+                match &response {
+                    Some(response) => {
+                        let _ = stream.write_all(response.as_bytes()).await;
+                    }
+                    // Hold the connection open without ever answering.
+                    None => {
+                        tokio::spawn(async move {
+                            let _stream = stream;
+                            std::future::pending::<()>().await;
+                        });
+                    }
+                }
+            }
+        });
+
+        Ok(Self { url, requests })
+    }
+
+    /// A stand-in Microsoft Entra ID that issues `fake-token` for every
+    /// request.
+    pub async fn entra() -> azure_core::Result<Self> {
+        Self::start(Some(&token_response())).await
+    }
+
+    /// An authority host that sends token requests to this server.
+    pub fn authority_host(&self) -> azure_core::Result<AuthorityHost> {
+        Ok(AuthorityHost::Custom(Url::parse(&self.url)?))
+    }
+
+    /// The raw requests received so far, including headers and body.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .map(|requests| requests.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// A pipeline policy that answers requests itself, without sending them, with
+/// the status and body of the first canned response whose path suffix
+/// matches the URL.
+#[derive(Debug)]
+pub struct MockPolicy {
+    responses: Vec<(&'static str, StatusCode, &'static str)>,
+    /// Responses for requests whose body contains the given text, which take
+    /// precedence over `responses`.
+    body_responses: Vec<(&'static str, StatusCode, &'static str)>,
+    /// The URL and body of each request.
+    requests: Mutex<Vec<(String, String)>>,
+}
+
+impl MockPolicy {
+    /// Answer matching requests with `200 OK` and the given body.
+    pub fn new(responses: Vec<(&'static str, &'static str)>) -> Arc<Self> {
+        Self::with_status(
+            responses
+                .into_iter()
+                .map(|(suffix, body)| (suffix, StatusCode::Ok, body))
+                .collect(),
+        )
+    }
+
+    pub fn with_status(responses: Vec<(&'static str, StatusCode, &'static str)>) -> Arc<Self> {
+        Self::with_body_responses(vec![], responses)
+    }
+
+    /// Answer requests whose body contains the first element of one of
+    /// `body_responses` with that response, and others as `with_status`.
+    pub fn with_body_responses(
+        body_responses: Vec<(&'static str, StatusCode, &'static str)>,
+        responses: Vec<(&'static str, StatusCode, &'static str)>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            responses,
+            body_responses,
+            requests: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Client options that route every request to this policy.
+    pub fn client_options(self: &Arc<Self>) -> ClientOptions {
+        ClientOptions {
+            per_call_policies: vec![self.clone()],
+            ..ClientOptions::default()
+        }
+    }
+
+    /// The URLs requested so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .map(|requests| requests.iter().map(|(url, _)| url.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// The bodies of the requests so far.
+    pub fn bodies(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .map(|requests| requests.iter().map(|(_, body)| body.clone()).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Policy for MockPolicy {
+    async fn send(
+        &self,
+        _ctx: &Context,
+        request: &mut Request,
+        _next: &[Arc<dyn Policy>],
+    ) -> PolicyResult {
+        let body = match request.body() {
+            Body::Bytes(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+            Body::SeekableStream(_) => String::new(),
+        };
+        let body_response = self
+            .body_responses
+            .iter()
+            .find(|(fragment, _, _)| body.contains(fragment));
+        if let Ok(mut requests) = self.requests.lock() {
+            requests.push((request.url().to_string(), body));
+        }
+        let (status, body) = body_response
+            .or_else(|| {
+                self.responses
+                    .iter()
+                    .find(|(suffix, _, _)| request.url().path().ends_with(suffix))
+            })
+            .map_or((StatusCode::NotFound, "{}"), |(_, status, body)| {
+                (*status, *body)
+            });
+        Ok(AsyncRawResponse::from_bytes(
+            status,
+            Headers::new(),
+            body.as_bytes().to_vec(),
+        ))
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    while let Ok(read) = stream.read_buf(&mut request).await {
+        if read == 0 {
+            break;
+        }
+        let text = String::from_utf8_lossy(&request);
+        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+            let content_length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())
+                        .flatten()
+                })
+                .unwrap_or(0);
+            if body.len() >= content_length {
+                break;
+            }
+        }
+    }
+    String::from_utf8_lossy(&request).into_owned()
+}
+
+fn io_error(error: std::io::Error) -> Error {
+    Error::with_error(ErrorKind::Io, error, "setting up test fixture")
+}
@@ -0,0 +1,108 @@
+//! Requests to the Microsoft Entra token endpoint for credentials that post an
+//! OAuth 2.0 grant to it themselves.
+
+use crate::devicecode_credentials::convert_expires_in;
+use azure_core::{
+    credentials::{AccessToken, Secret},
+    error::{Error, ErrorKind},
+    http::{
+        ClientOptions, Context, Method, Pipeline, PipelineSendOptions, Request, StatusCode, Url,
+        headers::{self, content_type},
+        response::ResponseBody,
+    },
+    json::from_json,
+};
+use serde::{Deserialize, de::DeserializeOwned};
+
+/// The `client_assertion_type` of a JWT client assertion.
+pub const CLIENT_ASSERTION_TYPE: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Secret,
+    expires_in: u64,
+}
+
+/// The OAuth error in the body of a failed token request.
+#[derive(Debug, Deserialize)]
+pub struct TokenErrorResponse {
+    pub error: String,
+    #[serde(default)]
+    pub error_description: String,
+}
+
+/// A response from the token endpoint with a status other than success.
+#[derive(Debug)]
+pub struct ErrorResponse {
+    pub status: StatusCode,
+    body: ResponseBody,
+}
+
+impl ErrorResponse {
+    /// The body as `T`, or `None` if it is not one.
+    pub fn parse<T: DeserializeOwned>(&self) -> Option<T> {
+        from_json(&self.body).ok()
+    }
+
+    /// The error reported by `credential` for this response, including the
+    /// OAuth error and description when the body has them.
+    pub fn into_error(self, credential: &str) -> Error {
+        let message = self.parse::<TokenErrorResponse>().map_or_else(
+            || format!("status {}", self.status),
+            |error| format!("{}: {}", error.error, error.error_description),
+        );
+        Error::with_message(
+            ErrorKind::Credential,
+            format!("{credential} authentication failed. {message}"),
+        )
+    }
+}
+
+/// Post the URL-encoded `form` to `endpoint` on behalf of `credential`.
+///
+/// Returns the token, or the response if the endpoint rejected the request so
+/// that the caller can handle grant-specific errors.  Transport failures and
+/// malformed tokens are returned as errors.
+pub async fn request_token(
+    credential: &str,
+    endpoint: &Url,
+    form: String,
+    client_options: &ClientOptions,
+) -> azure_core::Result<Result<AccessToken, ErrorResponse>> {
+    let pipeline = Pipeline::new(None, None, client_options.clone(), vec![], vec![], None);
+    let mut request = Request::new(endpoint.clone(), Method::Post);
+    request.insert_header(
+        headers::CONTENT_TYPE,
+        content_type::APPLICATION_X_WWW_FORM_URLENCODED,
+    );
+    request.set_body(form);
+
+    let response = pipeline
+        .send(
+            &Context::new(),
+            &mut request,
+            Some(PipelineSendOptions {
+                skip_checks: true,
+                ..PipelineSendOptions::default()
+            }),
+        )
+        .await
+        .map_err(|error| {
+            Error::with_error(
+                ErrorKind::Credential,
+                error,
+                format!("{credential} authentication failed"),
+            )
+        })?;
+    let status = response.status();
+    let body = response.into_body();
+    if !status.is_success() {
+        return Ok(Err(ErrorResponse { status, body }));
+    }
+
+    let token: TokenResponse = from_json(&body)?;
+    Ok(Ok(AccessToken {
+        token: token.access_token,
+        expires_on: convert_expires_in(token.expires_in),
+    }))
+}
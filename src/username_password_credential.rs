@@ -0,0 +1,316 @@
+//! Authenticate a user with a username and password.
+//!
+//! This uses the OAuth 2.0 resource owner password credentials (ROPC) grant,
+//! which is only suitable for legacy automation and test accounts.  It does
+//! not support multi-factor authentication, personal Microsoft accounts or
+//! interactive consent.  Those failures are reported as a
+//! [`UsernamePasswordError`] with a distinct [`UsernamePasswordErrorKind`],
+//! which can be retrieved with [`azure_core::Error::downcast_ref`].
+
+use crate::{authority_host::AuthorityHost, cache::TokenCache, token_endpoint};
+use azure_core::{
+    credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::{ClientOptions, Url},
+};
+use serde::Deserialize;
+use std::{fmt, sync::Arc};
+use url::form_urlencoded;
+
+/// Microsoft Entra error codes indicating that multi-factor authentication
+/// or another strong authentication step is required.
+const MFA_REQUIRED_ERROR_CODES: [i64; 3] = [50076, 50079, 50158];
+/// The Microsoft Entra error code for missing user or administrator consent.
+const CONSENT_REQUIRED_ERROR_CODE: i64 = 65001;
+
+/// Options for constructing a [`UsernamePasswordCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct UsernamePasswordCredentialOptions {
+    /// Options for the HTTP pipeline used to request tokens.
+    pub client_options: ClientOptions,
+    /// Defaults to `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+}
+
+/// Why a username and password could not be redeemed for a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UsernamePasswordErrorKind {
+    /// The account requires multi-factor authentication, which this grant
+    /// cannot perform.
+    MfaRequired,
+    /// The user or an administrator has not consented to the application.
+    ConsentRequired,
+    /// Any other error returned by the token endpoint.
+    Other,
+}
+
+// cspell:ignore suberror
+
+/// An error response from the token endpoint.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UsernamePasswordError {
+    #[serde(skip, default = "other")]
+    kind: UsernamePasswordErrorKind,
+    error: String,
+    #[serde(default)]
+    error_description: String,
+    #[serde(default)]
+    error_codes: Vec<i64>,
+    suberror: Option<String>,
+}
+
+const fn other() -> UsernamePasswordErrorKind {
+    UsernamePasswordErrorKind::Other
+}
+
+impl UsernamePasswordError {
+    fn classify(mut self) -> Self {
+        self.kind = if self.error_codes.contains(&CONSENT_REQUIRED_ERROR_CODE)
+            || self.suberror.as_deref() == Some("consent_required")
+        {
+            UsernamePasswordErrorKind::ConsentRequired
+        } else if self
+            .error_codes
+            .iter()
+            .any(|code| MFA_REQUIRED_ERROR_CODES.contains(code))
+            || self.error == "interaction_required"
+        {
+            UsernamePasswordErrorKind::MfaRequired
+        } else {
+            UsernamePasswordErrorKind::Other
+        };
+        self
+    }
+
+    /// Why the request failed.
+    #[must_use]
+    pub const fn kind(&self) -> UsernamePasswordErrorKind {
+        self.kind
+    }
+
+    /// The OAuth error name, such as `invalid_grant`.
+    #[must_use]
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+
+    /// The Microsoft Entra error codes, such as `50076`.
+    #[must_use]
+    pub fn error_codes(&self) -> &[i64] {
+        &self.error_codes
+    }
+}
+
+impl std::error::Error for UsernamePasswordError {}
+
+impl fmt::Display for UsernamePasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            UsernamePasswordErrorKind::MfaRequired => write!(
+                f,
+                "multi-factor authentication is required, which username and password authentication does not support. Use an interactive credential such as DeviceCodeCredential instead. "
+            )?,
+            UsernamePasswordErrorKind::ConsentRequired => write!(
+                f,
+                "the user or an administrator must consent to the application before it can request tokens. "
+            )?,
+            UsernamePasswordErrorKind::Other => {}
+        }
+        write!(f, "{}: {}", self.error, self.error_description)
+    }
+}
+
+#[derive(Debug)]
+/// Enables authentication to Azure Active Directory using a username and password.
+pub struct UsernamePasswordCredential {
+    client_id: String,
+    username: String,
+    password: Secret,
+    endpoint: Url,
+    client_options: ClientOptions,
+    cache: TokenCache,
+}
+
+impl UsernamePasswordCredential {
+    /// Create a new `UsernamePasswordCredential`.
+    pub fn new<T, C, U>(
+        tenant_id: T,
+        client_id: C,
+        username: U,
+        password: Secret,
+        options: Option<UsernamePasswordCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>>
+    where
+        T: AsRef<str>,
+        C: Into<String>,
+        U: Into<String>,
+    {
+        let UsernamePasswordCredentialOptions {
+            client_options,
+            authority_host,
+        } = options.unwrap_or_default();
+        let endpoint =
+            AuthorityHost::or_from_env(authority_host)?.endpoint(tenant_id.as_ref(), "token")?;
+
+        Ok(Arc::new(Self {
+            client_id: client_id.into(),
+            username: username.into(),
+            password,
+            endpoint,
+            client_options,
+            cache: TokenCache::new(),
+        }))
+    }
+
+    async fn get_access_token(
+        &self,
+        scopes: &[&str],
+        _options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("client_id", &self.client_id)
+            .append_pair("scope", &scopes.join(" "))
+            .append_pair("grant_type", "password")
+            .append_pair("username", &self.username)
+            .append_pair("password", self.password.secret())
+            .append_pair("client_info", "1")
+            .finish();
+
+        token_endpoint::request_token(
+            "UsernamePasswordCredential",
+            &self.endpoint,
+            body,
+            &self.client_options,
+        )
+        .await?
+        .map_err(|response| {
+            response.parse::<UsernamePasswordError>().map_or_else(
+                || {
+                    Error::with_message(
+                        ErrorKind::Credential,
+                        format!(
+                            "UsernamePasswordCredential authentication failed with status {}",
+                            response.status
+                        ),
+                    )
+                },
+                |error| Error::new(ErrorKind::Credential, error.classify()),
+            )
+        })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for UsernamePasswordCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.cache
+            .get_token(scopes, options, |s, o| self.get_access_token(s, o))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockPolicy, TOKEN_BODY};
+    use azure_core::http::StatusCode;
+
+    fn credential(policy: &Arc<MockPolicy>) -> azure_core::Result<Arc<UsernamePasswordCredential>> {
+        UsernamePasswordCredential::new(
+            "fake-tenant",
+            "fake-client",
+            "user@example.com",
+            Secret::new("fake-password"),
+            Some(UsernamePasswordCredentialOptions {
+                client_options: policy.client_options(),
+                ..UsernamePasswordCredentialOptions::default()
+            }),
+        )
+    }
+
+    async fn error_kind(body: &'static str) -> azure_core::Result<UsernamePasswordErrorKind> {
+        let policy =
+            MockPolicy::with_status(vec![("/oauth2/v2.0/token", StatusCode::BadRequest, body)]);
+        let Err(error) = credential(&policy)?.get_token(&["fake-scope"], None).await else {
+            return Err(Error::with_message(ErrorKind::Other, "expected an error"));
+        };
+
+        assert!(matches!(error.kind(), ErrorKind::Credential));
+        error
+            .downcast_ref::<UsernamePasswordError>()
+            .map(UsernamePasswordError::kind)
+            .ok_or_else(|| Error::with_message(ErrorKind::Other, "not a UsernamePasswordError"))
+    }
+
+    #[tokio::test]
+    async fn requests_and_caches_token() -> azure_core::Result<()> {
+        let policy = MockPolicy::new(vec![("/oauth2/v2.0/token", TOKEN_BODY)]);
+        let credential = credential(&policy)?;
+
+        let token = credential.get_token(&["fake-scope"], None).await?;
+        credential.get_token(&["fake-scope"], None).await?;
+
+        assert_eq!(token.token.secret(), "fake-token");
+        assert_eq!(
+            policy.requests(),
+            vec!["https://login.microsoftonline.com/fake-tenant/oauth2/v2.0/token"]
+        );
+        let bodies = policy.bodies();
+        let body = bodies.first().map(String::as_str).unwrap_or_default();
+        let form: Vec<(String, String)> = form_urlencoded::parse(body.as_bytes())
+            .into_owned()
+            .collect();
+        for (name, value) in [
+            ("grant_type", "password"),
+            ("client_id", "fake-client"),
+            ("username", "user@example.com"),
+            ("password", "fake-password"),
+            ("scope", "fake-scope"),
+        ] {
+            assert!(
+                form.contains(&(name.to_owned(), value.to_owned())),
+                "{name}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn mfa_required_is_reported() -> azure_core::Result<()> {
+        let kind = error_kind(
+            r#"{"error":"invalid_grant","error_description":"AADSTS50076: Due to a configuration change made by your administrator, you must use multi-factor authentication.","error_codes":[50076],"suberror":"basic_action"}"#,
+        )
+        .await?;
+
+        assert_eq!(kind, UsernamePasswordErrorKind::MfaRequired);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn consent_required_is_reported() -> azure_core::Result<()> {
+        let kind = error_kind(
+            r#"{"error":"invalid_grant","error_description":"AADSTS65001: The user or administrator has not consented to use the application.","error_codes":[65001],"suberror":"consent_required"}"#,
+        )
+        .await?;
+
+        assert_eq!(kind, UsernamePasswordErrorKind::ConsentRequired);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn other_errors_are_reported() -> azure_core::Result<()> {
+        let kind = error_kind(
+            r#"{"error":"invalid_grant","error_description":"AADSTS50126: Error validating credentials due to invalid username or password.","error_codes":[50126]}"#,
+        )
+        .await?;
+
+        assert_eq!(kind, UsernamePasswordErrorKind::Other);
+        Ok(())
+    }
+}
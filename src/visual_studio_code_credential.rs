@@ -0,0 +1,584 @@
+//! Authenticate using the account signed in to the Visual Studio Code Azure
+//! extensions.
+//!
+//! The credential reads the `azure.tenant` and `azure.cloud` settings from the
+//! VS Code user `settings.json`, the authentication record written by the
+//! Azure Resources extension, and a refresh token for that account from the
+//! MSAL token cache stored alongside the record.  Tokens are then acquired
+//! silently through [`exchange_with_options`] from the authority host of the
+//! configured cloud.
+
+use crate::{
+    authority_host::AuthorityHost,
+    cache::TokenCache,
+    devicecode_credentials::convert_expires_in,
+    msal_cache::MsalCache,
+    refresh_token::{ExchangeOptions, exchange_with_options},
+};
+use async_lock::Mutex;
+use azure_core::{
+    credentials::{AccessToken, Secret, TokenCredential, TokenRequestOptions},
+    error::{Error, ErrorKind},
+    http::ClientOptions,
+    json::from_json,
+};
+use serde::Deserialize;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// The client ID used by the Visual Studio Code Azure extensions.
+const VSCODE_CLIENT_ID: &str = "aebc6443-996d-45c2-90f0-388ff96faa56";
+const AUTH_RECORD_DIR: [&str; 2] = [".azure", "ms-azuretools.vscode-azureresourcegroups"];
+const AUTH_RECORD_FILE: &str = "authRecord.json";
+const TOKEN_CACHE_FILE: &str = "msal.cache";
+
+/// Options for constructing a [`VisualStudioCodeCredential`].
+#[derive(Debug, Clone, Default)]
+pub struct VisualStudioCodeCredentialOptions {
+    /// Identifies the tenant the credential should authenticate in.
+    ///
+    /// Defaults to the `azure.tenant` setting, then to the tenant of the
+    /// signed in account.
+    pub tenant_id: Option<String>,
+    /// Path of the VS Code user `settings.json`.
+    pub settings_path: Option<PathBuf>,
+    /// Path of the authentication record written by the Azure extensions.
+    ///
+    /// Defaults to `~/.azure/ms-azuretools.vscode-azureresourcegroups/authRecord.json`.
+    pub auth_record_path: Option<PathBuf>,
+    /// Path of the MSAL token cache holding the account's refresh token.
+    ///
+    /// Defaults to `msal.cache` in the directory of the authentication record.
+    pub token_cache_path: Option<PathBuf>,
+    /// Options for the HTTP pipeline used to redeem the refresh token.
+    pub client_options: ClientOptions,
+    /// Defaults to the cloud selected by the `azure.cloud` setting, then to
+    /// `AZURE_AUTHORITY_HOST`, then to the public cloud.
+    pub authority_host: Option<AuthorityHost>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct VisualStudioCodeSettings {
+    #[serde(rename = "azure.tenant", default)]
+    tenant: Option<String>,
+    #[serde(rename = "azure.cloud", default)]
+    cloud: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthenticationRecord {
+    home_account_id: String,
+    tenant_id: String,
+    #[serde(rename = "clientId", default)]
+    client: Option<String>,
+}
+
+/// The signed in VS Code account that tokens are acquired for.
+#[derive(Debug)]
+struct VisualStudioCodeSession {
+    home_account_id: String,
+    tenant_id: String,
+    client_id: String,
+    refresh_token: Secret,
+    authority_host: AuthorityHost,
+}
+
+#[derive(Debug)]
+/// Enables authentication to Azure Active Directory using the account signed
+/// in to the Visual Studio Code Azure extensions.
+pub struct VisualStudioCodeCredential {
+    tenant_id: Option<String>,
+    settings_path: Option<PathBuf>,
+    auth_record_path: Option<PathBuf>,
+    token_cache_path: Option<PathBuf>,
+    client_options: ClientOptions,
+    authority_host: Option<AuthorityHost>,
+    cache: TokenCache,
+    refresh_token: Mutex<Option<(String, Secret)>>,
+}
+
+impl VisualStudioCodeCredential {
+    /// Create a new `VisualStudioCodeCredential`.
+    pub fn new(
+        options: Option<VisualStudioCodeCredentialOptions>,
+    ) -> azure_core::Result<Arc<Self>> {
+        let VisualStudioCodeCredentialOptions {
+            tenant_id,
+            settings_path,
+            auth_record_path,
+            token_cache_path,
+            client_options,
+            authority_host,
+        } = options.unwrap_or_default();
+        let auth_record_path = auth_record_path.or_else(default_auth_record_path);
+        let token_cache_path = token_cache_path.or_else(|| {
+            auth_record_path
+                .as_deref()
+                .and_then(Path::parent)
+                .map(|dir| dir.join(TOKEN_CACHE_FILE))
+        });
+
+        Ok(Arc::new(Self {
+            tenant_id,
+            settings_path: settings_path.or_else(default_settings_path),
+            auth_record_path,
+            token_cache_path,
+            client_options,
+            authority_host,
+            cache: TokenCache::new(),
+            refresh_token: Mutex::new(None),
+        }))
+    }
+
+    fn load_session(&self) -> azure_core::Result<VisualStudioCodeSession> {
+        let settings = match &self.settings_path {
+            Some(path) if path.exists() => read_settings(path)?,
+            _ => VisualStudioCodeSettings::default(),
+        };
+        let authority_host = match (&self.authority_host, settings.cloud.as_deref()) {
+            (Some(authority_host), _) => authority_host.clone(),
+            (None, Some(cloud)) => AuthorityHost::from_cloud_name(cloud).ok_or_else(|| {
+                Error::with_message(
+                    ErrorKind::Credential,
+                    format!("VS Code Azure cloud '{cloud}' is not supported"),
+                )
+            })?,
+            (None, None) => AuthorityHost::from_env()?,
+        };
+
+        let auth_record_path = self
+            .auth_record_path
+            .as_deref()
+            .filter(|path| path.exists())
+            .ok_or_else(no_session)?;
+        let record: AuthenticationRecord = from_json(read_file(auth_record_path)?)?;
+        let client_id = record.client.unwrap_or_else(|| VSCODE_CLIENT_ID.to_owned());
+
+        let token_cache_path = self
+            .token_cache_path
+            .as_deref()
+            .filter(|path| path.exists())
+            .ok_or_else(no_session)?;
+        let refresh_token = MsalCache::from_file(token_cache_path)?
+            .refresh_token(&authority_host, &record.home_account_id, &client_id)
+            .ok_or_else(no_session)?
+            .secret()
+            .clone();
+
+        let tenant_id = self
+            .tenant_id
+            .clone()
+            .or_else(|| settings.tenant.filter(|tenant| !tenant.is_empty()))
+            .unwrap_or(record.tenant_id);
+
+        Ok(VisualStudioCodeSession {
+            home_account_id: record.home_account_id,
+            tenant_id,
+            client_id,
+            refresh_token,
+            authority_host,
+        })
+    }
+
+    async fn get_access_token(
+        &self,
+        scopes: &[&str],
+        _options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        let session = self.load_session()?;
+        let mut refresh_token = self.refresh_token.lock().await;
+        let current = match refresh_token.as_ref() {
+            Some((home_account_id, token)) if *home_account_id == session.home_account_id => {
+                token.clone()
+            }
+            _ => session.refresh_token,
+        };
+
+        let response = exchange_with_options(
+            &session.tenant_id,
+            &session.client_id,
+            None,
+            &current,
+            ExchangeOptions {
+                scopes: scopes.iter().map(ToString::to_string).collect(),
+                client_options: self.client_options.clone(),
+                authority_host: Some(session.authority_host),
+            },
+        )
+        .await?;
+        *refresh_token = Some((session.home_account_id, response.refresh_token().clone()));
+
+        Ok(AccessToken {
+            token: response.access_token().clone(),
+            expires_on: convert_expires_in(response.expires_in()),
+        })
+    }
+}
+
+#[cfg_attr(target_arch = "wasm32", async_trait::async_trait(?Send))]
+#[cfg_attr(not(target_arch = "wasm32"), async_trait::async_trait)]
+impl TokenCredential for VisualStudioCodeCredential {
+    async fn get_token(
+        &self,
+        scopes: &[&str],
+        options: Option<TokenRequestOptions<'_>>,
+    ) -> azure_core::Result<AccessToken> {
+        self.cache
+            .get_token(scopes, options, |s, o| self.get_access_token(s, o))
+            .await
+    }
+}
+
+fn no_session() -> Error {
+    Error::with_message(
+        ErrorKind::Credential,
+        "no VS Code Azure account session was found. Sign in with the 'Azure: Sign In' command in VS Code",
+    )
+}
+
+fn read_file(path: &Path) -> azure_core::Result<Vec<u8>> {
+    fs::read(path).map_err(|error| {
+        Error::with_error(
+            ErrorKind::Io,
+            error,
+            format!("unable to read {}", path.display()),
+        )
+    })
+}
+
+fn read_settings(path: &Path) -> azure_core::Result<VisualStudioCodeSettings> {
+    let contents = String::from_utf8_lossy(&read_file(path)?).into_owned();
+    from_json(strip_json_comments(&contents))
+}
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    #[cfg(windows)]
+    let home = env::var_os("USERPROFILE");
+    #[cfg(not(windows))]
+    let home = env::var_os("HOME");
+    home.filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+pub(crate) fn default_auth_record_path() -> Option<PathBuf> {
+    let mut path = home_dir()?;
+    path.extend(AUTH_RECORD_DIR);
+    path.push(AUTH_RECORD_FILE);
+    Some(path)
+}
+
+fn default_settings_path() -> Option<PathBuf> {
+    #[cfg(windows)]
+    let config = env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(target_os = "macos")]
+    let config = home_dir().map(|home| home.join("Library").join("Application Support"));
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .filter(|config| !config.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".config")));
+
+    config.map(|config| config.join("Code").join("User").join("settings.json"))
+}
+
+/// Remove the comments and trailing commas VS Code allows in `settings.json`.
+fn strip_json_comments(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            match c {
+                '\\' => output.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                in_string = true;
+                output.push(c);
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        output.push(c);
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = None;
+                for c in chars.by_ref() {
+                    if previous == Some('*') && c == '/' {
+                        break;
+                    }
+                    previous = Some(c);
+                }
+            }
+            '}' | ']' => {
+                let trimmed = output.trim_end().len();
+                if output[..trimmed].ends_with(',') {
+                    output.truncate(trimmed - 1);
+                }
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{MockPolicy, TempDir};
+    use azure_core::http::Url;
+
+    const HOME_ACCOUNT_ID: &str = "fake-object.fake-home-tenant";
+
+    fn auth_record() -> String {
+        format!(
+            r#"{{
+                "username": "user@contoso.com",
+                "authority": "login.microsoftonline.com",
+                "homeAccountId": "{HOME_ACCOUNT_ID}",
+                "tenantId": "fake-home-tenant",
+                "clientId": "{VSCODE_CLIENT_ID}",
+                "datetime": "2026-01-01T00:00:00.000Z",
+                "version": "1.0"
+            }}"#
+        )
+    }
+
+    fn token_cache(client_id: &str, family_id: Option<&str>) -> String {
+        let family_id = family_id
+            .map(|family_id| format!(r#", "family_id": "{family_id}""#))
+            .unwrap_or_default();
+        format!(
+            r#"{{
+                "Account": {{}},
+                "RefreshToken": {{
+                    "{HOME_ACCOUNT_ID}-login.microsoftonline.com-refreshtoken-{client_id}--": {{
+                        "home_account_id": "{HOME_ACCOUNT_ID}",
+                        "environment": "login.microsoftonline.com",
+                        "credential_type": "RefreshToken",
+                        "client_id": "{client_id}",
+                        "secret": "fake-refresh-token"{family_id}
+                    }}
+                }}
+            }}"#
+        )
+    }
+
+    fn credential(
+        dir: &TempDir,
+        settings: Option<&str>,
+    ) -> azure_core::Result<Arc<VisualStudioCodeCredential>> {
+        let settings_path = settings
+            .map(|settings| dir.write("settings.json", settings))
+            .transpose()?;
+        VisualStudioCodeCredential::new(Some(VisualStudioCodeCredentialOptions {
+            tenant_id: None,
+            settings_path: Some(settings_path.unwrap_or_else(|| dir.path.join("settings.json"))),
+            auth_record_path: Some(dir.path.join(AUTH_RECORD_FILE)),
+            token_cache_path: None,
+            ..VisualStudioCodeCredentialOptions::default()
+        }))
+    }
+
+    #[test]
+    fn loads_session_from_fixtures() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        dir.write(AUTH_RECORD_FILE, &auth_record())?;
+        dir.write(TOKEN_CACHE_FILE, &token_cache(VSCODE_CLIENT_ID, None))?;
+        let credential = credential(
+            &dir,
+            Some(
+                r#"{
+                    // pinned by the user
+                    "azure.tenant": "fake-settings-tenant",
+                    /* the public cloud */
+                    "azure.cloud": "AzureCloud",
+                    "editor.fontSize": 14,
+                }"#,
+            ),
+        )?;
+
+        let session = credential.load_session()?;
+
+        assert_eq!(session.tenant_id, "fake-settings-tenant");
+        assert_eq!(session.client_id, VSCODE_CLIENT_ID);
+        assert_eq!(session.refresh_token.secret(), "fake-refresh-token");
+        Ok(())
+    }
+
+    #[test]
+    fn tenant_defaults_to_account_tenant() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        dir.write(AUTH_RECORD_FILE, &auth_record())?;
+        dir.write(TOKEN_CACHE_FILE, &token_cache(VSCODE_CLIENT_ID, None))?;
+
+        let session = credential(&dir, None)?.load_session()?;
+
+        assert_eq!(session.tenant_id, "fake-home-tenant");
+        Ok(())
+    }
+
+    #[test]
+    fn uses_family_refresh_token() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        dir.write(AUTH_RECORD_FILE, &auth_record())?;
+        dir.write(TOKEN_CACHE_FILE, &token_cache("other-client", Some("1")))?;
+
+        let session = credential(&dir, None)?.load_session()?;
+
+        assert_eq!(session.refresh_token.secret(), "fake-refresh-token");
+        Ok(())
+    }
+
+    #[test]
+    fn sovereign_cloud_selects_authority_host() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        dir.write(AUTH_RECORD_FILE, &auth_record())?;
+        dir.write(
+            TOKEN_CACHE_FILE,
+            &token_cache(VSCODE_CLIENT_ID, None)
+                .replace("login.microsoftonline.com", "login.chinacloudapi.cn"),
+        )?;
+
+        let session =
+            credential(&dir, Some(r#"{"azure.cloud": "AzureChinaCloud"}"#))?.load_session()?;
+
+        assert_eq!(session.authority_host, AuthorityHost::China);
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_cloud_is_unavailable() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        dir.write(AUTH_RECORD_FILE, &auth_record())?;
+        dir.write(TOKEN_CACHE_FILE, &token_cache(VSCODE_CLIENT_ID, None))?;
+
+        let result = credential(&dir, Some(r#"{"azure.cloud": "SomeOtherCloud"}"#))?.load_session();
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn redeems_refresh_token_at_authority_host() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        dir.write(AUTH_RECORD_FILE, &auth_record())?;
+        dir.write(
+            TOKEN_CACHE_FILE,
+            &token_cache(VSCODE_CLIENT_ID, None)
+                .replace("login.microsoftonline.com", "login.example.com"),
+        )?;
+        let policy = MockPolicy::new(vec![(
+            "/oauth2/v2.0/token",
+            r#"{"token_type":"Bearer","scope":"https://management.azure.com/.default","expires_in":3600,"ext_expires_in":3600,"access_token":"vscode-token","refresh_token":"rotated"}"#,
+        )]);
+        let credential =
+            VisualStudioCodeCredential::new(Some(VisualStudioCodeCredentialOptions {
+                settings_path: Some(dir.path.join("settings.json")),
+                auth_record_path: Some(dir.path.join(AUTH_RECORD_FILE)),
+                client_options: policy.client_options(),
+                authority_host: Some(AuthorityHost::Custom(Url::parse(
+                    "https://login.example.com",
+                )?)),
+                ..VisualStudioCodeCredentialOptions::default()
+            }))?;
+
+        let token = credential
+            .get_token(&["https://management.azure.com/.default"], None)
+            .await?;
+        credential
+            .get_token(&["https://storage.azure.com/.default"], None)
+            .await?;
+
+        assert_eq!(token.token.secret(), "vscode-token");
+        assert_eq!(
+            policy.requests(),
+            vec![
+                "https://login.example.com/fake-home-tenant/oauth2/v2.0/token",
+                "https://login.example.com/fake-home-tenant/oauth2/v2.0/token",
+            ]
+        );
+        let bodies = policy.bodies();
+        assert!(
+            bodies
+                .iter()
+                .all(|body| body.contains("grant_type=refresh_token"))
+        );
+        // The second request redeems the refresh token rotated by the first.
+        assert!(
+            bodies
+                .first()
+                .is_some_and(|body| body.contains("refresh_token=fake-refresh-token"))
+        );
+        assert!(
+            bodies
+                .get(1)
+                .is_some_and(|body| body.contains("refresh_token=rotated"))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn missing_session_is_unavailable() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+
+        let result = credential(&dir, None)?
+            .get_token(&["https://management.azure.com/.default"], None)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn missing_refresh_token_is_unavailable() -> azure_core::Result<()> {
+        let dir = TempDir::new()?;
+        dir.write(AUTH_RECORD_FILE, &auth_record())?;
+        dir.write(TOKEN_CACHE_FILE, &token_cache("other-client", None))?;
+
+        let result = credential(&dir, None)?
+            .get_token(&["https://management.azure.com/.default"], None)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ref error) if matches!(error.kind(), ErrorKind::Credential)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn strip_json_comments_keeps_strings() {
+        let stripped = strip_json_comments(
+            r#"{ "url": "https://example.com/*x*/", // trailing
+                 "list": [1, 2,], }"#,
+        );
+
+        assert_eq!(
+            stripped,
+            "{ \"url\": \"https://example.com/*x*/\", \n                 \"list\": [1, 2]}"
+        );
+    }
+}